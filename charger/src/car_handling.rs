@@ -1,13 +1,23 @@
 use bytes::Bytes;
 use tracing::{debug, info, trace};
-use powercable::{charger::{Get, ChargeAccept, ChargeOffer, ChargeRequest}, CHARGER_OFFER, CHARGER_CHARGING_ACK};
+use powercable::{
+    charger::{CancelReason, ChargeAccept, ChargeCancel, ChargeEta, ChargeOffer, ChargeReceipt, ChargeReject, ChargeRequest, Get, RejectReason},
+    routing::road_distance,
+    CHARGER_OFFER, CHARGER_CHARGING_ACK, CHARGER_RECEIPT, CHARGER_REJECT,
+};
 use rumqttc::QoS;
 
-use crate::{offer_handling::ReservedOffer, SharedCharger};
+use crate::{offer_handling::ReservedOffer, ChargerHandler, SharedCharger};
 
 /// # Description
-/// Every charge request is answered with a charge offer, if the charger has at least one free port.<br>
-/// The charger reserves the requested charge amount and sends a `ChargeOffer` to the vehicle.
+/// Every charge request is answered with a charge offer, if the charger has a free port with a matching connector and charge left.<br>
+/// The charger reserves the requested charge amount and sends a `ChargeOffer` to the vehicle.<br>
+/// An offer that the vehicle did not accept yet is replaced, so repeated requests don't block more ports.<br>
+/// Otherwise the vehicle is put on the waitlist and gets a `ChargeReject` with the estimated waiting time.
+/// Vehicles that are already waiting are served first.
+/// Requests of vehicles that can't plug into any of our ports, or that won't pay our price, are ignored.
+/// So are all requests while our price doesn't cover what we paid for the energy.
+/// While a charge point operator manages the charger, it routes the requests and this one is ignored.
 /// 
 /// # Arguments
 /// - `charger`: The shared charger handler containing the charger and its state.
@@ -18,23 +28,108 @@ pub async fn receive_request(charger: SharedCharger, payload: Bytes) {
     let charge_request= ChargeRequest::from_bytes(payload).unwrap();
     debug!("Charge request: {:?}", charge_request);
 
-    if handler.charger.get_free_ports() == 0 {
-        info!("Charger {} has no free ports, rejecting request from {}", handler.charger.get_name(), charge_request.vehicle_name);
+    if handler.is_managed() {
+        trace!("Our operator routes the request of {}", charge_request.vehicle_name);
         return;
     }
+    handle_request(&mut handler, charge_request).await;
+}
+
+/// # Description
+/// Answers a charge request with an offer or a reject, see `receive_request`.<br>
+/// Also used for requests our charge point operator routed to us.
+///
+/// # Arguments
+/// - `handler`: The charger handler containing the charger and its state.
+/// - `charge_request`: The charge request of the vehicle.
+pub async fn handle_request(handler: &mut ChargerHandler, charge_request: ChargeRequest) {
+    if handler.get_reserved_offer(charge_request.vehicle_name.clone()).is_some_and(|o| !o.was_accepted()) {
+        debug!("Replacing the previous offer for {}", charge_request.vehicle_name);
+        handler.release_offer(charge_request.vehicle_name.clone(), true);
+    }
+
+    if !handler.charger.has_compatible_port(&charge_request.connectors) {
+        debug!("{} can't charge at any of our ports", charge_request.vehicle_name);
+        return;
+    }
+
+    serve_waitlist(handler).await;
+    if !handler.sells_profitably() {
+        debug!(
+            "Not answering {}, our price of {:.3}€ per kWh doesn't cover what we paid for the energy",
+            charge_request.vehicle_name, handler.sell_price()
+        );
+        return;
+    }
+    if charge_request.affordable_charge(handler.sell_price()).is_none() {
+        debug!(
+            "{} won't pay {:.3}€ per kWh, it pays at most {:.3}€ and {:.2}€ in total",
+            charge_request.vehicle_name, handler.sell_price(), charge_request.max_price, charge_request.budget
+        );
+        return;
+    }
+
+    let port = handler.find_port(&charge_request);
+    match port {
+        Some(port) if handler.charger.get_available_charge() > 0 => {
+            send_offer(handler, charge_request, port).await;
+        }
+        _ => reject_request(handler, charge_request).await,
+    }
+}
 
-    let energy_for_way = (charge_request.vehicle_position.distance_to(handler.charger.get_position()) * (charge_request.vehicle_consumption/ 100.0)) as usize;// km * kWh/km = kWh
+/// # Description
+/// Offers the free ports to the vehicles on the waitlist, in the order they were put on it.<br>
+/// Vehicles that can't use any of the free ports, or that won't pay our current price, keep their position.
+/// Nobody is served while our price doesn't cover what we paid for the energy.
+///
+/// # Arguments
+/// - `handler`: The charger handler containing the charger and its state.
+pub async fn serve_waitlist(handler: &mut ChargerHandler) {
+    let mut i = 0;
+    while i < handler.waitlist.len() && handler.charger.get_available_charge() > 0 && handler.sells_profitably() {
+        let request = &handler.waitlist[i];
+        match handler.find_port(request) {
+            Some(port) if request.affordable_charge(handler.sell_price()).is_some() => {
+                let charge_request = handler.waitlist.remove(i).unwrap();
+                info!("Offering a free port to {} from the waitlist", charge_request.vehicle_name);
+                send_offer(handler, charge_request, port).await;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+/// # Description
+/// Reserves a port and the requested charge for a vehicle and sends it a `ChargeOffer`.<br>
+/// The offer states the power the vehicle will be charged with at the port.
+/// It is for no more charge than the vehicle can afford with its budget.
+///
+/// # Arguments
+/// - `handler`: The charger handler containing the charger and its state.
+/// - `charge_request`: The charge request of the vehicle.
+/// - `port`: The index of the free port that is reserved for the vehicle.
+async fn send_offer(handler: &mut ChargerHandler, charge_request: ChargeRequest, port: usize) {
+    let price = handler.sell_price();
+    let distance = road_distance(charge_request.vehicle_position, handler.charger.get_position());
+    let energy_for_way = (distance * (charge_request.vehicle_consumption/ 100.0)) as usize;// km * kWh/km = kWh
     let charge_amount = charge_request.charge_amount + energy_for_way;// including the energy for the way
     debug!("Vehicle {} wants {} kWh, but needs {} kWh for the way, so we need to reserve {} kWh", 
         charge_request.vehicle_name, charge_request.charge_amount, energy_for_way, charge_amount);
+    let charge_amount = charge_amount.min(charge_request.affordable_charge(price).unwrap_or(0));
     let reservable_charge = charge_amount.min(handler.charger.get_available_charge());// cant reserve more than the charger has
     debug!("Vehicle {} requests {} kWh and we can reserve {} kWh", charge_request.vehicle_name, charge_amount, reservable_charge);
+    // The rest of the request has to be bought first, as fast as the grid connection allows
+    let missing_charge = charge_amount - reservable_charge;
+    let estimated_wait_ticks = missing_charge.div_ceil(handler.charger.grid_energy_per_tick().max(1)) as u64;
 
     
     // ReserveOffer for own system
+    let terms = handler.reservation_policy.terms_for(distance);
     let offer = ReservedOffer::new(
         charge_request.vehicle_name.clone(),
-        reservable_charge, handler.charger.get_current_price());
+        reservable_charge, price,
+        port, terms, handler.current_tick);
     debug!("Creating reserved offer: {:?}", offer);
     handler.reserve_offer(offer);
 
@@ -42,9 +137,13 @@ pub async fn receive_request(charger: SharedCharger, payload: Bytes) {
     let offer = ChargeOffer::new(
         handler.charger.get_name().clone(),
         charge_request.vehicle_name.clone(),// TODO: why no gray name, like other fields have?
-        handler.charger.get_current_price(),
+        price,
         reservable_charge,
         handler.charger.get_position(),
+        terms,
+        handler.charger.get_port(port).get_connector(),
+        handler.charger.charge_power(port, |c| charge_request.power_for(c)),
+        estimated_wait_ticks,
     );
     debug!("Creating charge offer: {:?}", offer);
 
@@ -59,11 +158,46 @@ pub async fn receive_request(charger: SharedCharger, payload: Bytes) {
 }
 
 
+/// # Description
+/// Puts a vehicle on the waitlist and tells it why and for how long it has to wait.
+///
+/// # Arguments
+/// - `handler`: The charger handler containing the charger and its state.
+/// - `charge_request`: The charge request of the vehicle.
+async fn reject_request(handler: &mut ChargerHandler, charge_request: ChargeRequest) {
+    let reason = if handler.find_port(&charge_request).is_none() {
+        RejectReason::NoFreePorts
+    } else {
+        RejectReason::NotEnoughCharge
+    };
+    let vehicle_name = charge_request.vehicle_name.clone();
+    let connectors = charge_request.connectors.clone();
+    let waitlist_position = handler.enqueue_waitlist(charge_request);
+
+    let reject = ChargeReject {
+        charger_name: handler.charger.get_name().clone(),
+        vehicle_name,
+        reason,
+        estimated_wait_ticks: handler.estimated_wait_ticks(waitlist_position, &connectors),
+        waitlist_position,
+    };
+    info!("Charger {} can't make an offer: {:?}", handler.charger.get_name(), reject);
+
+    handler.client.publish(
+        CHARGER_REJECT,
+        QoS::ExactlyOnce,
+        false,
+        reject.to_bytes(),
+    ).await.unwrap();
+    trace!("Published on topic {}: {:?}", CHARGER_REJECT, reject);
+}
+
 /// # Description
 /// Handles a charge accept message from a vehicle.<br>
 /// This function first checks if the vehicle is in the reserved offers list.
 /// If it is, it checks if the charger name matches the one in the accept message.
-/// If it does, it accepts the reservation; otherwise, it releases the reservation offer.
+/// If it does, it accepts the reservation and lends the port to other vehicles until shortly before the vehicle arrives;
+/// otherwise, it releases the reservation offer.
 /// 
 /// # Arguments
 /// - `charger`: The shared charger handler containing the charger and its state.
@@ -75,6 +209,11 @@ pub async fn accept_handler(charger: SharedCharger, payload: Bytes) {
     let charge_accept: ChargeAccept = ChargeAccept::from_bytes(payload).unwrap();
     debug!("Charge accept: {:?}", charge_accept);
 
+    // The vehicle found another charger, so it doesn't wait for us anymore
+    if &charge_accept.charger_name != handler.charger.get_name() && handler.leave_waitlist(&charge_accept.vehicle_name) {
+        debug!("{} left our waitlist for {}", charge_accept.vehicle_name, charge_accept.charger_name);
+    }
+
     // This is not something we care about
     if handler.get_reserved_offer(charge_accept.vehicle_name.clone()).is_none() {
         debug!("Received accept from {} but we didn`t reserve an offer", charge_accept.vehicle_name);
@@ -82,9 +221,26 @@ pub async fn accept_handler(charger: SharedCharger, payload: Bytes) {
         info!("We were not accepted by {}, removing from reserved list", charge_accept.vehicle_name);
         handler.release_offer(charge_accept.vehicle_name.clone(), true);
     } else {
-        info!("We were accepted by {}", charge_accept.vehicle_name);
+        info!("We were accepted by {}, it arrives in {} ticks", charge_accept.vehicle_name, charge_accept.eta_ticks);
         handler.accept_reserve(charge_accept.vehicle_name.clone());
+        handler.set_arrival(&charge_accept.vehicle_name, charge_accept.eta_ticks);
+    }
+}
+
+/// # Description
+/// Updates the estimated arrival of a vehicle that booked one of our ports, see `ChargerHandler::set_arrival`.
+///
+/// # Arguments
+/// - `charger`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the estimated arrival.
+pub async fn eta_handler(charger: SharedCharger, payload: Bytes) {
+    let eta = ChargeEta::from_bytes(payload).unwrap();
+    let mut handler = charger.lock().await;
+    if &eta.charger_name != handler.charger.get_name() {
+        return;
     }
+    trace!("Estimated arrival: {:?}", eta);
+    handler.set_arrival(&eta.vehicle_name, eta.eta_ticks);
 }
 
 /// # Description
@@ -100,7 +256,13 @@ pub async fn answer_get(charger: SharedCharger, payload: Bytes) {
     if get.charger_name.eq(handler.charger.get_name()) {
         info!("Received get request from {}", get.vehicle_name);
 
-        let amount_we_can_give = handler.charger.take_reserved_charge(get.amount);
+        let amount_we_can_give = handler.take_reserved_charge(&get.vehicle_name, get.amount);
+        let price = match handler.get_reserved_offer(get.vehicle_name.clone()) {
+            Some(offer) => offer.get_price(),
+            None => handler.sell_price(),
+        };
+        let current_tick = handler.current_tick;
+        handler.ledger.record_sale(current_tick, get.vehicle_name.clone(), amount_we_can_give as f64, price);
 
         get.amount = amount_we_can_give;
 
@@ -115,6 +277,14 @@ pub async fn answer_get(charger: SharedCharger, payload: Bytes) {
     }
 }
 
+/// # Description
+/// Handles a vehicle that is done charging and releases its port.<br>
+/// The receipt of the charging session is sent to the vehicle, which pays it from its wallet.
+/// The port and the charge that is left of the reservation are offered to the waitlist.
+///
+/// # Arguments
+/// - `charger`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the released get request.
 pub async fn release_car(charger: SharedCharger, payload: Bytes) {
     let mut handler = charger.lock().await;
 
@@ -124,6 +294,110 @@ pub async fn release_car(charger: SharedCharger, payload: Bytes) {
 
     if get.charger_name.eq(handler.charger.get_name()) {
         info!("Received release request from {}", get.vehicle_name);
-        handler.release_offer(get.vehicle_name, false);
+        send_receipt(&mut handler, &get.vehicle_name).await;
+        handler.release_offer(get.vehicle_name, true);
+        serve_waitlist(&mut handler).await;
+    }
+}
+
+impl ChargerHandler {
+    /// # Description
+    /// Sums up the session of a vehicle at our charger, from its first get request until now.<br>
+    /// Energy it fed into the grid through us is listed with what it earned for it.
+    ///
+    /// # Arguments
+    /// - `vehicle_name`: The name of the vehicle.
+    ///
+    /// # Returns
+    /// The receipt of the session, `None` if the vehicle never got or fed in any energy through us.
+    pub fn receipt(&self, vehicle_name: &str) -> Option<ChargeReceipt> {
+        let arrived_at = self.get_reserved_offer(vehicle_name.to_string()).and_then(|o| o.get_arrived_at())?;
+        let session = self.ledger.session(vehicle_name);
+        if session.energy <= 0.0 && session.fed_in <= 0.0 {
+            return None;
+        }
+
+        Some(ChargeReceipt {
+            charger_name: self.charger.get_name().clone(),
+            vehicle_name: vehicle_name.to_string(),
+            tick: self.current_tick,
+            energy: session.energy,
+            price: if session.energy > 0.0 { session.cost / session.energy } else { 0.0 },
+            cost: session.cost,
+            fed_in: session.fed_in,
+            earned: session.earned,
+            duration_ticks: self.current_tick + 1 - arrived_at,
+        })
+    }
+}
+
+/// # Description
+/// Sends the receipt of the session of a vehicle, from its first get request until now.<br>
+/// Vehicles that never got or fed in any energy through us get no receipt.
+///
+/// # Arguments
+/// - `handler`: The charger handler.
+/// - `vehicle_name`: The name of the vehicle that released its port.
+async fn send_receipt(handler: &mut ChargerHandler, vehicle_name: &str) {
+    match handler.receipt(vehicle_name) {
+        Some(receipt) => publish_receipt(handler, receipt).await,
+        None => debug!("{} released without a charging session", vehicle_name),
     }
+}
+
+/// # Description
+/// Publishes the receipt of a session, the vehicle pays it from its wallet.
+///
+/// # Arguments
+/// - `handler`: The charger handler.
+/// - `receipt`: The receipt of the session.
+pub async fn publish_receipt(handler: &mut ChargerHandler, receipt: ChargeReceipt) {
+    info!(
+        "{} charged {} kWh for {:.2}€ and fed in {} kWh for {:.2}€ in {} ticks",
+        receipt.vehicle_name, receipt.energy, receipt.cost, receipt.fed_in, receipt.earned, receipt.duration_ticks
+    );
+    handler.client.publish(
+        CHARGER_RECEIPT,
+        QoS::ExactlyOnce,
+        false,
+        receipt.to_json(),
+    ).await.unwrap();
+    trace!("Published on topic {}: {:?}", CHARGER_RECEIPT, receipt);
+}
+
+/// # Description
+/// Handles a cancellation of a reservation by a vehicle.<br>
+/// The port and the reserved charge are released immediately and offered to the waitlist.
+/// A vehicle that cancels while it is on the waitlist just leaves it.
+/// If the vehicle had already accepted the offer, the cancellation fee of the reservation terms is recorded.
+///
+/// # Arguments
+/// - `charger`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the charge cancel data.
+pub async fn cancel_handler(charger: SharedCharger, payload: Bytes) {
+    let mut handler = charger.lock().await;
+
+    let cancel = ChargeCancel::from_bytes(payload).unwrap();
+    if cancel.reason != CancelReason::Vehicle || &cancel.charger_name != handler.charger.get_name() {
+        return;
+    }
+
+    if handler.leave_waitlist(&cancel.vehicle_name) {
+        info!("{} left the waitlist", cancel.vehicle_name);
+    }
+
+    let fee = match handler.get_reserved_offer(cancel.vehicle_name.clone()) {
+        Some(offer) if offer.was_accepted() => offer.get_terms().cancellation_fee,
+        Some(_) => 0.0,
+        None => {
+            debug!("Received cancel from {} but we didn`t reserve an offer", cancel.vehicle_name);
+            return;
+        }
+    };
+
+    info!("{} cancelled its reservation, charging a cancellation fee of {}€", cancel.vehicle_name, fee);
+    let current_tick = handler.current_tick;
+    handler.ledger.record_fee(current_tick, cancel.vehicle_name.clone(), fee);
+    handler.release_offer(cancel.vehicle_name, true);
+    serve_waitlist(&mut handler).await;
 }
\ No newline at end of file
@@ -1,5 +1,67 @@
 use tracing::debug;
-use powercable::{offer::structure::OFFER_PACKAGE_SIZE, Position};
+use powercable::{charger::ConnectorType, offer::structure::OFFER_PACKAGE_SIZE, tickgen::TICK_AS_HOUR, Position};
+
+/// # Description
+/// Represents a single charging port of a charger.
+///
+/// # Fields
+/// - `connector`: The connector type of the port.
+/// - `max_power`: The maximum power of the port in kW.
+/// - `reserved`: Whether the port is currently reserved for a vehicle.
+#[derive(Debug, Clone)]
+pub struct ChargePort {
+    connector: ConnectorType,
+    max_power: f64,
+    reserved: bool,
+}
+
+impl ChargePort {
+    /// # Description
+    /// Creates a new free ChargePort.
+    ///
+    /// # Arguments
+    /// `connector`: The connector type of the port.
+    /// `max_power`: The maximum power of the port in kW.
+    pub fn new(connector: ConnectorType, max_power: f64) -> Self {
+        ChargePort {
+            connector,
+            max_power,
+            reserved: false,
+        }
+    }
+
+    pub fn get_connector(&self) -> ConnectorType {
+        self.connector
+    }
+
+    pub fn get_max_power(&self) -> f64 {
+        self.max_power
+    }
+}
+
+/// # Description
+/// The ports of a basic charging site: five 50 kW DC ports, four CCS and one CHAdeMO, sharing the power of the site.
+pub fn basic_ports() -> Vec<ChargePort> {
+    vec![
+        ChargePort::new(ConnectorType::Ccs, 50.0),
+        ChargePort::new(ConnectorType::Ccs, 50.0),
+        ChargePort::new(ConnectorType::Ccs, 50.0),
+        ChargePort::new(ConnectorType::Ccs, 50.0),
+        ChargePort::new(ConnectorType::Chademo, 50.0),
+    ]
+}
+
+/// # Description
+/// The ports of a typical charging site: two CCS fast chargers, one CHAdeMO and two AC ports.
+pub fn default_ports() -> Vec<ChargePort> {
+    vec![
+        ChargePort::new(ConnectorType::Ccs, 150.0),
+        ChargePort::new(ConnectorType::Ccs, 150.0),
+        ChargePort::new(ConnectorType::Chademo, 50.0),
+        ChargePort::new(ConnectorType::Type2, 22.0),
+        ChargePort::new(ConnectorType::Type2, 22.0),
+    ]
+}
 
 /// # Description
 /// Represents a charger in the simulation.
@@ -7,23 +69,31 @@ use powercable::{offer::structure::OFFER_PACKAGE_SIZE, Position};
 /// # Fields
 /// - `name`: The name of the charger.
 /// - `position`: The geographical position of the charger.
-/// - `rate`: The charging rate of the charger in kW/s.
-/// - `capacity`: The total capacity of the charger in kWh.
+/// - `rate`: The power limit of the whole site in kW, shared by all ports and the buffer battery.
+/// - `grid_limit`: The power limit of the grid connection in kW, for energy bought and sold on the market.
+/// - `capacity`: The total capacity of the buffer battery in kWh.
 /// - `reserved_charge`: The amount of charge reserved for future use in kWh.
-/// - `current_charge`: The current charge level of the charger in kWh.
-/// - `charging_ports`: The number of charging ports available on the charger.
-/// - `reserved_ports`: The number of charging ports currently reserved.
+/// - `current_charge`: The current charge level of the buffer battery in kWh.
+/// - `grid_charge`: Energy bought for this tick that goes to vehicles directly, the rest tops up the battery.
+/// - `grid_energy_this_tick`: The energy that went through the grid connection in the current market round in kWh.
+/// - `grid_carry`: The energy the grid connection could have taken in the last market round but didn't, up to one package.
+/// - `ports`: The charging ports of the charger.
+/// - `delivered_this_tick`: The charge handed out to vehicles in the current tick in kWh.
 /// - `visible`: Indicates whether the charger is visible in the simulation.
 #[derive(Debug, Clone)]
 pub struct Charger {
     name: String,
     position: Position,
-    rate: usize,// in kw/s
+    rate: usize,// in kW
+    grid_limit: usize,// in kW
     capacity: usize,// in kWh
     reserved_charge: usize,// in kWh
     current_charge: usize,// in kWh
-    charging_ports: usize,
-    reserved_ports: usize,
+    grid_charge: usize,// in kWh
+    grid_energy_this_tick: usize,// in kWh
+    grid_carry: f64,// in kWh
+    ports: Vec<ChargePort>,
+    delivered_this_tick: usize,// in kWh
     pub visible: bool,
 }
 
@@ -34,9 +104,10 @@ impl Charger {
     /// # Arguments
     /// `name`: The name of the charger.
     /// `position`: The geographical position of the charger.
-    /// `rate`: The charging rate of the charger in kW/s.
-    /// `capacity`: The total capacity of the charger in kWh.
-    /// `charging_ports`: The number of charging ports the charger should have.
+    /// `rate`: The power limit of the whole site in kW.
+    /// `grid_limit`: The power limit of the grid connection in kW.
+    /// `capacity`: The total capacity of the buffer battery in kWh.
+    /// `ports`: The charging ports the charger should have.
     /// 
     /// # Returns
     /// A new instance of `Charger`.
@@ -44,18 +115,23 @@ impl Charger {
         name: String,
         position: Position,
         rate: usize,
+        grid_limit: usize,
         capacity: usize,
-        charging_ports: usize,
+        ports: Vec<ChargePort>,
     ) -> Self {
         Charger {
             name,
             position,
             rate,
+            grid_limit,
             capacity,
             reserved_charge: 0,
             current_charge: 0,
-            charging_ports,
-            reserved_ports: 0,
+            grid_charge: 0,
+            grid_energy_this_tick: 0,
+            grid_carry: 0.0,
+            ports,
+            delivered_this_tick: 0,
             visible: true,
         }
     }
@@ -65,9 +141,10 @@ impl Charger {
     }
 
     pub fn get_available_charge(&self) -> usize {
-        // Calculate the available charge based on current charge and reserved charge
-        if self.current_charge >= self.reserved_charge {
-            self.current_charge - self.reserved_charge
+        // Calculate the available charge based on current charge, energy from the grid and reserved charge
+        let charge = self.current_charge + self.grid_charge;
+        if charge >= self.reserved_charge {
+            charge - self.reserved_charge
         } else {
             0 // No available charge if reserved exceeds current
         }
@@ -103,6 +180,123 @@ impl Charger {
         }
     }
 
+    /// # Description
+    /// Takes charge for a vehicle, from the energy bought for this tick first and from the buffer battery second.
+    ///
+    /// # Returns
+    /// The charge in kWh that was taken.
+    fn draw_charge(&mut self, charge: usize) -> usize {
+        let from_grid = charge.min(self.grid_charge);
+        self.grid_charge -= from_grid;
+        let from_battery = self.remove_charge(charge - from_grid) as usize;
+        debug!("Charger {} hands out {} kWh from the grid and {} kWh from its battery", self.name, from_grid, from_battery);
+        from_grid + from_battery
+    }
+
+    pub fn set_grid_limit(&mut self, grid_limit: usize) {
+        self.grid_limit = grid_limit;
+    }
+
+    /// # Description
+    /// Changes the capacity of the buffer battery, charge that doesn't fit anymore is lost.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.current_charge = self.current_charge.min(capacity);
+    }
+
+    /// # Returns
+    /// The energy in kWh that can go through the grid connection in the current tick.<br>
+    /// What was left unused in the last tick is carried over, up to one package, so a grid connection
+    /// that takes less than a package per tick still buys a whole package every few ticks.
+    pub fn grid_energy_per_tick(&self) -> usize {
+        (self.grid_limit as f64 * TICK_AS_HOUR + self.grid_carry) as usize
+    }
+
+    /// # Returns
+    /// The energy in kWh that can still go through the grid connection in the current market round.
+    pub fn grid_energy_left(&self) -> usize {
+        self.grid_energy_per_tick().saturating_sub(self.grid_energy_this_tick)
+    }
+
+    /// # Returns
+    /// Whether the grid connection can still import the given charge in kWh in the current market round.
+    pub fn can_import(&self, charge: usize) -> bool {
+        charge <= self.grid_energy_left()
+    }
+
+    /// # Returns
+    /// The average load on the grid connection in the last market round in kW.
+    pub fn get_grid_load(&self) -> f64 {
+        self.grid_energy_this_tick as f64 / TICK_AS_HOUR
+    }
+
+    /// # Description
+    /// Takes energy bought on the market from the grid, as much as the grid connection allows.<br>
+    /// It goes to vehicles directly in the next tick and only the rest tops up the buffer battery.
+    ///
+    /// # Returns
+    /// The energy in kWh that was imported.
+    pub fn import_charge(&mut self, charge: usize) -> usize {
+        let imported = charge.min(self.grid_energy_left());
+        if imported < charge {
+            debug!("Charger {} hit its grid limit of {} kW, only importing {} of {} kWh", self.name, self.grid_limit, imported, charge);
+        }
+        self.grid_charge += imported;
+        self.grid_energy_this_tick += imported;
+        imported
+    }
+
+    /// # Description
+    /// Takes energy from the buffer battery to sell it to the grid, as much as the grid connection allows.
+    ///
+    /// # Returns
+    /// The energy in kWh that was exported.
+    pub fn export_charge(&mut self, charge: usize) -> usize {
+        let exported = self.remove_charge(charge.min(self.grid_energy_left())) as usize;
+        self.grid_energy_this_tick += exported;
+        exported
+    }
+
+    /// # Description
+    /// Puts energy back into the buffer battery, that was exported but not sold.
+    pub fn cancel_export(&mut self, charge: usize) {
+        self.add_charge(charge);
+        self.grid_energy_this_tick = self.grid_energy_this_tick.saturating_sub(charge);
+    }
+
+    /// # Description
+    /// Books energy of a vehicle, that is fed into the grid through the charger, on the grid connection.
+    ///
+    /// # Returns
+    /// Whether the grid connection can take the energy in this market round.
+    pub fn feed_in(&mut self, charge: usize) -> bool {
+        if charge > self.grid_energy_left() {
+            return false;
+        }
+        self.grid_energy_this_tick += charge;
+        true
+    }
+
+    /// # Description
+    /// Frees the grid connection from energy of a vehicle, that was booked but not sold.
+    pub fn cancel_feed_in(&mut self, charge: usize) {
+        self.grid_energy_this_tick = self.grid_energy_this_tick.saturating_sub(charge);
+    }
+
+    /// # Description
+    /// Starts a new market round: the energy bought for the last tick, that no vehicle took, tops up the buffer battery
+    /// and the grid connection is free again. What it didn't take in the last round is carried over, up to one package.
+    pub fn store_grid_charge(&mut self) {
+        let stored = self.add_charge(self.grid_charge) as usize;
+        if stored < self.grid_charge {
+            debug!("Charger {} could only store {} of {} kWh from the grid", self.name, stored, self.grid_charge);
+        }
+        let unused = self.grid_limit as f64 * TICK_AS_HOUR + self.grid_carry - self.grid_energy_this_tick as f64;
+        self.grid_carry = unused.clamp(0.0, OFFER_PACKAGE_SIZE);
+        self.grid_charge = 0;
+        self.grid_energy_this_tick = 0;
+    }
+
     pub fn get_position(&self) -> Position {
         self.position
     }
@@ -116,11 +310,99 @@ impl Charger {
     }
 
     pub fn get_ports(&self) -> usize {
-        self.charging_ports
+        self.ports.len()
     }
 
-    pub fn get_free_ports(&self) -> usize {
-        self.charging_ports - self.reserved_ports
+    pub fn get_reserved_ports(&self) -> usize {
+        self.ports.iter().filter(|p| p.reserved).count()
+    }
+
+    pub fn get_port(&self, port: usize) -> &ChargePort {
+        &self.ports[port]
+    }
+
+    /// # Returns
+    /// The connector types of all ports.
+    pub fn get_connectors(&self) -> Vec<ConnectorType> {
+        self.ports.iter().map(|p| p.connector).collect()
+    }
+
+    /// # Returns
+    /// The connector types of all ports that are not reserved.
+    pub fn get_free_connectors(&self) -> Vec<ConnectorType> {
+        self.ports.iter().filter(|p| !p.reserved).map(|p| p.connector).collect()
+    }
+
+    /// # Returns
+    /// Whether the charger has a port with one of the given connectors, no matter if it is free.
+    pub fn has_compatible_port(&self, connectors: &[ConnectorType]) -> bool {
+        self.ports.iter().any(|p| connectors.contains(&p.connector))
+    }
+
+    /// # Description
+    /// Finds the free port that charges a vehicle the fastest.<br>
+    /// If several ports are equally fast for the vehicle, the weakest one is chosen to keep the strong ports free.
+    ///
+    /// # Arguments
+    /// `connectors`: The connector types of the vehicle.
+    /// `vehicle_power`: The power in kW the vehicle can be charged with at a connector, AC is often lower than DC.
+    ///
+    /// # Returns
+    /// The index of the port, or `None` if no compatible port is free.
+    pub fn find_free_port(&self, connectors: &[ConnectorType], vehicle_power: impl Fn(ConnectorType) -> f64) -> Option<usize> {
+        self.find_free_port_where(connectors, vehicle_power, |_| true)
+    }
+
+    /// # Description
+    /// Finds the free port that charges a vehicle the fastest, like `find_free_port`, among the ports that are usable.
+    ///
+    /// # Arguments
+    /// `connectors`: The connector types of the vehicle.
+    /// `vehicle_power`: The power in kW the vehicle can be charged with at a connector.
+    /// `usable`: Whether the port with the given index may be used.
+    ///
+    /// # Returns
+    /// The index of the port, or `None` if no compatible port is free and usable.
+    pub fn find_free_port_where(
+        &self,
+        connectors: &[ConnectorType],
+        vehicle_power: impl Fn(ConnectorType) -> f64,
+        usable: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        self.ports
+            .iter()
+            .enumerate()
+            .filter(|(i, p)| !p.reserved && connectors.contains(&p.connector) && usable(*i))
+            .max_by(|(a, pa), (b, pb)| {
+                self.charge_power(*a, &vehicle_power)
+                    .total_cmp(&self.charge_power(*b, &vehicle_power))
+                    .then(pb.max_power.total_cmp(&pa.max_power))
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// # Returns
+    /// The power in kW a vehicle is charged with at the given port, limited by the port, the vehicle and the site.
+    pub fn charge_power(&self, port: usize, vehicle_power: impl Fn(ConnectorType) -> f64) -> f64 {
+        let port = &self.ports[port];
+        port.max_power.min(vehicle_power(port.connector)).min(self.rate as f64)
+    }
+
+    /// # Returns
+    /// The charge in kWh that can be handed out at the given port in this tick.
+    /// Without a port only the power limit of the site applies.
+    pub fn deliverable_charge(&self, port: Option<usize>) -> usize {
+        let site_left = ((self.rate as f64 * TICK_AS_HOUR) as usize).saturating_sub(self.delivered_this_tick);
+        match port {
+            Some(port) => site_left.min((self.ports[port].max_power * TICK_AS_HOUR) as usize),
+            None => site_left,
+        }
+    }
+
+    /// # Description
+    /// Starts a new tick, in which the full power limit of the site is available again.
+    pub fn reset_delivered_charge(&mut self) {
+        self.delivered_this_tick = 0;
     }
 
     pub fn get_capacity(&self) -> usize {
@@ -135,35 +417,17 @@ impl Charger {
         self.current_charge as f64 / self.capacity as f64
     }
 
-    pub fn get_current_price(&self) -> f64 {
-        1.01 - self.get_charge_percentage()// TODO: !!!@Tom!!! habe das auf 1.1 gesetzt, damit es nie 0.0 wird, weil dann alle Offers gleichen Preis haben obowhl sie unterschiedlich weit weg sind
-    }
-
-    /// Gets the amount of charge needed to fill the charger
+    /// Gets the amount of charge needed to fill the charger, limited by what the grid connection can take in a tick
     pub fn amount_of_needed_packages(&self) -> usize {
         // Calculate the number of packages needed to fill the charger
-        let remaining_capacity = self.capacity - self.current_charge;
+        let remaining_capacity = self.capacity.saturating_sub(self.current_charge + self.grid_charge);
 
         if remaining_capacity == 0 {
             return 0; // No packages needed if already full
         }
 
-        
-        (remaining_capacity as f64 / OFFER_PACKAGE_SIZE).ceil() as usize
-    }
-
-    /// Gets the price of the charger if it had a charge of `amount` added to it.
-    /// This is used to progressively reduce the price of buy offers sent
-    /// I swear this makes sense :P
-    pub fn get_price_if_had_charge(&self, amount: usize) -> f64 {
-        let mut price = 1.0 - ((self.current_charge + amount) as f64 / self.capacity as f64);
-        // At a certain point we run into float weirdness when transforming
-        if price < 0.1 {
-            price = 0.1;
-        }
-        debug!("Charger {} would have price {} if it had {} charge added", self.name, price, amount);
-
-        price
+        let grid_packages = (self.grid_energy_left() as f64 / OFFER_PACKAGE_SIZE).floor() as usize;
+        ((remaining_capacity as f64 / OFFER_PACKAGE_SIZE).ceil() as usize).min(grid_packages)
     }
 
     pub fn reserve_charge(&mut self, charge: usize) -> isize {
@@ -177,24 +441,26 @@ impl Charger {
         }
     }
 
-    pub fn take_reserved_charge(&mut self, charge: usize) -> usize {
-        // Take reserved charge if available
-        if self.reserved_charge >= charge {
-            self.reserved_charge -= charge;
-            self.remove_charge(charge); // Also remove from current charge
-            charge
-        } else {
-            debug!("Charger {} does not have enough reserved charge to take {}. Reserved: {}", self.name, charge, self.reserved_charge);
-            let remaining_reserved_charge = self.reserved_charge;
-            let reservable_charge = if (charge - remaining_reserved_charge) > self.get_available_charge() {
-                self.get_available_charge()
-            } else {
-                charge - remaining_reserved_charge
-            };
-            self.remove_charge(reservable_charge + remaining_reserved_charge); // Remove available charge
-            self.reserved_charge = 0; // Reset reserved charge
-            reservable_charge + remaining_reserved_charge // Return what was taken
+    /// # Description
+    /// Hands out charge to a vehicle, from its reservation first.<br>
+    /// Anything beyond the reservation is only taken from the charge that isn't reserved for other vehicles.
+    ///
+    /// # Arguments
+    /// - `charge`: The charge the vehicle wants in kWh.
+    /// - `reserved`: The charge that is still reserved for the vehicle in kWh.
+    ///
+    /// # Returns
+    /// The charge in kWh that was taken.
+    pub fn take_reserved_charge(&mut self, charge: usize, reserved: usize) -> usize {
+        let from_reservation = charge.min(reserved).min(self.reserved_charge);
+        let unreserved = (charge - from_reservation).min(self.get_available_charge());
+        if from_reservation + unreserved < charge {
+            debug!("Charger {} can only hand out {} of {} kWh. Reserved for the vehicle: {}", self.name, from_reservation + unreserved, charge, reserved);
         }
+        self.reserved_charge -= from_reservation;
+        let taken = self.draw_charge(from_reservation + unreserved);
+        self.delivered_this_tick += taken;
+        taken
     }
 
     pub fn release_reserved_charge(&mut self, charge: usize) -> isize {
@@ -208,25 +474,100 @@ impl Charger {
         }
     }
 
-    pub fn reserve_port(&mut self) -> bool {
-        // Reserve a charging port if available
-        if self.reserved_ports < self.charging_ports {
-            self.reserved_ports += 1;
+    pub fn reserve_port(&mut self, port: usize) -> bool {
+        // Reserve the charging port if it is free
+        if !self.ports[port].reserved {
+            self.ports[port].reserved = true;
             true
         } else {
-            debug!("Charger {} has no free ports to reserve", self.name);
-            false // No free ports available
+            debug!("Charger {} port {} is already reserved", self.name, port);
+            false // Port is already in use
         }
     }
 
-    pub fn release_port(&mut self) -> bool {
-        // Release a charging port if used
-        if self.reserved_ports > 0 {
-            self.reserved_ports -= 1;
+    pub fn release_port(&mut self, port: usize) -> bool {
+        // Release the charging port if used
+        if self.ports[port].reserved {
+            self.ports[port].reserved = false;
             true
         } else {
-            debug!("Charger {} has no ports to release", self.name);
-            false // No ports to release
+            debug!("Charger {} port {} is not reserved", self.name, port);
+            false // No port to release
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charger(rate: usize, grid_limit: usize, capacity: usize) -> Charger {
+        Charger::new("charger".to_string(), Position::new(52.52, 13.40), rate, grid_limit, capacity, default_ports())
+    }
+
+    #[test]
+    fn site_below_package_size_saves_up_for_packages() {
+        // A 22 kW grid connection takes 5.5 kWh a tick, so a package every second tick
+        let mut tiny = charger(22, 22, 200);
+        let mut bought = 0;
+        for _ in 0..4 {
+            let packages = tiny.amount_of_needed_packages();
+            bought += tiny.import_charge(packages * OFFER_PACKAGE_SIZE as usize);
+            tiny.store_grid_charge();
         }
+        assert_eq!(bought, 20);
+        assert!(bought as f64 <= 4.0 * 22.0 * TICK_AS_HOUR);
+    }
+
+    #[test]
+    fn packages_are_limited_by_grid_and_capacity() {
+        let mut charger = charger(300, 200, 200);
+        assert_eq!(charger.amount_of_needed_packages(), 5); // 200 kW for a quarter hour
+
+        charger.add_charge(185);
+        assert_eq!(charger.amount_of_needed_packages(), 2); // 15 kWh left, rounded up
+
+        charger.add_charge(15);
+        assert_eq!(charger.amount_of_needed_packages(), 0);
+    }
+
+    #[test]
+    fn import_is_capped_by_grid_limit() {
+        let mut charger = charger(300, 100, 200);
+        assert!(charger.can_import(25));
+        assert!(!charger.can_import(26));
+        assert_eq!(charger.import_charge(20), 20);
+        assert!(!charger.can_import(10));
+        assert_eq!(charger.import_charge(20), 5);
+        assert_eq!(charger.grid_energy_left(), 0);
+        assert_eq!(charger.get_available_charge(), 25);
+
+        charger.store_grid_charge();
+        assert_eq!(charger.get_current_charge(), 25);
+        assert_eq!(charger.grid_energy_left(), 25);
+    }
+
+    #[test]
+    fn reservations_of_others_are_not_taken() {
+        let mut charger = charger(300, 100, 200);
+        charger.add_charge(50);
+        charger.reserve_charge(20); // for the vehicle
+        charger.reserve_charge(25); // for another vehicle
+
+        // 20 kWh from its reservation and only 5 kWh more, that aren't reserved
+        assert_eq!(charger.take_reserved_charge(40, 20), 25);
+        assert_eq!(charger.get_current_charge(), 25);
+        assert_eq!(charger.get_available_charge(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn vehicles_get_grid_energy_before_battery() {
+        let mut charger = charger(300, 100, 200);
+        charger.add_charge(50);
+        charger.import_charge(20);
+
+        assert_eq!(charger.draw_charge(30), 30);
+        assert_eq!(charger.get_current_charge(), 40);
+        assert_eq!(charger.get_available_charge(), 40);
+    }
+}
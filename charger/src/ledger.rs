@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use powercable::charger::ChargerStats;
+
+/// # Description
+/// Energy the charger bought on the market.
+///
+/// # Fields
+/// - `tick`: The tick in which the energy was bought.
+/// - `seller`: The name of the seller.
+/// - `amount`: The amount of energy in kWh.
+/// - `price`: The price per kWh in €.
+#[derive(Debug, Clone)]
+pub struct Purchase {
+    pub tick: u64,
+    pub seller: String,
+    pub amount: f64,
+    pub price: f64,
+}
+
+/// # Description
+/// Energy the charger sold to a vehicle or back to the grid.
+///
+/// # Fields
+/// - `tick`: The tick in which the energy was sold.
+/// - `buyer`: The name of the vehicle or the id of the buy offer on the market.
+/// - `amount`: The amount of energy in kWh.
+/// - `price`: The price per kWh in €.
+#[derive(Debug, Clone)]
+pub struct Sale {
+    pub tick: u64,
+    pub buyer: String,
+    pub amount: f64,
+    pub price: f64,
+}
+
+/// # Description
+/// A cancellation or no-show fee the charger received from a vehicle.
+///
+/// # Fields
+/// - `tick`: The tick in which the fee was charged.
+/// - `vehicle`: The name of the vehicle.
+/// - `amount`: The fee in €.
+#[derive(Debug, Clone)]
+pub struct Fee {
+    pub tick: u64,
+    pub vehicle: String,
+    pub amount: f64,
+}
+
+/// # Description
+/// The share the charger kept of the price of energy of a parked vehicle it passed on to the grid.
+///
+/// # Fields
+/// - `tick`: The tick in which the energy was sold.
+/// - `amount`: The margin in €.
+#[derive(Debug, Clone)]
+pub struct V2gMargin {
+    pub tick: u64,
+    pub amount: f64,
+}
+
+/// # Description
+/// What a vehicle bought from the charger and fed into the grid through it, since it arrived.
+///
+/// # Fields
+/// - `energy`: The energy sold to the vehicle in kWh.
+/// - `cost`: The money the vehicle paid for it in €.
+/// - `fed_in`: The energy the vehicle fed into the grid in kWh.
+/// - `earned`: The money the vehicle was paid for it in €.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Session {
+    pub energy: f64,
+    pub cost: f64,
+    pub fed_in: f64,
+    pub earned: f64,
+}
+
+/// # Description
+/// The `Ledger` records all money a charger spent and earned.<br>
+/// The entries are kept until the stats of their tick are published, afterwards only the running totals are left.
+///
+/// # Fields
+/// - `purchases`: The energy bought on the market.
+/// - `sales`: The energy sold to vehicles.
+/// - `grid_sales`: The energy sold back to the grid from the battery.
+/// - `fees`: The fees received from vehicles.
+/// - `v2g_margins`: The margins kept of energy of parked vehicles passed on to the grid.
+/// - `sessions`: The open session of every vehicle that bought or fed in energy, until it is released.
+/// - `total_bought`: The energy bought since the start in kWh.
+/// - `total_purchase_cost`: The money paid for energy since the start.
+/// - `total_revenue`: The money received for energy since the start, from vehicles and the grid.
+/// - `total_fees`: The fees received since the start.
+/// - `total_v2g_margin`: The V2G margins kept since the start.
+/// - `total_margin`: The margins of all published ticks.
+#[derive(Debug, Clone, Default)]
+pub struct Ledger {
+    purchases: Vec<Purchase>,
+    sales: Vec<Sale>,
+    grid_sales: Vec<Sale>,
+    fees: Vec<Fee>,
+    v2g_margins: Vec<V2gMargin>,
+    sessions: HashMap<String, Session>,
+    total_bought: f64,
+    total_purchase_cost: f64,
+    total_revenue: f64,
+    total_fees: f64,
+    total_v2g_margin: f64,
+    total_margin: f64,
+}
+
+impl Ledger {
+    pub fn record_purchase(&mut self, tick: u64, seller: String, amount: f64, price: f64) {
+        self.total_bought += amount;
+        self.total_purchase_cost += amount * price;
+        self.purchases.push(Purchase { tick, seller, amount, price });
+    }
+
+    pub fn record_sale(&mut self, tick: u64, vehicle: String, amount: f64, price: f64) {
+        self.total_revenue += amount * price;
+        let session = self.sessions.entry(vehicle.clone()).or_default();
+        session.energy += amount;
+        session.cost += amount * price;
+        self.sales.push(Sale { tick, buyer: vehicle, amount, price });
+    }
+
+    pub fn record_grid_sale(&mut self, tick: u64, offer_id: String, amount: f64, price: f64) {
+        self.total_revenue += amount * price;
+        self.grid_sales.push(Sale { tick, buyer: offer_id, amount, price });
+    }
+
+    pub fn record_fee(&mut self, tick: u64, vehicle: String, amount: f64) {
+        self.total_fees += amount;
+        self.fees.push(Fee { tick, vehicle, amount });
+    }
+
+    /// # Description
+    /// Records energy of a parked vehicle passed on to the grid, at the price paid to the vehicle.<br>
+    /// The charger only keeps its margin of it, which is recorded with `record_v2g_margin`.
+    pub fn record_v2g_purchase(&mut self, vehicle: String, amount: f64, price: f64) {
+        let session = self.sessions.entry(vehicle).or_default();
+        session.fed_in += amount;
+        session.earned += amount * price;
+    }
+
+    /// # Description
+    /// Records the margin the charger kept of energy of a parked vehicle passed on to the grid.<br>
+    /// It is neither a fee nor a sale, so the vehicle doesn't count as a customer.
+    pub fn record_v2g_margin(&mut self, tick: u64, amount: f64) {
+        self.total_v2g_margin += amount;
+        self.v2g_margins.push(V2gMargin { tick, amount });
+    }
+
+    /// # Returns
+    /// The average price per kWh the charger paid, `None` if it didn't buy anything yet.
+    pub fn avg_purchase_price(&self) -> Option<f64> {
+        (self.total_bought > 0.0).then(|| self.total_purchase_cost / self.total_bought)
+    }
+
+    /// # Returns
+    /// The open session of a vehicle, empty if it didn't buy or feed in anything yet.
+    pub fn session(&self, vehicle: &str) -> Session {
+        self.sessions.get(vehicle).copied().unwrap_or_default()
+    }
+
+    /// # Description
+    /// Closes the session of a vehicle that was released, its next session starts empty.
+    pub fn close_session(&mut self, vehicle: &str) {
+        self.sessions.remove(vehicle);
+    }
+
+    /// # Description
+    /// Sums up the ledger for a tick and since the start, the entries of the tick are dropped afterwards.<br>
+    /// Entries of earlier ticks that came in after their stats were published are counted with this tick.
+    /// The margin is the revenue of the tick minus the average purchase price of the sold energy,
+    /// energy sold back to the grid included.
+    ///
+    /// # Arguments
+    /// - `charger_name` - The name of the charger
+    /// - `tick` - The tick to sum up
+    /// - `timestamp` - The timestamp of the tick
+    ///
+    /// # Returns
+    /// The summary as `ChargerStats`.
+    pub fn stats(&mut self, charger_name: String, tick: u64, timestamp: usize) -> ChargerStats {
+        let purchases: Vec<Purchase> = self.purchases.extract_if(.., |p| p.tick <= tick).collect();
+        let sales: Vec<Sale> = self.sales.extract_if(.., |s| s.tick <= tick).collect();
+        let grid_sales: Vec<Sale> = self.grid_sales.extract_if(.., |s| s.tick <= tick).collect();
+        let fees: Vec<Fee> = self.fees.extract_if(.., |f| f.tick <= tick).collect();
+        let v2g_margins: Vec<V2gMargin> = self.v2g_margins.extract_if(.., |m| m.tick <= tick).collect();
+
+        let mut sellers: Vec<String> = purchases.iter().map(|p| p.seller.clone()).collect();
+        sellers.sort();
+        sellers.dedup();
+        let mut customers: Vec<String> = sales.iter().map(|s| s.buyer.clone())
+            .chain(fees.iter().map(|f| f.vehicle.clone()))
+            .collect();
+        customers.sort();
+        customers.dedup();
+
+        let bought = purchases.iter().map(|p| p.amount).sum();
+        let purchase_cost = purchases.iter().map(|p| p.amount * p.price).sum();
+        let sold: f64 = sales.iter().map(|s| s.amount).sum();
+        let revenue: f64 = sales.iter().map(|s| s.amount * s.price).sum();
+        let sold_to_grid: f64 = grid_sales.iter().map(|s| s.amount).sum();
+        let grid_revenue: f64 = grid_sales.iter().map(|s| s.amount * s.price).sum();
+        let fees = fees.iter().map(|f| f.amount).sum();
+        let v2g_margin = v2g_margins.iter().map(|m| m.amount).sum();
+        let margin = revenue + grid_revenue - (sold + sold_to_grid) * self.avg_purchase_price().unwrap_or(0.0);
+        self.total_margin += margin;
+
+        ChargerStats {
+            charger_name,
+            tick,
+            timestamp,
+            bought,
+            purchase_cost,
+            sold,
+            revenue,
+            fees,
+            sold_to_grid,
+            grid_revenue,
+            v2g_margin,
+            margin,
+            sellers,
+            customers,
+            total_purchase_cost: self.total_purchase_cost,
+            total_revenue: self.total_revenue,
+            total_fees: self.total_fees,
+            total_v2g_margin: self.total_v2g_margin,
+            total_margin: self.total_margin,
+            total_profit: self.total_revenue + self.total_fees + self.total_v2g_margin - self.total_purchase_cost,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_sum_up_the_tick() {
+        let mut ledger = Ledger::default();
+        ledger.record_purchase(1, "Turbine".to_string(), 20.0, 0.2);
+        ledger.record_sale(1, "Vehicle".to_string(), 10.0, 0.5);
+        ledger.record_fee(1, "Other Vehicle".to_string(), 2.0);
+        ledger.record_sale(2, "Vehicle".to_string(), 10.0, 0.5);
+
+        let stats = ledger.stats("Charger".to_string(), 1, 0);
+        assert_eq!(stats.bought, 20.0);
+        assert_eq!(stats.sold, 10.0);
+        assert_eq!(stats.revenue, 5.0);
+        assert_eq!(stats.fees, 2.0);
+        assert!((stats.margin - 3.0).abs() < 1e-9);
+        assert_eq!(stats.customers, vec!["Other Vehicle".to_string(), "Vehicle".to_string()]);
+        assert!((stats.total_profit - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn published_entries_are_dropped_but_totals_kept() {
+        let mut ledger = Ledger::default();
+        ledger.record_purchase(1, "Turbine".to_string(), 20.0, 0.2);
+        ledger.record_sale(1, "Vehicle".to_string(), 10.0, 0.5);
+        ledger.stats("Charger".to_string(), 1, 0);
+        assert!(ledger.purchases.is_empty() && ledger.sales.is_empty());
+
+        let stats = ledger.stats("Charger".to_string(), 2, 0);
+        assert_eq!(stats.bought, 0.0);
+        assert_eq!(stats.sold, 0.0);
+        assert!((stats.total_purchase_cost - 4.0).abs() < 1e-9);
+        assert!((stats.total_revenue - 5.0).abs() < 1e-9);
+        assert!((stats.total_margin - 3.0).abs() < 1e-9);
+        assert_eq!(ledger.avg_purchase_price(), Some(0.2));
+    }
+
+    #[test]
+    fn late_entries_count_with_the_next_tick() {
+        let mut ledger = Ledger::default();
+        ledger.stats("Charger".to_string(), 1, 0);
+        ledger.record_sale(1, "Vehicle".to_string(), 10.0, 0.5);
+
+        let stats = ledger.stats("Charger".to_string(), 2, 0);
+        assert_eq!(stats.sold, 10.0);
+    }
+
+    #[test]
+    fn v2g_margin_is_no_fee() {
+        let mut ledger = Ledger::default();
+        ledger.record_v2g_purchase("Vehicle".to_string(), 4.0, 0.25);
+        ledger.record_v2g_margin(1, 0.2);
+
+        let stats = ledger.stats("Charger".to_string(), 1, 0);
+        assert_eq!(stats.fees, 0.0);
+        assert_eq!(stats.v2g_margin, 0.2);
+        assert!(stats.customers.is_empty());
+        assert_eq!(stats.total_v2g_margin, 0.2);
+        assert_eq!(stats.total_profit, 0.2);
+    }
+
+    #[test]
+    fn session_lasts_until_closed() {
+        let mut ledger = Ledger::default();
+        ledger.record_sale(1, "Vehicle".to_string(), 10.0, 0.5);
+        ledger.stats("Charger".to_string(), 1, 0);
+        ledger.record_sale(2, "Vehicle".to_string(), 5.0, 0.4);
+        ledger.record_v2g_purchase("Vehicle".to_string(), 4.0, 0.25);
+
+        let session = ledger.session("Vehicle");
+        assert_eq!(session.energy, 15.0);
+        assert!((session.cost - 7.0).abs() < 1e-9);
+        assert_eq!(session.fed_in, 4.0);
+        assert_eq!(session.earned, 1.0);
+
+        ledger.close_session("Vehicle");
+        assert_eq!(ledger.session("Vehicle"), Session::default());
+    }
+}
@@ -1,40 +1,149 @@
-use car_handling::{accept_handler, answer_get, receive_request};
-use charger::Charger;
+use car_handling::{accept_handler, answer_get, eta_handler, receive_request};
+use charger::{basic_ports, default_ports, Charger};
+use ledger::Ledger;
+use negotiation::{counter_handler, negotiation_policy_handler, NegotiationPolicy};
 use tracing::{info, warn};
-use offer_handling::ReservedOffer;
+use offer_handling::{ReservationPolicy, ReservedOffer};
+use operator_handling::{heartbeat_handler, route_handler, transfer_handler};
+use pricing::{PricingStrategy, SocLinear};
+use registry::{ChargerSite, REGISTRY_CHARGER_CAPACITY};
 use powercable::{
-    generate_rnd_pos, generate_seed, generate_unique_name, OfferHandler, OwnType,
-    ACCEPT_BUY_OFFER_TOPIC, CHARGER_ACCEPT, CHARGER_CHARGING_GET, CHARGER_CHARGING_RELEASE, CHARGER_REQUEST, CONFIG_VEHICLE, TICK_TOPIC
+    charger::ChargeRequest, generate_rnd_pos, generate_seed, generate_unique_name, OfferHandler, OwnType,
+    ACCEPT_BUY_OFFER_TOPIC, ACK_ACCEPT_BUY_OFFER_TOPIC, BUY_OFFER_TOPIC, CHARGER_ACCEPT, CHARGER_CANCEL, CHARGER_CHARGING_GET, CHARGER_CHARGING_RELEASE, CHARGER_COUNTER, CHARGER_ETA, CHARGER_REQUEST,
+    CHARGER_V2G_OFFER, OPERATOR_HEARTBEAT, OPERATOR_ROUTE, OPERATOR_TRANSFER, CONFIG_CHARGER_GRID, CONFIG_CHARGER_NEGOTIATION, CONFIG_CHARGER_PRICING, CONFIG_CHARGER_RESERVATION,
+    CONFIG_CHARGER_STORAGE, CONFIG_CHARGER_V2G, CONFIG_VEHICLE, TICK_TOPIC
 };
 use rumqttc::{AsyncClient, MqttOptions, QoS};
-use std::{sync::Arc, time::Duration};
+use storage::{ack_sell_handler, buy_offer_handler, StoragePolicy};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
 use tokio::{sync::Mutex, task};
 use topic_handler::{accept_offer_handler, tick_handler};
+use v2g::{v2g_offer_handler, v2g_policy_handler, V2gPolicy, V2gState};
 
-use crate::{car_handling::release_car, topic_handler::show_handler};
+use crate::{car_handling::{cancel_handler, release_car}, topic_handler::{grid_handler, pricing_handler, reservation_policy_handler, show_handler, storage_policy_handler}};
 
 mod car_handling;
 mod charger;
+mod ledger;
+mod negotiation;
 mod offer_handling;
+mod operator_handling;
+mod pricing;
+pub mod registry;
+mod storage;
 mod topic_handler;
+mod v2g;
 
 type SharedCharger = Arc<Mutex<ChargerHandler>>;
 
+/// The buffer battery of a randomly placed charger in kWh
+const CHARGER_CAPACITY: usize = 300;
+/// The power limit of a basic site in kW, shared by all of its ports
+const BASIC_RATE: usize = 50;
+/// The power limit of the grid connection of a basic site in kW
+const BASIC_GRID_LIMIT: usize = 150;
+/// The power limit of a site with mixed ports in kW
+const MIXED_RATE: usize = 300;
+/// The power limit of the grid connection of a site with mixed ports in kW
+const MIXED_GRID_LIMIT: usize = 150;
+
 struct ChargerHandler {
     pub charger: Charger,
     pub client: AsyncClient,
     pub currently_reserved_for: Vec<ReservedOffer>,
     pub offer_handler: OfferHandler,
     pub consumed_last_tick: f64,
+    pub current_tick: u64,
+    pub reservation_policy: ReservationPolicy,
+    pub waitlist: VecDeque<ChargeRequest>,
+    pub current_timestamp: usize,
+    pub pricing: Box<dyn PricingStrategy>,
+    pub has_own_pricing: bool,
+    pub ledger: Ledger,
+    pub sell_offer_handler: OfferHandler,
+    pub storage_policy: StoragePolicy,
+    pub sold_to_grid: f64,
+    pub operator: Option<String>,
+    pub operator_seen_tick: Option<u64>,
+    pub v2g_policy: V2gPolicy,
+    pub has_own_v2g_policy: bool,
+    pub v2g: V2gState,
+    pub negotiation_policy: NegotiationPolicy,
+}
+
+impl ChargerHandler {
+    /// # Description
+    /// Creates the handler of a charger with the default policies, before its first tick.
+    ///
+    /// # Arguments
+    /// - `charger`: The charger.
+    /// - `client`: The MQTT client.
+    /// - `operator`: The charge point operator the charger belongs to, if any.
+    fn new(charger: Charger, client: AsyncClient, operator: Option<String>) -> Self {
+        ChargerHandler {
+            charger,
+            client,
+            offer_handler: OfferHandler::new(),
+            currently_reserved_for: Vec::new(),
+            consumed_last_tick: 0.0,
+            current_tick: 0,
+            reservation_policy: ReservationPolicy::default(),
+            waitlist: VecDeque::new(),
+            current_timestamp: 0,
+            pricing: Box::new(SocLinear),
+            has_own_pricing: false,
+            ledger: Ledger::default(),
+            sell_offer_handler: OfferHandler::new(),
+            storage_policy: StoragePolicy::default(),
+            sold_to_grid: 0.0,
+            operator,
+            operator_seen_tick: None,
+            v2g_policy: V2gPolicy::default(),
+            has_own_v2g_policy: false,
+            v2g: V2gState::default(),
+            negotiation_policy: NegotiationPolicy::default(),
+        }
+    }
 }
 
-pub async fn start_charger(i: u64) {
+/// # Description
+/// Starts a charger at a random position.<br>
+/// By default it has five 50 kW ports sharing 50 kW behind a grid connection of 150 kW.
+/// With mixed ports it has fast DC and slow AC ports, up to 300 kW and a grid connection of 150 kW.
+///
+/// # Arguments
+/// - `i`: The index of the charger, used for its seed.
+/// - `operator`: The charge point operator the charger belongs to, if any.
+/// - `mixed_ports`: Whether the charger gets the mixed ports of a typical site instead of the basic ones.
+pub async fn start_charger(i: u64, operator: Option<String>, mixed_ports: bool) {
     let seed = generate_seed(i, OwnType::Charger);
 
     let charger_name: String = format!("Charger {}", generate_unique_name(seed));
-    info!("Starting charger simulation...");
+    let charger = if mixed_ports {
+        Charger::new(charger_name, generate_rnd_pos(seed), MIXED_RATE, MIXED_GRID_LIMIT, CHARGER_CAPACITY, default_ports())
+    } else {
+        Charger::new(charger_name, generate_rnd_pos(seed), BASIC_RATE, BASIC_GRID_LIMIT, CHARGER_CAPACITY, basic_ports())
+    };
+    run_charger(charger, operator).await;
+}
+
+/// # Description
+/// Starts a charger for a site of the charger registry, with its position, ports and power.
+///
+/// # Arguments
+/// - `i`: The index of the charger, used for its seed.
+/// - `site`: The site from the registry.
+pub async fn start_charger_at(i: u64, site: ChargerSite) {
+    let seed = generate_seed(i, OwnType::Charger);
 
-    let charger = Charger::new(charger_name.clone(), generate_rnd_pos(seed), 50, 300, 5);
+    let charger_name: String = format!("Charger {}", generate_unique_name(seed));
+    let charger = Charger::new(charger_name, site.position, site.power, site.power, REGISTRY_CHARGER_CAPACITY, site.ports);
+    run_charger(charger, Some(site.operator)).await;
+}
+
+async fn run_charger(charger: Charger, operator: Option<String>) {
+    let charger_name = charger.get_name().clone();
+    info!("Starting charger simulation...");
     info!("{:#?}", charger);
 
     let mut mqttoptions = MqttOptions::new(
@@ -43,7 +152,7 @@ pub async fn start_charger(i: u64) {
         powercable::MQTT_BROKER_PORT,
     );
     mqttoptions.set_keep_alive(Duration::from_secs(5));
-    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+    let (client, mut eventloop) = AsyncClient::new(mqttoptions, powercable::MQTT_REQUEST_CAPACITY);
     client
         .subscribe(TICK_TOPIC, QoS::ExactlyOnce)
         .await
@@ -52,6 +161,14 @@ pub async fn start_charger(i: u64) {
         .subscribe(ACCEPT_BUY_OFFER_TOPIC, QoS::ExactlyOnce)
         .await
         .unwrap();
+    client
+        .subscribe(BUY_OFFER_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(ACK_ACCEPT_BUY_OFFER_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
     client
         .subscribe(CHARGER_REQUEST, QoS::ExactlyOnce)
         .await
@@ -68,19 +185,75 @@ pub async fn start_charger(i: u64) {
         .subscribe(CHARGER_CHARGING_RELEASE, QoS::ExactlyOnce)
         .await
         .unwrap();
+    client
+        .subscribe(CHARGER_CANCEL, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CHARGER_COUNTER, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CHARGER_ETA, QoS::ExactlyOnce)
+        .await
+        .unwrap();
     client
         .subscribe(CONFIG_VEHICLE, QoS::ExactlyOnce)
         .await
         .unwrap();
+    client
+        .subscribe(CONFIG_CHARGER_RESERVATION, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CONFIG_CHARGER_STORAGE, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CONFIG_CHARGER_GRID, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CONFIG_CHARGER_NEGOTIATION, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(OPERATOR_HEARTBEAT, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(OPERATOR_TRANSFER, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(OPERATOR_ROUTE, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CHARGER_V2G_OFFER, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    let own_pricing_topic = format!("{}/{}", CONFIG_CHARGER_PRICING, charger_name);
+    client
+        .subscribe(CONFIG_CHARGER_PRICING, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(&own_pricing_topic, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    let own_v2g_topic = format!("{}/{}", CONFIG_CHARGER_V2G, charger_name);
+    client
+        .subscribe(CONFIG_CHARGER_V2G, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(&own_v2g_topic, QoS::ExactlyOnce)
+        .await
+        .unwrap();
     info!("Connected to MQTT broker");
 
-    let shared_charger = Arc::new(Mutex::new(ChargerHandler {
-        charger,
-        client: client.clone(),
-        offer_handler: OfferHandler::new(),
-        currently_reserved_for: Vec::new(),
-        consumed_last_tick: 0.0,
-    }));
+    let shared_charger = Arc::new(Mutex::new(ChargerHandler::new(charger, client.clone(), operator)));
 
     while let Ok(notification) = eventloop.poll().await {
         if let rumqttc::Event::Incoming(rumqttc::Packet::Publish(p)) = notification {
@@ -91,6 +264,12 @@ pub async fn start_charger(i: u64) {
                 ACCEPT_BUY_OFFER_TOPIC => {
                     task::spawn(accept_offer_handler(shared_charger.clone(), p.payload));
                 }
+                BUY_OFFER_TOPIC => {
+                    task::spawn(buy_offer_handler(shared_charger.clone(), p.payload));
+                }
+                ACK_ACCEPT_BUY_OFFER_TOPIC => {
+                    task::spawn(ack_sell_handler(shared_charger.clone(), p.payload));
+                }
                 CHARGER_REQUEST => {
                     task::spawn(receive_request(shared_charger.clone(), p.payload));
                 }
@@ -103,9 +282,54 @@ pub async fn start_charger(i: u64) {
                 CHARGER_CHARGING_RELEASE => {
                     task::spawn(release_car(shared_charger.clone(), p.payload));
                 }
+                CHARGER_CANCEL => {
+                    task::spawn(cancel_handler(shared_charger.clone(), p.payload));
+                }
+                CHARGER_COUNTER => {
+                    task::spawn(counter_handler(shared_charger.clone(), p.payload));
+                }
+                CHARGER_ETA => {
+                    task::spawn(eta_handler(shared_charger.clone(), p.payload));
+                }
                 CONFIG_VEHICLE => {
                     task::spawn(show_handler(shared_charger.clone(), p.payload));    
                 }
+                CONFIG_CHARGER_RESERVATION => {
+                    task::spawn(reservation_policy_handler(shared_charger.clone(), p.payload));
+                }
+                CONFIG_CHARGER_STORAGE => {
+                    task::spawn(storage_policy_handler(shared_charger.clone(), p.payload));
+                }
+                OPERATOR_HEARTBEAT => {
+                    task::spawn(heartbeat_handler(shared_charger.clone(), p.payload));
+                }
+                OPERATOR_TRANSFER => {
+                    task::spawn(transfer_handler(shared_charger.clone(), p.payload));
+                }
+                OPERATOR_ROUTE => {
+                    task::spawn(route_handler(shared_charger.clone(), p.payload));
+                }
+                CHARGER_V2G_OFFER => {
+                    task::spawn(v2g_offer_handler(shared_charger.clone(), p.payload));
+                }
+                CONFIG_CHARGER_GRID => {
+                    task::spawn(grid_handler(shared_charger.clone(), p.payload));
+                }
+                CONFIG_CHARGER_NEGOTIATION => {
+                    task::spawn(negotiation_policy_handler(shared_charger.clone(), p.payload));
+                }
+                CONFIG_CHARGER_PRICING => {
+                    task::spawn(pricing_handler(shared_charger.clone(), p.payload, false));
+                }
+                topic if topic == own_pricing_topic => {
+                    task::spawn(pricing_handler(shared_charger.clone(), p.payload, true));
+                }
+                CONFIG_CHARGER_V2G => {
+                    task::spawn(v2g_policy_handler(shared_charger.clone(), p.payload, false));
+                }
+                topic if topic == own_v2g_topic => {
+                    task::spawn(v2g_policy_handler(shared_charger.clone(), p.payload, true));
+                }
                 _ => {
                     warn!("Unknown topic: {}", p.topic);
                 }
@@ -113,4 +337,13 @@ pub async fn start_charger(i: u64) {
         }
     }
     println!("Exiting charger simulation...");
-}
\ No newline at end of file
+}
+
+/// # Returns
+/// A handler for a charger with the mixed ports and a client that is never connected, for tests.
+#[cfg(test)]
+fn test_handler() -> ChargerHandler {
+    let charger = Charger::new("Test Charger".to_string(), powercable::Position::new(52.52, 13.40), MIXED_RATE, MIXED_GRID_LIMIT, CHARGER_CAPACITY, default_ports());
+    let (client, _) = AsyncClient::new(MqttOptions::new("Test Charger", "localhost", 1883), 10);
+    ChargerHandler::new(charger, client, None)
+}
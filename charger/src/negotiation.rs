@@ -0,0 +1,193 @@
+use bytes::Bytes;
+use powercable::{charger::ChargeCounter, CHARGER_REVISE};
+use rumqttc::QoS;
+use serde::Deserialize;
+use tracing::{debug, info, trace, warn};
+
+use crate::SharedCharger;
+
+/// # Description
+/// The `NegotiationPolicy` defines how far a charger gives in when a vehicle counters its offer.<br>
+/// It can be configured on the `CONFIG_CHARGER_NEGOTIATION` topic.
+///
+/// # Fields
+/// - `max_discount`: The largest share (0.0 to 1.0) the charger takes off the price of its offer.
+///   It never goes below the average price it paid for its energy.
+/// - `concession`: The share (0.0 to 1.0) of the gap between its price and the counter offer the charger gives in per round.
+/// - `max_rounds`: The round in which the charger makes its final offer.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct NegotiationPolicy {
+    pub max_discount: f64,
+    pub concession: f64,
+    pub max_rounds: u32,
+}
+
+impl Default for NegotiationPolicy {
+    fn default() -> Self {
+        NegotiationPolicy {
+            max_discount: 0.15,
+            concession: 0.5,
+            max_rounds: 3,
+        }
+    }
+}
+
+impl NegotiationPolicy {
+    /// # Description
+    /// Decides the answer to a counter offer.<br>
+    /// A counter offer at or above the lowest acceptable price is taken as it is. Otherwise the charger meets
+    /// the vehicle part of the way, but not below the lowest price. In the last round it offers the lowest price.
+    ///
+    /// # Arguments
+    /// - `list_price`: The price per kWh of the original offer.
+    /// - `price`: The current price per kWh of the offer.
+    /// - `counter`: The price per kWh the vehicle is willing to pay.
+    /// - `round`: The round of the negotiation.
+    /// - `avg_purchase_price`: The average price the charger paid per kWh, `None` if it didn't buy anything yet.
+    ///
+    /// # Returns
+    /// The revised price per kWh and whether it is the final offer.
+    pub fn revise(&self, list_price: f64, price: f64, counter: f64, round: u32, avg_purchase_price: Option<f64>) -> (f64, bool) {
+        let lowest_price = (list_price * (1.0 - self.max_discount))
+            .max(avg_purchase_price.unwrap_or(0.0))
+            .min(list_price);
+        if counter >= lowest_price {
+            (counter.min(price), true)
+        } else if round >= self.max_rounds {
+            (lowest_price, true)
+        } else {
+            ((price - self.concession * (price - counter)).max(lowest_price), false)
+        }
+    }
+}
+
+/// # Description
+/// Answers a counter offer of a vehicle to one of our offers with a revised offer.<br>
+/// The reserved offer is held at the revised price, which becomes binding once the vehicle accepts it.
+/// Counter offers for other chargers, or for offers that were accepted or expired, are ignored.
+///
+/// # Arguments
+/// - `charger`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the counter offer.
+pub async fn counter_handler(charger: SharedCharger, payload: Bytes) {
+    let mut handler = charger.lock().await;
+
+    let counter = ChargeCounter::from_bytes(payload).unwrap();
+    if &counter.charger_name != handler.charger.get_name() {
+        return;
+    }
+    debug!("Counter offer: {:?}", counter);
+
+    let Some(offer) = handler.get_reserved_offer(counter.vehicle_name.clone()).filter(|o| !o.was_accepted()).cloned() else {
+        debug!("{} countered, but we hold no open offer for it", counter.vehicle_name);
+        return;
+    };
+    let (price, is_final) = handler.negotiation_policy.revise(
+        offer.get_list_price(), offer.get_price(), counter.charge_price, counter.round, handler.ledger.avg_purchase_price(),
+    );
+    let Some(charge_amount) = handler.revise_offer(&counter.vehicle_name, price) else {
+        return;
+    };
+    info!(
+        "Revising the offer for {} from {:.3}€ to {:.3}€ in round {}{}",
+        counter.vehicle_name, offer.get_price(), price, counter.round, if is_final { ", final" } else { "" }
+    );
+
+    let revision = ChargeCounter {
+        charger_name: counter.charger_name,
+        vehicle_name: counter.vehicle_name,
+        charge_price: price,
+        charge_amount,
+        round: counter.round,
+        is_final,
+    };
+    handler.client.publish(
+        CHARGER_REVISE,
+        QoS::ExactlyOnce,
+        false,
+        revision.to_bytes(),
+    ).await.unwrap();
+    trace!("Published on topic {}: {:?}", CHARGER_REVISE, revision);
+}
+
+/// # Description
+/// The `negotiation_policy_handler` function processes incoming negotiation configuration messages.<br>
+/// It replaces the negotiation policy of the charger, which is used for all following counter offers.<br>
+/// It is called when a message is received on the `CONFIG_CHARGER_NEGOTIATION` topic.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the charger handler, which contains the charger instance.
+/// - `payload`: The incoming payload containing the negotiation policy in JSON format.
+pub async fn negotiation_policy_handler(handler: SharedCharger, payload: Bytes) {
+    let policy: NegotiationPolicy = match serde_json::from_slice(&payload) {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Invalid negotiation policy: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    debug!("{} negotiation policy set to: {:?}", handler.charger.get_name(), policy);
+    handler.negotiation_policy = policy;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use powercable::charger::ReservationTerms;
+
+    use crate::{offer_handling::ReservedOffer, test_handler};
+
+    #[test]
+    fn takes_counter_above_lowest_price() {
+        let policy = NegotiationPolicy::default();
+        assert_eq!(policy.revise(0.4, 0.4, 0.36, 1, None), (0.36, true));
+        // Never more than the current price
+        assert_eq!(policy.revise(0.4, 0.38, 0.39, 2, None), (0.38, true));
+    }
+
+    #[test]
+    fn meets_low_counter_halfway() {
+        let policy = NegotiationPolicy::default();
+        let (price, is_final) = policy.revise(0.4, 0.4, 0.2, 1, None);
+        assert!((price - 0.34).abs() < 1e-9); // 0.3 is below the lowest price of 0.34
+        assert!(!is_final);
+
+        let (price, is_final) = policy.revise(0.5, 0.5, 0.4, 1, None);
+        assert!((price - 0.45).abs() < 1e-9);
+        assert!(!is_final);
+    }
+
+    #[test]
+    fn final_round_offers_lowest_price() {
+        let policy = NegotiationPolicy::default();
+        let (price, is_final) = policy.revise(0.4, 0.37, 0.2, policy.max_rounds, None);
+        assert!((price - 0.34).abs() < 1e-9);
+        assert!(is_final);
+    }
+
+    #[test]
+    fn never_sells_below_purchase_price() {
+        let policy = NegotiationPolicy::default();
+        assert_eq!(policy.revise(0.4, 0.4, 0.2, policy.max_rounds, Some(0.38)), (0.38, true));
+        // Energy bought above the list price doesn't raise the offer
+        assert_eq!(policy.revise(0.4, 0.4, 0.2, policy.max_rounds, Some(0.5)), (0.4, true));
+    }
+
+    #[test]
+    fn revision_holds_open_offer_only() {
+        let mut handler = test_handler();
+        let terms = ReservationTerms { reservation_ticks: 3, no_show_fee: 0.0, cancellation_fee: 0.0 };
+        handler.reserve_offer(ReservedOffer::new("Vehicle".to_string(), 20, 0.4, 0, terms, 0));
+
+        assert_eq!(handler.revise_offer("Vehicle", 0.35), Some(20));
+        let offer = handler.get_reserved_offer("Vehicle".to_string()).unwrap();
+        assert_eq!(offer.get_price(), 0.35);
+        assert_eq!(offer.get_list_price(), 0.4);
+
+        handler.accept_reserve("Vehicle".to_string());
+        assert_eq!(handler.revise_offer("Vehicle", 0.3), None);
+        assert_eq!(handler.revise_offer("Other", 0.3), None);
+    }
+}
@@ -1,5 +1,62 @@
 use crate::ChargerHandler;
-use tracing::debug;
+use powercable::{charger::{ChargeReceipt, ChargeRequest, ConnectorType, ReservationTerms}, routing::road_distance, tickgen::TICK_AS_HOUR};
+use serde::Deserialize;
+use tracing::{debug, info};
+
+/// How many ticks an offer is held for a vehicle before it has to be accepted
+const OFFER_VALIDITY_TICKS: u64 = 1;
+/// The port is held for a booked vehicle from this many ticks before its estimated arrival,
+/// until then it can be lent to other vehicles
+const PORT_HOLD_AHEAD_TICKS: u64 = 1;
+/// The speed we assume a vehicle drives to the charger with, in km/h.
+/// It is the slowest speed a vehicle drives with, so the estimate is on the safe side.
+const ASSUMED_VEHICLE_SPEED: f64 = 30.0;
+
+/// # Description
+/// The `ReservationPolicy` defines how long a charger holds reservations and which fees it charges.<br>
+/// It can be configured on the `CONFIG_CHARGER_RESERVATION` topic.
+/// 
+/// # Fields
+/// - `grace_ticks`: The number of ticks a reservation is held longer than the estimated arrival of the vehicle.
+/// - `no_show_fee`: The fee a vehicle has to pay if it does not arrive before its reservation expires, in €.
+/// - `cancellation_fee`: The fee a vehicle has to pay if it cancels an accepted reservation, in €.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct ReservationPolicy {
+    pub grace_ticks: u64,
+    pub no_show_fee: f64,
+    pub cancellation_fee: f64,
+}
+
+impl Default for ReservationPolicy {
+    fn default() -> Self {
+        ReservationPolicy {
+            grace_ticks: 2,
+            no_show_fee: 0.0,
+            cancellation_fee: 0.0,
+        }
+    }
+}
+
+impl ReservationPolicy {
+    /// # Description
+    /// Calculates the reservation terms for a vehicle that is `distance` km away from the charger.<br>
+    /// The reservation covers the estimated driving time plus the grace period.
+    /// 
+    /// # Arguments
+    /// - `distance`: The distance from the vehicle to the charger in km.
+    /// 
+    /// # Returns
+    /// The `ReservationTerms` that are sent to the vehicle with the offer.
+    pub fn terms_for(&self, distance: f64) -> ReservationTerms {
+        let travel_ticks = (distance / ASSUMED_VEHICLE_SPEED / TICK_AS_HOUR).ceil() as u64;// km / km/h / h = ticks
+        ReservationTerms {
+            reservation_ticks: travel_ticks + self.grace_ticks,
+            no_show_fee: self.no_show_fee,
+            cancellation_fee: self.cancellation_fee,
+        }
+    }
+}
 
 /// # Description
 /// The `ReservedOffer` struct represents an offer that the charger has reserved for a vehicle.
@@ -8,14 +65,30 @@ use tracing::debug;
 /// # Fields
 /// - `vehicle_name`: The name of the vehicle for which the offer is reserved.
 /// - `quantity`: The amount of charge reserved for the offer, in kWh.
-/// - `price`: The price per unit of charge for the reserved offer.
+/// - `price`: The price per unit of charge for the reserved offer, revised if the vehicle negotiated.
+/// - `list_price`: The price per unit of charge of the original offer.
+/// - `port`: The index of the charging port that is reserved for the vehicle.
 /// - `was_accepted`: A boolean indicating whether the offer was accepted by the vehicle or not.
+/// - `terms`: The reservation terms that were sent to the vehicle with the offer.
+/// - `expires_at`: The tick at which the reservation is released if the vehicle did not show up.
+/// - `last_seen`: The tick of the last get request of the vehicle, `None` if it has not arrived yet.
+/// - `arrived_at`: The tick of the first get request of the vehicle, when its charging session started.
+/// - `arrival_tick`: The tick the vehicle estimated to arrive in, `None` until it accepted the offer.
+/// - `holds_port`: Whether the port is held for the vehicle, it is lent to other vehicles until shortly before the arrival.
 #[derive(Debug, Clone)]
 pub struct ReservedOffer {
     vehicle_name: String,
     quantity: usize,
-    price: f64,// TODO: should be used??
+    price: f64,
+    list_price: f64,
+    port: usize,
     was_accepted: bool,
+    terms: ReservationTerms,
+    expires_at: u64,
+    last_seen: Option<u64>,
+    arrived_at: Option<u64>,
+    arrival_tick: Option<u64>,
+    holds_port: bool,
 }
 
 impl ReservedOffer {
@@ -26,17 +99,70 @@ impl ReservedOffer {
     /// - `vehicle_name`: The name of the vehicle for which the offer is reserved.
     /// - `quantity`: The amount of charge reserved for the offer, in kWh.
     /// - `price`: The price per unit of charge for the reserved offer.
+    /// - `port`: The index of the charging port that is reserved for the vehicle.
+    /// - `terms`: The reservation terms that are sent to the vehicle with the offer.
+    /// - `current_tick`: The tick in which the offer is made, the offer has to be accepted shortly after.
     /// 
     /// # Returns
     /// A new `ReservedOffer` instance.
-    pub fn new(vehicle_name: String, quantity: usize, price: f64) -> Self {
+    pub fn new(vehicle_name: String, quantity: usize, price: f64, port: usize, terms: ReservationTerms, current_tick: u64) -> Self {
         ReservedOffer {
             vehicle_name,
             quantity,
             price,
+            list_price: price,
+            port,
             was_accepted: false,
+            terms,
+            expires_at: current_tick + OFFER_VALIDITY_TICKS,
+            last_seen: None,
+            arrived_at: None,
+            arrival_tick: None,
+            holds_port: true,
         }
     }
+
+    /// # Returns
+    /// The name of the vehicle for which the offer is reserved.
+    pub fn get_vehicle_name(&self) -> &String {
+        &self.vehicle_name
+    }
+
+    /// # Returns
+    /// The price per kWh the vehicle was offered.
+    pub fn get_price(&self) -> f64 {
+        self.price
+    }
+
+    /// # Returns
+    /// The price per kWh of the original offer, before any negotiation.
+    pub fn get_list_price(&self) -> f64 {
+        self.list_price
+    }
+
+    /// # Returns
+    /// Whether the vehicle has accepted the offer.
+    pub fn was_accepted(&self) -> bool {
+        self.was_accepted
+    }
+
+    /// # Returns
+    /// Whether the vehicle has arrived at the charger and requested charge.
+    pub fn has_arrived(&self) -> bool {
+        self.last_seen.is_some()
+    }
+
+    /// # Returns
+    /// The tick of the first get request of the vehicle, `None` if it has not arrived yet.
+    pub fn get_arrived_at(&self) -> Option<u64> {
+        self.arrived_at
+    }
+
+    /// # Returns
+    /// The reservation terms that were sent to the vehicle.
+    pub fn get_terms(&self) -> ReservationTerms {
+        self.terms
+    }
 }
 
 impl ChargerHandler {
@@ -48,7 +174,7 @@ impl ChargerHandler {
     pub fn reserve_offer(&mut self, offer: ReservedOffer) {
         debug!("Reserving offer {:?}", offer);
         self.charger.reserve_charge(offer.quantity);
-        self.charger.reserve_port();
+        self.charger.reserve_port(offer.port);
         self.currently_reserved_for.push(offer);
     }
 
@@ -56,11 +182,12 @@ impl ChargerHandler {
     /// # Arguments
     /// * `vehicle_name` - The name of the vehicle for which the offer was reserved
     ///
-    /// This method will release the reserved offer and free up the port and charge.
+    /// This method will release the reserved offer and free up the port, if it is held for the vehicle, and the charge.
+    /// The session of the vehicle in the ledger is closed, so its receipt has to be sent before.
     /// If the offer is not found, it will log a debug message.
     pub fn release_offer(&mut self, vehicle_name: String, release_reserved_charge: bool) {
         let offer = match self.get_reserved_offer(vehicle_name.clone()) {
-            Some(o) => o,
+            Some(o) => o.clone(),
             None => {
                 debug!("ReservedOffer for {} not found in reserved offers", vehicle_name);
                 return;
@@ -70,10 +197,13 @@ impl ChargerHandler {
         debug!("Releasing ReservedOffer for {}", vehicle_name);
         if release_reserved_charge {
             self.charger
-                .release_reserved_charge(offer.quantity as usize);
+                .release_reserved_charge(offer.quantity);
+        }
+        if offer.holds_port {
+            self.charger.release_port(offer.port);
         }
-        self.charger.release_port();
 
+        self.ledger.close_session(&vehicle_name);
         self.currently_reserved_for.retain(|o| o.vehicle_name != vehicle_name);
     }
 
@@ -98,14 +228,438 @@ impl ChargerHandler {
     /// # Arguments
     /// - `vehicle_name` - The name of the vehicle
     pub fn accept_reserve(&mut self, vehicle_name: String) {
-        if self.get_reserved_offer(vehicle_name.clone()).is_some() {
-            debug!("Accepting reserved offer for {}", vehicle_name);
-            self.currently_reserved_for
-                .iter_mut()
-                .find(|o| o.vehicle_name == vehicle_name)
-                .map(|o| o.was_accepted = true);
-        } else {
-            debug!("Offer for {} not found for acceptance", vehicle_name);
+        let current_tick = self.current_tick;
+        match self.currently_reserved_for.iter_mut().find(|o| o.vehicle_name == vehicle_name) {
+            Some(offer) => {
+                debug!("Accepting reserved offer for {}", vehicle_name);
+                offer.was_accepted = true;
+                offer.expires_at = current_tick + offer.terms.reservation_ticks;
+            }
+            None => debug!("Offer for {} not found for acceptance", vehicle_name),
         }
     }
+
+    /// # Description
+    /// Updates when a vehicle that accepted our offer arrives.<br>
+    /// The reservation expires the grace period after the new arrival, so a vehicle that is delayed is no no-show.
+    /// If it arrives later than shortly from now, its port is lent to other vehicles until then.
+    /// If it arrives soon, the port is held for it again.
+    ///
+    /// # Arguments
+    /// - `vehicle_name` - The name of the vehicle
+    /// - `eta_ticks` - In how many ticks the vehicle expects to arrive
+    pub fn set_arrival(&mut self, vehicle_name: &str, eta_ticks: u64) {
+        let current_tick = self.current_tick;
+        let grace_ticks = self.reservation_policy.grace_ticks;
+        let Some(i) = self.currently_reserved_for.iter()
+            .position(|o| o.vehicle_name == vehicle_name && o.was_accepted && !o.has_arrived()) else {
+            debug!("{} sent its arrival, but has no booking with us", vehicle_name);
+            return;
+        };
+
+        let offer = &mut self.currently_reserved_for[i];
+        offer.arrival_tick = Some(current_tick + eta_ticks);
+        offer.expires_at = current_tick + eta_ticks + grace_ticks;
+        if offer.holds_port && eta_ticks > PORT_HOLD_AHEAD_TICKS {
+            debug!("{} arrives in {} ticks, lending its port until then", vehicle_name, eta_ticks);
+            offer.holds_port = false;
+            let port = offer.port;
+            self.charger.release_port(port);
+        } else if !offer.holds_port && eta_ticks <= PORT_HOLD_AHEAD_TICKS {
+            self.hold_port(i);
+        }
+    }
+
+    /// # Description
+    /// Holds the ports of the booked vehicles that arrive shortly, they were lent to other vehicles until now.
+    pub fn hold_booked_ports(&mut self) {
+        let hold_until = self.current_tick + PORT_HOLD_AHEAD_TICKS;
+        for i in 0..self.currently_reserved_for.len() {
+            let offer = &self.currently_reserved_for[i];
+            if !offer.holds_port && offer.arrival_tick.is_none_or(|arrival| arrival <= hold_until) {
+                self.hold_port(i);
+            }
+        }
+    }
+
+    /// # Description
+    /// Holds the port of a reserved offer again.<br>
+    /// If a vehicle it was lent to still uses it, another free port with the same connector is taken instead.
+    /// If there is none, the vehicle has to wait and it is tried again in the next tick.
+    ///
+    /// # Arguments
+    /// - `i` - The index of the reserved offer
+    ///
+    /// # Returns
+    /// Whether a port is held for the offer.
+    fn hold_port(&mut self, i: usize) -> bool {
+        let offer = &self.currently_reserved_for[i];
+        let mut port = offer.port;
+        if !self.charger.reserve_port(port) {
+            let connector = self.charger.get_port(port).get_connector();
+            match self.charger.find_free_port(&[connector], |_| f64::MAX) {
+                Some(free_port) => {
+                    self.charger.reserve_port(free_port);
+                    port = free_port;
+                }
+                None => {
+                    info!("The port for {} is still taken, it has to wait", offer.vehicle_name);
+                    return false;
+                }
+            }
+        }
+        let offer = &mut self.currently_reserved_for[i];
+        debug!("Holding port {} for {}", port, offer.vehicle_name);
+        offer.port = port;
+        offer.holds_port = true;
+        true
+    }
+
+    /// # Description
+    /// Finds the free port that charges the vehicle of a request the fastest.<br>
+    /// A port that is lent out until a booked vehicle arrives is only used if the vehicle is expected to be done by then.
+    /// Like for the reservation terms, the vehicle is assumed to drive slowly and to use the grace period.
+    ///
+    /// # Arguments
+    /// - `request` - The charge request of the vehicle
+    ///
+    /// # Returns
+    /// The index of the port, or `None` if no compatible port can be used.
+    pub fn find_port(&self, request: &ChargeRequest) -> Option<usize> {
+        let distance = road_distance(request.vehicle_position, self.charger.get_position());
+        let arrival = self.current_tick + self.reservation_policy.terms_for(distance).reservation_ticks;
+        self.charger.find_free_port_where(&request.connectors, |c| request.power_for(c), |port| {
+            self.booked_from(port).is_none_or(|booked_from| {
+                let charge_per_tick = (self.charger.charge_power(port, |c| request.power_for(c)) * TICK_AS_HOUR).max(1.0);
+                arrival + (request.charge_amount as f64 / charge_per_tick).ceil() as u64 <= booked_from
+            })
+        })
+    }
+
+    /// # Returns
+    /// The tick from which the port is held for a booked vehicle again, `None` if it isn't lent out.
+    fn booked_from(&self, port: usize) -> Option<u64> {
+        self.currently_reserved_for
+            .iter()
+            .filter(|o| o.port == port && !o.holds_port)
+            .filter_map(|o| o.arrival_tick)
+            .min()
+            .map(|arrival| arrival.saturating_sub(PORT_HOLD_AHEAD_TICKS))
+    }
+
+    /// # Description
+    /// Revises the price of an offer the vehicle did not accept yet, while they negotiate.<br>
+    /// The offer is held for the vehicle for another round.
+    ///
+    /// # Arguments
+    /// - `vehicle_name` - The name of the vehicle
+    /// - `price` - The revised price per kWh
+    ///
+    /// # Returns
+    /// The amount of charge the offer is for, or `None` if there is no open offer for the vehicle.
+    pub fn revise_offer(&mut self, vehicle_name: &str, price: f64) -> Option<usize> {
+        let current_tick = self.current_tick;
+        let offer = self.currently_reserved_for.iter_mut().find(|o| o.vehicle_name == vehicle_name && !o.was_accepted)?;
+        offer.price = price;
+        offer.expires_at = current_tick + OFFER_VALIDITY_TICKS;
+        Some(offer.quantity)
+    }
+
+    /// # Description
+    /// Hands out charge to a vehicle that arrived at the charger.<br>
+    /// The charge is limited by the power of the reserved port and the grid limit of the site.
+    /// A port that was lent out until the vehicle arrives is held for it again, nothing is handed out while it is still taken.
+    /// It is taken from the reservation of the vehicle first, which then no longer expires,
+    /// anything beyond it only from the charge that isn't reserved for other vehicles.
+    ///
+    /// # Arguments
+    /// - `vehicle_name` - The name of the vehicle
+    /// - `amount` - The amount of charge the vehicle requested, in kWh
+    ///
+    /// # Returns
+    /// The amount of charge that was handed out, in kWh.
+    pub fn take_reserved_charge(&mut self, vehicle_name: &str, amount: usize) -> usize {
+        let current_tick = self.current_tick;
+        let i = self.currently_reserved_for.iter().position(|o| o.vehicle_name == vehicle_name);
+        if let Some(i) = i {
+            let offer = &mut self.currently_reserved_for[i];
+            offer.last_seen = Some(current_tick);
+            offer.arrived_at.get_or_insert(current_tick);
+
+            // The vehicle came earlier than it estimated, it can't charge on a port another vehicle still uses
+            if !offer.holds_port && !self.hold_port(i) {
+                return 0;
+            }
+        }
+
+        let offer = i.map(|i| &self.currently_reserved_for[i]);
+        let port = offer.map(|o| o.port);
+        let reserved = offer.map_or(0, |o| o.quantity);
+        let amount = amount.min(self.charger.deliverable_charge(port));
+        let taken = self.charger.take_reserved_charge(amount, reserved);
+        if let Some(i) = i {
+            let offer = &mut self.currently_reserved_for[i];
+            offer.quantity = offer.quantity.saturating_sub(taken);
+        }
+        taken
+    }
+
+    /// # Description
+    /// Checks whether a vehicle is plugged into the charger, which it is once it arrived for its reservation.<br>
+    /// A plugged in vehicle is seen in this tick, so its reservation doesn't run out while it is parked.
+    ///
+    /// # Arguments
+    /// - `vehicle_name` - The name of the vehicle
+    ///
+    /// # Returns
+    /// Whether the vehicle is plugged in.
+    pub fn keep_plugged_in(&mut self, vehicle_name: &str) -> bool {
+        let current_tick = self.current_tick;
+        match self.currently_reserved_for.iter_mut().find(|o| o.vehicle_name == vehicle_name && o.has_arrived()) {
+            Some(offer) => {
+                offer.last_seen = Some(current_tick);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// # Description
+    /// Releases all reservations that expired.<br>
+    /// - Offers that were not accepted in time are released silently.
+    /// - Accepted reservations of vehicles that never arrived are no-shows, the no-show fee is recorded.
+    /// - Vehicles that arrived but stopped requesting charge for longer than the grace period are released as well,
+    ///   with the receipt of their session.
+    ///
+    /// # Returns
+    /// The reserved offers of the vehicles that did not show up, to notify them, and the receipts of the released sessions.
+    pub fn release_expired_offers(&mut self) -> (Vec<ReservedOffer>, Vec<ChargeReceipt>) {
+        let current_tick = self.current_tick;
+        let grace_ticks = self.reservation_policy.grace_ticks;
+        let expired: Vec<ReservedOffer> = self.currently_reserved_for
+            .iter()
+            .filter(|o| match o.last_seen {
+                Some(last_seen) => last_seen + grace_ticks < current_tick,
+                None => o.expires_at <= current_tick,
+            })
+            .cloned()
+            .collect();
+
+        let mut no_shows = Vec::new();
+        let mut receipts = Vec::new();
+        for offer in expired {
+            if !offer.was_accepted {
+                debug!("Offer for {} was not accepted in time", offer.vehicle_name);
+            } else if offer.has_arrived() {
+                info!("{} stopped charging without releasing its port", offer.vehicle_name);
+                receipts.extend(self.receipt(&offer.vehicle_name));
+            } else {
+                info!("{} did not show up, charging a no-show fee of {}€", offer.vehicle_name, offer.terms.no_show_fee);
+                self.ledger.record_fee(current_tick, offer.vehicle_name.clone(), offer.terms.no_show_fee);
+                no_shows.push(offer.clone());
+            }
+            self.release_offer(offer.vehicle_name.clone(), true);
+        }
+        (no_shows, receipts)
+    }
+
+    /// # Description
+    /// Puts a charge request on the waitlist of the charger.<br>
+    /// A vehicle that is already waiting keeps its position, only its request is updated.
+    ///
+    /// # Arguments
+    /// - `request` - The charge request of the vehicle
+    ///
+    /// # Returns
+    /// The position of the vehicle on the waitlist, starting at 0.
+    pub fn enqueue_waitlist(&mut self, request: ChargeRequest) -> usize {
+        match self.waitlist.iter().position(|r| r.vehicle_name == request.vehicle_name) {
+            Some(position) => {
+                self.waitlist[position] = request;
+                position
+            }
+            None => {
+                self.waitlist.push_back(request);
+                self.waitlist.len() - 1
+            }
+        }
+    }
+
+    /// # Description
+    /// Removes a vehicle from the waitlist of the charger.
+    ///
+    /// # Arguments
+    /// - `vehicle_name` - The name of the vehicle
+    ///
+    /// # Returns
+    /// Whether the vehicle was on the waitlist.
+    pub fn leave_waitlist(&mut self, vehicle_name: &str) -> bool {
+        let waiting = self.waitlist.len();
+        self.waitlist.retain(|r| r.vehicle_name != vehicle_name);
+        waiting != self.waitlist.len()
+    }
+
+    /// # Description
+    /// Estimates how many ticks a vehicle on the waitlist has to wait for an offer.<br>
+    /// Every reservation of a port the vehicle can use is expected to end once its vehicle arrived
+    /// and charged the reserved amount with the power of the port.
+    /// The waiting vehicles take the ports in the order they get free, if there are more vehicles than ports
+    /// they have to wait for more than one session.<br>
+    /// If there is a free port, the charger only lacks charge, which it buys in the next tick.
+    ///
+    /// # Arguments
+    /// - `position` - The position of the vehicle on the waitlist
+    /// - `connectors` - The connector types of the vehicle
+    ///
+    /// # Returns
+    /// The estimated amount of ticks until the vehicle gets an offer.
+    pub fn estimated_wait_ticks(&self, position: usize, connectors: &[ConnectorType]) -> u64 {
+        if self.charger.find_free_port(connectors, |_| f64::MAX).is_some() {
+            return 1;
+        }
+
+        let mut free_in: Vec<u64> = self.currently_reserved_for
+            .iter()
+            .filter(|o| connectors.contains(&self.charger.get_port(o.port).get_connector()))
+            .map(|o| {
+                let charge_per_tick = (self.charger.get_port(o.port).get_max_power() * TICK_AS_HOUR).max(1.0);
+                let arrival = if o.has_arrived() { 0 } else { o.expires_at.saturating_sub(self.current_tick) };
+                arrival + (o.quantity as f64 / charge_per_tick).ceil() as u64
+            })
+            .collect();
+        if free_in.is_empty() {
+            return 1;
+        }
+        free_in.sort_unstable();
+
+        let sessions_ahead = (position / free_in.len()) as u64;
+        let longest_session = *free_in.last().unwrap();
+        (free_in[position % free_in.len()] + sessions_ahead * longest_session).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ledger::Session, test_handler};
+
+    fn booked(handler: &mut ChargerHandler, no_show_fee: f64) {
+        let terms = ReservationTerms { reservation_ticks: 3, no_show_fee, cancellation_fee: 0.0 };
+        handler.reserve_offer(ReservedOffer::new("Vehicle".to_string(), 20, 0.4, 0, terms, handler.current_tick));
+        handler.accept_reserve("Vehicle".to_string());
+    }
+
+    #[test]
+    fn unaccepted_offer_expires_without_fee() {
+        let mut handler = test_handler();
+        let terms = ReservationTerms { reservation_ticks: 3, no_show_fee: 5.0, cancellation_fee: 0.0 };
+        handler.reserve_offer(ReservedOffer::new("Vehicle".to_string(), 20, 0.4, 0, terms, 0));
+
+        handler.current_tick = OFFER_VALIDITY_TICKS;
+        assert!(handler.release_expired_offers().0.is_empty());
+        assert!(handler.get_reserved_offer("Vehicle".to_string()).is_none());
+    }
+
+    #[test]
+    fn accepted_offer_expires_as_no_show() {
+        let mut handler = test_handler();
+        booked(&mut handler, 5.0);
+
+        handler.current_tick = 2;
+        assert!(handler.release_expired_offers().0.is_empty());
+        handler.current_tick = 3;
+        let (no_shows, receipts) = handler.release_expired_offers();
+        assert!(receipts.is_empty());
+        assert_eq!(no_shows.len(), 1);
+        assert_eq!(no_shows[0].get_terms().no_show_fee, 5.0);
+    }
+
+    #[test]
+    fn delayed_arrival_extends_reservation() {
+        let mut handler = test_handler();
+        booked(&mut handler, 5.0);
+        let grace_ticks = handler.reservation_policy.grace_ticks;
+
+        handler.set_arrival("Vehicle", 5);
+        handler.current_tick = 5 + grace_ticks - 1;
+        assert!(handler.release_expired_offers().0.is_empty());
+        handler.current_tick = 5 + grace_ticks;
+        assert_eq!(handler.release_expired_offers().0.len(), 1);
+    }
+
+    #[test]
+    fn late_arrival_lends_port_until_shortly_before() {
+        let mut handler = test_handler();
+        booked(&mut handler, 0.0);
+
+        handler.set_arrival("Vehicle", 5);
+        assert_eq!(handler.booked_from(0), Some(5 - PORT_HOLD_AHEAD_TICKS));
+        handler.current_tick = 5 - PORT_HOLD_AHEAD_TICKS;
+        handler.hold_booked_ports();
+        assert_eq!(handler.booked_from(0), None);
+    }
+
+    #[test]
+    fn early_arrival_waits_for_lent_port() {
+        let mut handler = test_handler();
+        handler.charger.add_charge(50);
+        booked(&mut handler, 0.0);
+        handler.set_arrival("Vehicle", 5);
+
+        // Both CCS ports are used by other vehicles
+        assert!(handler.charger.reserve_port(0));
+        assert!(handler.charger.reserve_port(1));
+        assert_eq!(handler.take_reserved_charge("Vehicle", 10), 0);
+        assert!(handler.get_reserved_offer("Vehicle".to_string()).unwrap().has_arrived());
+
+        handler.charger.release_port(1);
+        assert_eq!(handler.take_reserved_charge("Vehicle", 10), 10);
+        assert_eq!(handler.get_reserved_offer("Vehicle".to_string()).unwrap().port, 1);
+    }
+
+    #[test]
+    fn vehicle_that_stopped_charging_gets_receipt() {
+        let mut handler = test_handler();
+        booked(&mut handler, 5.0);
+        handler.current_tick = 1;
+        handler.take_reserved_charge("Vehicle", 0);
+        handler.ledger.record_sale(1, "Vehicle".to_string(), 10.0, 0.5);
+        handler.ledger.record_v2g_purchase("Vehicle".to_string(), 4.0, 0.25);
+
+        handler.current_tick = 2 + handler.reservation_policy.grace_ticks;
+        let (no_shows, receipts) = handler.release_expired_offers();
+        assert!(no_shows.is_empty());
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].energy, 10.0);
+        assert_eq!(receipts[0].cost, 5.0);
+        assert_eq!(receipts[0].earned, 1.0);
+        assert!(handler.get_reserved_offer("Vehicle".to_string()).is_none());
+        assert_eq!(handler.ledger.session("Vehicle"), Session::default());
+    }
+
+    #[test]
+    fn terms_cover_travel_and_grace() {
+        let policy = ReservationPolicy::default();
+        let one_tick = ASSUMED_VEHICLE_SPEED * TICK_AS_HOUR;
+        assert_eq!(policy.terms_for(0.0).reservation_ticks, policy.grace_ticks);
+        assert_eq!(policy.terms_for(one_tick * 2.5).reservation_ticks, 3 + policy.grace_ticks);
+
+        let policy = ReservationPolicy { no_show_fee: 5.0, cancellation_fee: 2.0, ..policy };
+        let terms = policy.terms_for(0.0);
+        assert_eq!((terms.no_show_fee, terms.cancellation_fee), (5.0, 2.0));
+    }
+
+    #[test]
+    fn released_offer_frees_port_and_charge() {
+        let mut handler = test_handler();
+        handler.charger.add_charge(50);
+        booked(&mut handler, 0.0);
+        assert_eq!(handler.charger.get_available_charge(), 30);
+        assert_eq!(handler.charger.get_reserved_ports(), 1);
+
+        handler.release_offer("Vehicle".to_string(), true);
+        assert!(handler.get_reserved_offer("Vehicle".to_string()).is_none());
+        assert_eq!(handler.charger.get_available_charge(), 50);
+        assert_eq!(handler.charger.get_reserved_ports(), 0);
+    }
 }
@@ -0,0 +1,119 @@
+use bytes::Bytes;
+use powercable::{
+    operator::{ChargeTransfer, ChargerStatus, RoutedRequest},
+    OPERATOR_CHARGER_STATUS,
+};
+use rumqttc::QoS;
+use tracing::{debug, info, trace};
+
+use crate::{car_handling::{handle_request, serve_waitlist}, ChargerHandler, SharedCharger};
+
+/// How many ticks a charger waits for the heartbeat of its operator, before it buys and sells on its own again
+const OPERATOR_TIMEOUT_TICKS: u64 = 2;
+
+impl ChargerHandler {
+    /// # Returns
+    /// Whether a charge point operator currently buys energy, sets prices and routes requests for the charger.
+    pub fn is_managed(&self) -> bool {
+        self.operator_seen_tick
+            .is_some_and(|tick| self.current_tick <= tick + OPERATOR_TIMEOUT_TICKS)
+    }
+}
+
+/// # Description
+/// Handles the heartbeat of a charge point operator.<br>
+/// If it is our operator, the charger lets it buy energy and route requests until the heartbeats stop.
+///
+/// # Arguments
+/// - `handler`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the name of the operator.
+pub async fn heartbeat_handler(handler: SharedCharger, payload: Bytes) {
+    let operator = String::from_utf8_lossy(&payload);
+    let mut handler = handler.lock().await;
+
+    if handler.operator.as_deref() != Some(operator.as_ref()) {
+        return;
+    }
+    if !handler.is_managed() {
+        info!("{} is managed by {} now", handler.charger.get_name(), operator);
+    }
+    handler.operator_seen_tick = Some(handler.current_tick);
+}
+
+/// # Description
+/// Reports the state of the charger to its operator.
+///
+/// # Arguments
+/// - `handler`: The charger handler containing the charger and its state.
+pub async fn publish_status(handler: &mut ChargerHandler) {
+    let operator = match &handler.operator {
+        Some(operator) => operator.clone(),
+        None => return,
+    };
+    let status = ChargerStatus {
+        charger_name: handler.charger.get_name().clone(),
+        operator,
+        position: handler.charger.get_position(),
+        current_charge: handler.charger.get_current_charge(),
+        capacity: handler.charger.get_capacity(),
+        available_charge: handler.charger.get_available_charge(),
+        grid_energy_per_tick: handler.charger.grid_energy_per_tick(),
+        connectors: handler.charger.get_connectors(),
+        free_connectors: handler.charger.get_free_connectors(),
+    };
+
+    handler.client.publish(
+        OPERATOR_CHARGER_STATUS,
+        QoS::ExactlyOnce,
+        false,
+        status.to_bytes(),
+    ).await.unwrap();
+    trace!("Published on topic {}: {:?}", OPERATOR_CHARGER_STATUS, status);
+}
+
+/// # Description
+/// Handles energy our operator moves to or from the charger.<br>
+/// Energy the operator bought on the market comes in through the grid connection like our own purchases.
+/// When the operator balances its batteries, the giving charger sells the energy to the receiving one.
+///
+/// # Arguments
+/// - `handler`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the charge transfer.
+pub async fn transfer_handler(handler: SharedCharger, payload: Bytes) {
+    let transfer = ChargeTransfer::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+    let name = handler.charger.get_name().clone();
+    let current_tick = handler.current_tick;
+
+    if transfer.from.as_ref() == Some(&name) {
+        let exported = handler.charger.export_charge(transfer.amount);
+        debug!("{} gave {} kWh to {} for {}", name, exported, transfer.to, transfer.operator);
+        handler.ledger.record_grid_sale(current_tick, transfer.to, exported as f64, transfer.price);
+    } else if transfer.to == name {
+        let imported = handler.charger.import_charge(transfer.amount);
+        debug!("{} got {} kWh from {}", name, imported, transfer.from.as_ref().unwrap_or(&transfer.operator));
+        if transfer.from.is_none() {
+            handler.consumed_last_tick += imported as f64;
+        }
+        let seller = transfer.from.unwrap_or(transfer.operator);
+        handler.ledger.record_purchase(current_tick, seller, imported as f64, transfer.price);
+        serve_waitlist(&mut handler).await;
+    }
+}
+
+/// # Description
+/// Handles a charge request our operator routed to us, like a request directly from the vehicle.
+///
+/// # Arguments
+/// - `handler`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the routed request.
+pub async fn route_handler(handler: SharedCharger, payload: Bytes) {
+    let routed = RoutedRequest::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+
+    if &routed.charger_name != handler.charger.get_name() {
+        return;
+    }
+    debug!("Our operator routed the request of {} to us", routed.request.vehicle_name);
+    handle_request(&mut handler, routed.request).await;
+}
@@ -0,0 +1,300 @@
+use std::fmt::Debug;
+
+use powercable::tickgen::hour_of_day;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::ChargerHandler;
+
+/// The lowest price a charger offers to buy energy for.
+/// At a certain point we run into float weirdness when transforming.
+const MIN_BUY_PRICE: f64 = 0.1;
+
+/// # Description
+/// The state of a charger a pricing strategy bases its prices on.
+///
+/// # Fields
+/// - `soc`: The state of charge of the charger (0.0 to 1.0).
+/// - `avg_purchase_price`: The average price the charger paid per kWh, `None` if it didn't buy anything yet.
+/// - `utilisation`: The share of reserved ports (0.0 to 1.0).
+/// - `hour`: The hour of the day in the simulation (0 to 23).
+#[derive(Debug, Clone, Copy)]
+pub struct PricingContext {
+    pub soc: f64,
+    pub avg_purchase_price: Option<f64>,
+    pub utilisation: f64,
+    pub hour: usize,
+}
+
+/// # Description
+/// A `PricingStrategy` decides which price a charger asks from vehicles and which price it offers to pay
+/// for energy on the market.
+pub trait PricingStrategy: Debug + Send + Sync {
+    /// # Returns
+    /// The price per kWh the charger asks from vehicles.
+    fn sell_price(&self, context: &PricingContext) -> f64;
+
+    /// # Returns
+    /// The price per kWh the charger offers to pay on the market, if it had the state of charge `soc_after`
+    /// after the purchase. Used to progressively reduce the price of buy offers.
+    fn buy_price(&self, context: &PricingContext, soc_after: f64) -> f64;
+}
+
+/// # Description
+/// The price falls linearly with the state of charge of the charger, a full charger is cheap.<br>
+/// Energy is bought the same way, the fuller the charger would be, the less it pays.
+#[derive(Debug, Clone, Copy)]
+pub struct SocLinear;
+
+impl PricingStrategy for SocLinear {
+    fn sell_price(&self, context: &PricingContext) -> f64 {
+        1.01 - context.soc// 1.01 so it never is 0.0, else all offers have the same price even if they are far away
+    }
+
+    fn buy_price(&self, _context: &PricingContext, soc_after: f64) -> f64 {
+        (1.0 - soc_after).max(MIN_BUY_PRICE)
+    }
+}
+
+/// # Description
+/// Vehicles pay what the charger paid for the energy on average plus a margin.<br>
+/// As long as the charger didn't buy anything, it prices like `SocLinear`.
+///
+/// # Fields
+/// - `margin`: The margin on top of the purchase price, 0.2 means 20%.
+#[derive(Debug, Clone, Copy)]
+pub struct CostPlus {
+    pub margin: f64,
+}
+
+impl PricingStrategy for CostPlus {
+    fn sell_price(&self, context: &PricingContext) -> f64 {
+        match context.avg_purchase_price {
+            Some(price) => price * (1.0 + self.margin),
+            None => SocLinear.sell_price(context),
+        }
+    }
+
+    fn buy_price(&self, context: &PricingContext, soc_after: f64) -> f64 {
+        SocLinear.buy_price(context, soc_after)
+    }
+}
+
+/// # Description
+/// Energy is more expensive in the peak hours of the day.
+/// The charger also pays less for energy during the peak, so it buys most of it off-peak.
+///
+/// # Fields
+/// - `peak_start`: The first hour of the peak.
+/// - `peak_end`: The first hour after the peak.
+/// - `peak_factor`: The factor the `SocLinear` price is multiplied with during the peak.
+/// - `off_peak_factor`: The factor the `SocLinear` price is multiplied with outside of the peak.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeOfUse {
+    pub peak_start: usize,
+    pub peak_end: usize,
+    pub peak_factor: f64,
+    pub off_peak_factor: f64,
+}
+
+impl TimeOfUse {
+    /// # Returns
+    /// Whether the hour lies in the peak, the peak may span midnight.
+    fn is_peak(&self, hour: usize) -> bool {
+        if self.peak_start <= self.peak_end {
+            (self.peak_start..self.peak_end).contains(&hour)
+        } else {
+            hour >= self.peak_start || hour < self.peak_end
+        }
+    }
+}
+
+impl PricingStrategy for TimeOfUse {
+    fn sell_price(&self, context: &PricingContext) -> f64 {
+        let factor = if self.is_peak(context.hour) { self.peak_factor } else { self.off_peak_factor };
+        SocLinear.sell_price(context) * factor
+    }
+
+    fn buy_price(&self, context: &PricingContext, soc_after: f64) -> f64 {
+        let price = SocLinear.buy_price(context, soc_after);
+        if self.is_peak(context.hour) {
+            (price / self.peak_factor).max(MIN_BUY_PRICE)
+        } else {
+            price
+        }
+    }
+}
+
+/// # Description
+/// The price rises with the utilisation of the ports, once it exceeds a threshold.<br>
+/// With all ports reserved, the `SocLinear` price is raised by the maximum surcharge.
+///
+/// # Fields
+/// - `threshold`: The utilisation from which on the surcharge applies (0.0 to 1.0).
+/// - `max_surcharge`: The surcharge with all ports reserved, 0.5 means 50%.
+#[derive(Debug, Clone, Copy)]
+pub struct Surge {
+    pub threshold: f64,
+    pub max_surcharge: f64,
+}
+
+impl PricingStrategy for Surge {
+    fn sell_price(&self, context: &PricingContext) -> f64 {
+        let surge = ((context.utilisation - self.threshold) / (1.0 - self.threshold).max(f64::EPSILON)).clamp(0.0, 1.0);
+        SocLinear.sell_price(context) * (1.0 + surge * self.max_surcharge)
+    }
+
+    fn buy_price(&self, context: &PricingContext, soc_after: f64) -> f64 {
+        SocLinear.buy_price(context, soc_after)
+    }
+}
+
+/// # Description
+/// The pricing configuration as received on the `CONFIG_CHARGER_PRICING` topic, e.g.
+/// `{"strategy": "cost_plus", "margin": 0.2}`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum PricingConfig {
+    SocLinear,
+    CostPlus {
+        margin: f64,
+    },
+    TimeOfUse {
+        peak_start: usize,
+        peak_end: usize,
+        peak_factor: f64,
+        off_peak_factor: f64,
+    },
+    Surge {
+        threshold: f64,
+        max_surcharge: f64,
+    },
+}
+
+impl PricingConfig {
+    /// # Returns
+    /// The pricing strategy described by the configuration.
+    pub fn into_strategy(self) -> Box<dyn PricingStrategy> {
+        match self {
+            PricingConfig::SocLinear => Box::new(SocLinear),
+            PricingConfig::CostPlus { margin } => Box::new(CostPlus { margin }),
+            PricingConfig::TimeOfUse { peak_start, peak_end, peak_factor, off_peak_factor } => {
+                Box::new(TimeOfUse { peak_start, peak_end, peak_factor, off_peak_factor })
+            }
+            PricingConfig::Surge { threshold, max_surcharge } => Box::new(Surge { threshold, max_surcharge }),
+        }
+    }
+}
+
+impl ChargerHandler {
+    /// # Returns
+    /// The current state of the charger for the pricing strategy.
+    pub fn pricing_context(&self) -> PricingContext {
+        let ports = self.charger.get_ports().max(1);
+        PricingContext {
+            soc: self.charger.get_charge_percentage(),
+            avg_purchase_price: self.ledger.avg_purchase_price(),
+            utilisation: self.charger.get_reserved_ports() as f64 / ports as f64,
+            hour: hour_of_day(self.current_timestamp),
+        }
+    }
+
+    /// # Returns
+    /// The price per kWh the charger currently asks from vehicles.
+    pub fn sell_price(&self) -> f64 {
+        self.pricing.sell_price(&self.pricing_context())
+    }
+
+    /// # Returns
+    /// Whether selling at the current price covers what the charger paid for its energy on average.
+    pub fn sells_profitably(&self) -> bool {
+        self.ledger.avg_purchase_price().is_none_or(|cost| self.sell_price() >= cost)
+    }
+
+    /// # Returns
+    /// The price per kWh the charger offers to pay for energy, if it had `amount` kWh more charge.
+    pub fn buy_price(&self, amount: usize) -> f64 {
+        let soc_after = (self.charger.get_current_charge() + amount) as f64 / self.charger.get_capacity() as f64;
+        let price = self.pricing.buy_price(&self.pricing_context(), soc_after);
+        debug!("{} would pay {} if it had {} charge added", self.charger.get_name(), price, amount);
+        price
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_handler;
+
+    fn context(soc: f64) -> PricingContext {
+        PricingContext { soc, avg_purchase_price: None, utilisation: 0.0, hour: 12 }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn soc_linear_is_cheap_when_full() {
+        assert_close(SocLinear.sell_price(&context(0.0)), 1.01);
+        assert_close(SocLinear.sell_price(&context(0.75)), 0.26);
+        assert_close(SocLinear.sell_price(&context(1.0)), 0.01);
+        assert_close(SocLinear.buy_price(&context(0.0), 0.25), 0.75);
+        assert_eq!(SocLinear.buy_price(&context(0.0), 0.95), MIN_BUY_PRICE);
+    }
+
+    #[test]
+    fn cost_plus_adds_its_margin_to_the_purchase_price() {
+        let strategy = CostPlus { margin: 0.2 };
+        assert_close(strategy.sell_price(&context(0.5)), SocLinear.sell_price(&context(0.5)));
+
+        let context = PricingContext { avg_purchase_price: Some(0.3), ..context(0.5) };
+        assert_close(strategy.sell_price(&context), 0.36);
+        assert_close(strategy.buy_price(&context, 0.25), 0.75);
+    }
+
+    #[test]
+    fn time_of_use_is_expensive_in_the_peak() {
+        let strategy = TimeOfUse { peak_start: 22, peak_end: 6, peak_factor: 2.0, off_peak_factor: 0.5 };
+        let night = PricingContext { hour: 23, ..context(0.5) };
+        let early = PricingContext { hour: 5, ..context(0.5) };
+        let day = PricingContext { hour: 6, ..context(0.5) };
+
+        assert_close(strategy.sell_price(&night), 1.02);
+        assert_close(strategy.sell_price(&early), 1.02);
+        assert_close(strategy.sell_price(&day), 0.255);
+        assert_close(strategy.buy_price(&night, 0.25), 0.375);
+        assert_close(strategy.buy_price(&day, 0.25), 0.75);
+        assert_eq!(strategy.buy_price(&night, 0.85), MIN_BUY_PRICE);
+    }
+
+    #[test]
+    fn surge_rises_above_the_threshold() {
+        let strategy = Surge { threshold: 0.5, max_surcharge: 0.5 };
+        let at = |utilisation| strategy.sell_price(&PricingContext { utilisation, ..context(0.01) });
+
+        assert_close(at(0.0), 1.0);
+        assert_close(at(0.5), 1.0);
+        assert_close(at(0.75), 1.25);
+        assert_close(at(1.0), 1.5);
+    }
+
+    #[test]
+    fn hour_comes_from_the_timestamp() {
+        let mut handler = test_handler();
+        handler.current_timestamp = 3 * 24 * 3_600_000 + 17 * 3_600_000 + 45 * 60_000;
+        assert_eq!(handler.pricing_context().hour, 17);
+    }
+
+    #[test]
+    fn selling_below_the_purchase_price_is_not_profitable() {
+        let mut handler = test_handler();
+        assert!(handler.sells_profitably());
+
+        // An empty charger asks 1.01€ with `SocLinear`
+        handler.ledger.record_purchase(0, "Seller".to_string(), 10.0, 1.5);
+        assert!(!handler.sells_profitably());
+        handler.ledger.record_purchase(0, "Seller".to_string(), 40.0, 0.5);
+        assert!(handler.sells_profitably());
+    }
+}
@@ -0,0 +1,319 @@
+use std::error::Error;
+
+use powercable::{charger::ConnectorType, Position};
+use tracing::{debug, info, warn};
+
+use crate::charger::ChargePort;
+
+/// The buffer battery every charger from the registry gets in kWh, the registry doesn't know about batteries
+pub const REGISTRY_CHARGER_CAPACITY: usize = 300;
+
+/// # Description
+/// A charging site as listed in the Ladesäulenregister of the Bundesnetzagentur.
+///
+/// # Fields
+/// - `position`: The geographical position of the site.
+/// - `operator`: The operator of the site.
+/// - `power`: The rated power of the whole site in kW.
+/// - `ports`: The charging points of the site.
+#[derive(Debug, Clone)]
+pub struct ChargerSite {
+    pub position: Position,
+    pub operator: String,
+    pub power: usize,
+    pub ports: Vec<ChargePort>,
+}
+
+/// # Description
+/// A rectangle on the map, sites outside of it are skipped.
+///
+/// # Fields
+/// - `south_west`: The south west corner of the rectangle.
+/// - `north_east`: The north east corner of the rectangle.
+#[derive(Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub south_west: Position,
+    pub north_east: Position,
+}
+
+impl BoundingBox {
+    /// # Returns
+    /// Whether the position lies inside the rectangle.
+    pub fn contains(&self, position: Position) -> bool {
+        (self.south_west.latitude..=self.north_east.latitude).contains(&position.latitude)
+            && (self.south_west.longitude..=self.north_east.longitude).contains(&position.longitude)
+    }
+}
+
+/// # Description
+/// Decides which sites of the registry are spawned as chargers.
+///
+/// # Fields
+/// - `bounding_box`: Only sites inside of it are used, all sites if `None`.
+/// - `every_nth`: Only every n-th of the remaining sites is used, to thin out the about 100.000 sites of Germany.
+/// - `limit`: The maximum number of sites, all if `None`.
+#[derive(Debug, Clone, Copy)]
+pub struct RegistryFilter {
+    pub bounding_box: Option<BoundingBox>,
+    pub every_nth: usize,
+    pub limit: Option<usize>,
+}
+
+impl Default for RegistryFilter {
+    fn default() -> Self {
+        RegistryFilter {
+            bounding_box: None,
+            every_nth: 1,
+            limit: None,
+        }
+    }
+}
+
+/// # Description
+/// The columns of the registry we need, found by their header.
+/// The registry comes in Latin-1, so we match on the parts of the header names without umlauts.
+struct Columns {
+    operator: usize,
+    latitude: usize,
+    longitude: usize,
+    power: usize,
+    ports: usize,
+    connectors: Vec<usize>,
+    port_powers: Vec<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &[String]) -> Option<Self> {
+        let find = |name: &str| header.iter().position(|h| h.contains(name));
+        Some(Columns {
+            operator: find("Betreiber")?,
+            latitude: find("Breitengrad")?,
+            longitude: find("ngengrad")?,
+            power: find("Nennleistung")?,
+            ports: find("Anzahl Ladepunkte")?,
+            connectors: (1..=4).map_while(|i| find(&format!("Steckertypen{}", i))).collect(),
+            port_powers: (1..=4).map_while(|i| find(&format!("P{} [kW]", i))).collect(),
+        })
+    }
+}
+
+/// # Description
+/// Parses a number of the registry, which uses a decimal comma.
+fn parse_number(value: Option<&String>) -> Option<f64> {
+    value?.trim().replace(',', ".").parse().ok()
+}
+
+/// # Description
+/// Maps the plug description of the registry, like "AC Typ 2 Steckdose, DC Kupplung Combo", to a connector.<br>
+/// A charging point with several plugs gets its DC plug, as that is what fast charging vehicles are looking for.
+fn parse_connector(plugs: &str) -> ConnectorType {
+    if plugs.contains("Combo") || plugs.contains("CCS") {
+        ConnectorType::Ccs
+    } else if plugs.contains("CHAdeMO") {
+        ConnectorType::Chademo
+    } else {
+        ConnectorType::Type2
+    }
+}
+
+/// # Description
+/// Turns a row of the registry into a charging site.<br>
+/// The power of a charging point is taken from its own column, or shared equally from the rated power of the site.
+fn parse_site(columns: &Columns, row: &[String]) -> Option<ChargerSite> {
+    let position = Position::new(parse_number(row.get(columns.latitude))?, parse_number(row.get(columns.longitude))?);
+    let power = parse_number(row.get(columns.power))?;
+    let port_count = parse_number(row.get(columns.ports)).unwrap_or(1.0).max(1.0) as usize;
+
+    let ports = (0..port_count)
+        .map(|i| {
+            let plugs = columns.connectors.get(i).and_then(|&c| row.get(c)).map(String::as_str).unwrap_or("");
+            let port_power = columns.port_powers.get(i)
+                .and_then(|&c| parse_number(row.get(c)))
+                .unwrap_or(power / port_count as f64);
+            ChargePort::new(parse_connector(plugs), port_power)
+        })
+        .collect();
+
+    Some(ChargerSite {
+        position,
+        operator: row.get(columns.operator).cloned().unwrap_or_default(),
+        power: power.ceil() as usize,
+        ports,
+    })
+}
+
+/// # Description
+/// Reads a CSV export of the Ladesäulenregister of the Bundesnetzagentur.<br>
+/// The preamble above the header is skipped, rows that can't be parsed are skipped with a warning.
+///
+/// # Arguments
+/// - `path`: The path to the CSV file.
+/// - `filter`: Which of the sites are used.
+///
+/// # Returns
+/// The charging sites that passed the filter, in the order of the registry.
+pub fn read_registry(path: &str, filter: &RegistryFilter) -> Result<Vec<ChargerSite>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .delimiter(b';')
+        .from_path(path)?;
+
+    let mut columns = None;
+    let mut sites = Vec::new();
+    let mut matching = 0;
+    for record in reader.byte_records() {
+        let row: Vec<String> = record?.iter().map(|field| String::from_utf8_lossy(field).into_owned()).collect();
+
+        let Some(columns) = &columns else {
+            columns = Columns::from_header(&row);
+            continue;
+        };
+
+        let site = match parse_site(columns, &row) {
+            Some(site) => site,
+            None => {
+                warn!("Skipping unreadable registry row: {:?}", row);
+                continue;
+            }
+        };
+        if filter.bounding_box.is_some_and(|b| !b.contains(site.position)) {
+            continue;
+        }
+
+        matching += 1;
+        if (matching - 1) % filter.every_nth.max(1) != 0 {
+            continue;
+        }
+        debug!("Read charger site from registry: {:?}", site);
+        sites.push(site);
+
+        if filter.limit.is_some_and(|limit| sites.len() >= limit) {
+            break;
+        }
+    }
+
+    if columns.is_none() {
+        return Err(format!("No header found in charger registry {}", path).into());
+    }
+    info!("Read {} charger sites from {}", sites.len(), path);
+    Ok(sites)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::charger::Charger;
+
+    const HEADER: &[u8] = b"Betreiber;Breitengrad;L\xe4ngengrad;Nennleistung Ladeeinrichtung [kW];Anzahl Ladepunkte;Steckertypen1;P1 [kW];Steckertypen2;P2 [kW]\n";
+
+    /// Writes a registry with a preamble and the given rows to a temporary file.
+    fn write_registry(name: &str, rows: &[&[u8]]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("charger_registry_{}_{}.csv", name, std::process::id()));
+        let mut contents = b"Lades\xe4ulenregister;;;\nStand: 01.04.2025;;;\n".to_vec();
+        contents.extend_from_slice(HEADER);
+        for row in rows {
+            contents.extend_from_slice(row);
+            contents.push(b'\n');
+        }
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn read(name: &str, rows: &[&[u8]], filter: &RegistryFilter) -> Vec<ChargerSite> {
+        let path = write_registry(name, rows);
+        let sites = read_registry(path.to_str().unwrap(), filter).unwrap();
+        std::fs::remove_file(path).unwrap();
+        sites
+    }
+
+    /// A row with one site at the given latitude
+    fn row_at(latitude: &str) -> Vec<u8> {
+        format!("Betreiber;{};13,40;50;1;DC Kupplung Combo;50;;", latitude).into_bytes()
+    }
+
+    #[test]
+    fn parses_semicolon_separated_row() {
+        let sites = read("row", &[b"\"Meier; S\xf6hne\";52,52;13,40;150;2;AC Typ 2 Steckdose, DC Kupplung Combo;150;DC CHAdeMO;50"], &RegistryFilter::default());
+
+        assert_eq!(sites.len(), 1);
+        let site = &sites[0];
+        assert!(site.operator.starts_with("Meier; S"));
+        assert_eq!(site.position, Position::new(52.52, 13.40));
+        assert_eq!(site.power, 150);
+        assert_eq!(site.ports.len(), 2);
+        assert_eq!(site.ports[0].get_connector(), ConnectorType::Ccs);
+        assert_eq!(site.ports[0].get_max_power(), 150.0);
+        assert_eq!(site.ports[1].get_connector(), ConnectorType::Chademo);
+        assert_eq!(site.ports[1].get_max_power(), 50.0);
+    }
+
+    #[test]
+    fn reads_latin1_header_and_fields() {
+        let sites = read("latin1", &[b"Stadtwerke M\xfcnchen;48,14;11,58;22;1;AC Typ 2 Steckdose;22;;"], &RegistryFilter::default());
+
+        assert_eq!(sites.len(), 1);
+        assert!(sites[0].operator.starts_with("Stadtwerke M"));
+        assert_eq!(sites[0].position, Position::new(48.14, 11.58));
+        assert_eq!(sites[0].ports[0].get_connector(), ConnectorType::Type2);
+    }
+
+    #[test]
+    fn shares_site_power_without_port_powers() {
+        let sites = read("shared", &[b"Betreiber;52,52;13,40;44;4;;;;"], &RegistryFilter::default());
+
+        assert_eq!(sites[0].ports.len(), 4);
+        assert!(sites[0].ports.iter().all(|p| p.get_max_power() == 11.0));
+    }
+
+    #[test]
+    fn skips_unreadable_rows() {
+        let sites = read("unreadable", &[b"Betreiber;keine Angabe;13,40;50;1;;;;", &row_at("52,52")], &RegistryFilter::default());
+        assert_eq!(sites.len(), 1);
+    }
+
+    #[test]
+    fn filters_by_bounding_box_every_nth_and_limit() {
+        let rows: Vec<Vec<u8>> = ["50,0", "52,1", "52,2", "52,3", "52,4", "52,5", "55,0"].iter().map(|l| row_at(l)).collect();
+        let rows: Vec<&[u8]> = rows.iter().map(Vec::as_slice).collect();
+        let bounding_box = BoundingBox { south_west: Position::new(52.0, 13.0), north_east: Position::new(53.0, 14.0) };
+        let latitudes = |sites: Vec<ChargerSite>| sites.iter().map(|s| s.position.latitude).collect::<Vec<f64>>();
+
+        let filter = RegistryFilter { bounding_box: Some(bounding_box), ..RegistryFilter::default() };
+        assert_eq!(latitudes(read("bbox", &rows, &filter)), vec![52.1, 52.2, 52.3, 52.4, 52.5]);
+
+        let filter = RegistryFilter { bounding_box: Some(bounding_box), every_nth: 2, limit: None };
+        assert_eq!(latitudes(read("nth", &rows, &filter)), vec![52.1, 52.3, 52.5]);
+
+        let filter = RegistryFilter { bounding_box: Some(bounding_box), every_nth: 2, limit: Some(2) };
+        assert_eq!(latitudes(read("limit", &rows, &filter)), vec![52.1, 52.3]);
+    }
+
+    #[test]
+    fn fails_without_header() {
+        let path = std::env::temp_dir().join(format!("charger_registry_no_header_{}.csv", std::process::id()));
+        std::fs::write(&path, "a;b;c\n1;2;3\n").unwrap();
+        let result = read_registry(path.to_str().unwrap(), &RegistryFilter::default());
+        std::fs::remove_file(path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn small_ac_site_buys_energy() {
+        let path = write_registry("small_ac", &[b"Stadtwerke;52,52;13,40;22;2;AC Typ 2 Steckdose;11;AC Typ 2 Steckdose;11"]);
+        let sites = read_registry(path.to_str().unwrap(), &RegistryFilter::default()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let site = sites.into_iter().next().unwrap();
+        assert_eq!(site.power, 22);
+
+        // The grid connection takes the rated power, the package is bought once enough was saved up
+        let mut charger = Charger::new("Charger".to_string(), site.position, site.power, site.power, REGISTRY_CHARGER_CAPACITY, site.ports);
+        assert_eq!(charger.amount_of_needed_packages(), 0);
+        charger.store_grid_charge();
+        assert_eq!(charger.amount_of_needed_packages(), 1);
+    }
+}
@@ -0,0 +1,194 @@
+use bytes::Bytes;
+use powercable::{Offer, ACCEPT_BUY_OFFER_TOPIC};
+use rumqttc::QoS;
+use serde::Deserialize;
+use tracing::{debug, info, trace, warn};
+
+use crate::{v2g::{finish_v2g_sale, sell_v2g}, ChargerHandler, SharedCharger};
+
+/// # Description
+/// The `StoragePolicy` defines whether and how much of its battery a charger sells back to the grid.<br>
+/// It can be configured on the `CONFIG_CHARGER_STORAGE` topic.
+///
+/// # Fields
+/// - `enabled`: Whether the charger sells energy on the market at all.
+/// - `reserve`: The state of charge (0.0 to 1.0) the charger keeps for vehicles, it never sells below it.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct StoragePolicy {
+    pub enabled: bool,
+    pub reserve: f64,
+}
+
+impl Default for StoragePolicy {
+    fn default() -> Self {
+        StoragePolicy {
+            enabled: false,
+            reserve: 0.5,
+        }
+    }
+}
+
+impl ChargerHandler {
+    /// # Returns
+    /// The charge in kWh the charger can sell to the grid from its battery without falling below its reserve,
+    /// limited by the grid connection. Charge that is reserved for vehicles is never sold.
+    pub fn sellable_charge(&self) -> usize {
+        if !self.storage_policy.enabled {
+            return 0;
+        }
+        let reserve = (self.charger.get_capacity() as f64 * self.storage_policy.reserve).ceil() as usize;
+        self.charger.get_available_charge()
+            .min(self.charger.get_current_charge())
+            .saturating_sub(reserve)
+            .min(self.charger.grid_energy_left())
+    }
+
+    /// # Returns
+    /// The buy offer on the market that pays the most and more than the charger paid for its energy on average,
+    /// `None` if there is no such offer, the charger didn't buy any energy yet or can't sell enough for it.
+    fn best_buy_offer(&self) -> Option<Offer> {
+        let min_price = self.ledger.avg_purchase_price()?;
+        let sellable = self.sellable_charge() as f64;
+        self.sell_offer_handler
+            .get_all_offers()
+            .into_iter()
+            .filter(|offer| !self.sell_offer_handler.has_sent_offer(offer.get_id()))
+            .filter(|offer| offer.get_price() > min_price && offer.get_amount() <= sellable)
+            .max_by(|a, b| a.get_price().total_cmp(&b.get_price()))
+            .cloned()
+    }
+}
+
+/// # Description
+/// Sells energy from the battery to the grid, like a turbine does, as long as the charger stays above its reserve.<br>
+/// The best paying buy offers are accepted first, the energy is taken from the battery right away
+/// and is given back if the buyer acknowledges someone else.
+///
+/// # Arguments
+/// - `handler`: The charger handler containing the charger and its state.
+pub async fn sell_to_grid(handler: &mut ChargerHandler) {
+    while let Some(mut offer) = handler.best_buy_offer() {
+        handler.charger.export_charge(offer.get_amount() as usize);
+        offer.set_accepted_by(handler.charger.get_name().clone());
+        handler.sell_offer_handler.add_sent_offer(offer.clone());
+        debug!("{} offers {} kWh to {} for {}€", handler.charger.get_name(), offer.get_amount(), offer.get_id(), offer.get_price());
+
+        handler.client.publish(
+            ACCEPT_BUY_OFFER_TOPIC,
+            QoS::ExactlyOnce,
+            false,
+            offer.to_bytes(),
+        ).await.unwrap();
+        trace!("Published on topic {}: {:?}", ACCEPT_BUY_OFFER_TOPIC, offer);
+    }
+}
+
+/// # Returns
+/// Whether the buy offer comes from a consumer, their offer ids are `<consumer type>-<index>`.
+fn is_consumer_offer(offer: &Offer) -> bool {
+    offer.get_id().rsplit_once('-').is_some_and(|(buyer, _)| matches!(buyer, "H" | "G" | "L"))
+}
+
+/// # Description
+/// Collects the buy offers on the market the charger could sell energy to.<br>
+/// Only consumers are sold to, buy offers of chargers, our own included, and of operators are ignored,
+/// they would just pass the energy on to the next charger.
+///
+/// # Arguments
+/// - `handler`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the buy offer.
+pub async fn buy_offer_handler(handler: SharedCharger, payload: Bytes) {
+    let offer = Offer::from_bytes(payload).unwrap();
+    if !is_consumer_offer(&offer) {
+        return;
+    }
+    let mut handler = handler.lock().await;
+    handler.sell_offer_handler.add_offer(offer);
+}
+
+/// # Description
+/// Handles the acknowledgement of a buy offer we wanted to sell energy to.<br>
+/// If the buyer chose us, the sale is recorded in the ledger.
+/// Otherwise the energy goes back into the battery and is offered to the remaining buy offers.
+/// Sales of energy of plugged in vehicles are finished by `finish_v2g_sale`.
+///
+/// # Arguments
+/// - `handler`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the acknowledged offer.
+pub async fn ack_sell_handler(handler: SharedCharger, payload: Bytes) {
+    let offer = Offer::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+
+    if !handler.sell_offer_handler.has_sent_offer(offer.get_id()) {
+        return;
+    }
+
+    let ack_for = match offer.get_ack_for() {
+        Some(ack_for) => ack_for.clone(),
+        None => {
+            warn!("Received ACK for offer {} without ack_for field", offer.get_id());
+            return;
+        }
+    };
+
+    if let Some(vehicle_name) = handler.v2g.sales.remove(offer.get_id()) {
+        let sold = &ack_for == handler.charger.get_name();
+        finish_v2g_sale(&mut handler, &offer, vehicle_name, sold).await;
+        if !sold {
+            sell_v2g(&mut handler).await;
+        }
+        return;
+    }
+
+    if &ack_for == handler.charger.get_name() {
+        info!("{} sold {} kWh to the grid for {}€", ack_for, offer.get_amount(), offer.get_price());
+        let current_tick = handler.current_tick;
+        handler.ledger.record_grid_sale(current_tick, offer.get_id().to_string(), offer.get_amount(), offer.get_price());
+        handler.sold_to_grid += offer.get_amount();
+    } else {
+        debug!("Offer {} went to {}, putting the energy back into the battery", offer.get_id(), ack_for);
+        handler.charger.cancel_export(offer.get_amount() as usize);
+        sell_to_grid(&mut handler).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_handler;
+
+    fn buy_offer(id: &str, price: f64) -> Offer {
+        Offer::new(id.to_string(), price, 10.0, 52.52, 13.40)
+    }
+
+    #[test]
+    fn only_consumers_are_sold_to() {
+        assert!(is_consumer_offer(&buy_offer("H-0", 0.5)));
+        assert!(is_consumer_offer(&buy_offer("L-12", 0.5)));
+        assert!(!is_consumer_offer(&buy_offer("Charger Foo-Bar-0", 0.5)));
+        assert!(!is_consumer_offer(&buy_offer("Operator 1-0", 0.5)));
+    }
+
+    #[test]
+    fn sells_only_above_purchase_price() {
+        let mut handler = test_handler();
+        handler.storage_policy.enabled = true;
+        handler.charger.add_charge(300);
+        handler.sell_offer_handler.add_offer(buy_offer("H-0", 0.3));
+        assert!(handler.best_buy_offer().is_none()); // nothing bought yet
+
+        handler.ledger.record_purchase(0, "Turbine".to_string(), 10.0, 0.4);
+        assert!(handler.best_buy_offer().is_none());
+
+        handler.sell_offer_handler.add_offer(buy_offer("G-0", 0.5));
+        assert_eq!(handler.best_buy_offer().unwrap().get_id(), "G-0");
+    }
+
+    #[test]
+    fn storage_is_not_sold_by_default() {
+        let mut handler = test_handler();
+        handler.charger.add_charge(300);
+        assert_eq!(handler.sellable_charge(), 0);
+    }
+}
@@ -1,15 +1,20 @@
 use bytes::Bytes;
 use tracing::{debug, info, trace, warn};
 use powercable::{
+    charger::{CancelReason, ChargeCancel},
     offer::structure::OFFER_PACKAGE_SIZE,
     tickgen::{Phase, TickPayload, TICK_AS_SEC},
-    ChartEntry, Offer, ACK_ACCEPT_BUY_OFFER_TOPIC, BUY_OFFER_TOPIC, POWER_CHARGER_TOPIC,
-    POWER_LOCATION_TOPIC, POWER_TRANSFORMER_CONSUMPTION_TOPIC,
+    ChartEntry, Offer, ACK_ACCEPT_BUY_OFFER_TOPIC, BUY_OFFER_TOPIC, CHARGER_CANCEL, CHARGER_STATS, POWER_CHARGER_TOPIC,
+    POWER_CHARGER_GRID_LOAD_TOPIC, POWER_LOCATION_TOPIC, POWER_TRANSFORMER_CONSUMPTION_TOPIC, POWER_TRANSFORMER_GENERATION_TOPIC,
 };
 use rumqttc::QoS;
+use serde::Deserialize;
 use serde_json::json;
 
-use crate::SharedCharger;
+use crate::{
+    car_handling::{publish_receipt, serve_waitlist}, offer_handling::ReservationPolicy, operator_handling::publish_status, pricing::PricingConfig,
+    storage::{sell_to_grid, StoragePolicy}, v2g::sell_v2g, SharedCharger,
+};
 
 /**
  * This function delegates the tick event handling based on the phase of the tick.
@@ -27,6 +32,12 @@ pub async fn tick_handler(handler: SharedCharger, payload: Bytes) {
             commerce_tick(handler, payload).await;
         }
         Phase::PowerImport => {
+            // The market round is over, vehicles offer their energy again in the next tick
+            {
+                let mut handler = handler.lock().await;
+                handler.v2g.offers.clear();
+                handler.v2g.sales.clear();
+            }
             publish_location(handler.clone()).await;
         }
     }
@@ -58,9 +69,119 @@ async fn process_tick(handler: SharedCharger, payload: TickPayload) {
             .unwrap();
 
         handler.consumed_last_tick = 0.0;
+
+        // Publish the amount of power we sold back to the grid in the last tick
+        handler
+            .client
+            .publish(
+                POWER_TRANSFORMER_GENERATION_TOPIC,
+                QoS::ExactlyOnce,
+                false,
+                ChartEntry::new(
+                    handler.charger.get_name().clone(),
+                    handler.sold_to_grid as isize,
+                    last_timestamp,
+                )
+                .to_string(),
+            )
+            .await
+            .unwrap();
+
+        handler.sold_to_grid = 0.0;
+
+        // Publish the energy of plugged in vehicles we fed into the grid in the last tick
+        handler
+            .client
+            .publish(
+                POWER_TRANSFORMER_GENERATION_TOPIC,
+                QoS::ExactlyOnce,
+                false,
+                ChartEntry::new(
+                    format!("V2G {}", handler.charger.get_name()),
+                    handler.v2g.supplied as isize,
+                    last_timestamp,
+                )
+                .to_string(),
+            )
+            .await
+            .unwrap();
+
+        handler.v2g.supplied = 0.0;
+
+        // Publish the load on our grid connection in the last tick
+        handler
+            .client
+            .publish(
+                POWER_CHARGER_GRID_LOAD_TOPIC,
+                QoS::ExactlyOnce,
+                false,
+                ChartEntry::new(
+                    handler.charger.get_name().clone(),
+                    handler.charger.get_grid_load() as isize,
+                    last_timestamp,
+                )
+                .to_string(),
+            )
+            .await
+            .unwrap();
+
+        // Publish the ledger of the last tick
+        let charger_name = handler.charger.get_name().clone();
+        let stats = handler.ledger.stats(
+            charger_name,
+            payload.tick.saturating_sub(1),
+            last_timestamp,
+        );
+        handler
+            .client
+            .publish(CHARGER_STATS, QoS::ExactlyOnce, false, stats.to_json())
+            .await
+            .unwrap();
+        trace!("Published on topic {}: {:?}", CHARGER_STATS, stats);
+    }
+
+    // Release reservations that ran out, send the receipts of sessions that ended without a release, tell the vehicles that didn't show up, hold the ports for vehicles that arrive soon
+    // and offer the free ports to the waitlist
+    {
+        let mut handler = handler.lock().await;
+        handler.current_tick = payload.tick;
+        handler.current_timestamp = payload.timestamp;
+        handler.charger.reset_delivered_charge();
+
+        let (no_shows, receipts) = handler.release_expired_offers();
+        for receipt in receipts {
+            publish_receipt(&mut handler, receipt).await;
+        }
+        for offer in no_shows {
+            let cancel = ChargeCancel::new(
+                handler.charger.get_name().clone(),
+                offer.get_vehicle_name().clone(),
+                CancelReason::NoShow,
+                offer.get_terms().no_show_fee,
+            );
+            handler
+                .client
+                .publish(CHARGER_CANCEL, QoS::ExactlyOnce, false, cancel.to_bytes())
+                .await
+                .unwrap();
+            debug!("Published on topic {}: {:?}", CHARGER_CANCEL, cancel);
+        }
+        handler.hold_booked_ports();
+        serve_waitlist(&mut handler).await;
     }
 
     handler.lock().await.offer_handler.remove_all_offers();
+    handler.lock().await.sell_offer_handler.remove_all_offers();
+
+    // Our operator buys the energy for us
+    {
+        let mut handler = handler.lock().await;
+        publish_status(&mut handler).await;
+        if handler.is_managed() {
+            debug!("{} leaves buying energy to its operator", handler.charger.get_name());
+            return;
+        }
+    }
     let mut packages_askable = handler.lock().await.charger.amount_of_needed_packages();
     debug!(
         "Packages askable: {}, current charge: {}",
@@ -81,9 +202,7 @@ async fn process_tick(handler: SharedCharger, payload: TickPayload) {
         let offer_id = format!("{}-{}", handler.charger.get_name().clone(), i);
         let offer = Offer::new(
             offer_id,
-            handler
-                .charger
-                .get_price_if_had_charge(i * OFFER_PACKAGE_SIZE as usize),
+            handler.buy_price(i * OFFER_PACKAGE_SIZE as usize),
             OFFER_PACKAGE_SIZE,
             handler.charger.get_latitude(),
             handler.charger.get_longitude(),
@@ -108,7 +227,12 @@ async fn process_tick(handler: SharedCharger, payload: TickPayload) {
  * This function handles the tick event for commerce phase.
  */
 async fn commerce_tick(handler: SharedCharger, tick_payload: TickPayload) {
-    let handler = handler.lock().await;
+    let mut handler = handler.lock().await;
+    handler.charger.store_grid_charge();
+    if !handler.is_managed() {
+        sell_to_grid(&mut handler).await;
+    }
+    sell_v2g(&mut handler).await;
     let current_power = handler.charger.get_current_charge();
 
     handler
@@ -154,6 +278,12 @@ pub async fn accept_offer_handler(handler: SharedCharger, payload: Bytes) {
     }
 
     if !handler.offer_handler.has_sent_offer(offer.get_id()) {
+        // Without an ACK the seller keeps the energy, so only offers the grid connection can still take are acknowledged
+        if !handler.charger.can_import(offer.get_amount() as usize) {
+            debug!("Grid connection of {} is used up, not accepting offer {}", handler.charger.get_name(), offer.get_id());
+            return;
+        }
+
         offer.set_ack_for(offer.get_accepted_by().unwrap().clone());
 
         handler.offer_handler.add_sent_offer(offer.clone());
@@ -169,8 +299,11 @@ pub async fn accept_offer_handler(handler: SharedCharger, payload: Bytes) {
             .unwrap();
         debug!("ACK for offer {} sent", offer.get_id());
 
-        handler.charger.add_charge(offer.get_amount() as usize);
+        handler.charger.import_charge(offer.get_amount() as usize);
         handler.consumed_last_tick += offer.get_amount();
+        let current_tick = handler.current_tick;
+        let seller = offer.get_accepted_by().cloned().unwrap_or_default();
+        handler.ledger.record_purchase(current_tick, seller, offer.get_amount(), offer.get_price());
     }
 }
 
@@ -185,16 +318,20 @@ async fn publish_location(handler: SharedCharger) {
     let longitude = handler.charger.get_longitude();
     let percentage = handler.charger.get_charge_percentage() * 100.0;
     let visible = handler.charger.visible;
+    let operator = handler.operator.clone();
     let client = &mut handler.client;
-    let location_payload = json!({
+    let mut location_payload = json!({
         "name" : name,
         "lat": latitude,
         "lon": longitude,
         "icon": ":battery:",
         "label": format!("{:.1}%", percentage),
         "deleted": !visible,
-    })
-    .to_string();
+    });
+    if let Some(operator) = operator {
+        location_payload["operator"] = json!(operator);
+    }
+    let location_payload = location_payload.to_string();
 
     client
         .publish(
@@ -221,4 +358,113 @@ pub async fn show_handler(handler: SharedCharger, payload: Bytes) {
     let value = serde_json::from_slice(&payload).unwrap();
     handler.charger.visible = value;
     debug!("{} visibility set to: {}", handler.charger.get_name(), value);
-}
\ No newline at end of file
+}
+
+/// # Description
+/// The `reservation_policy_handler` function processes incoming reservation configuration messages.<br>
+/// It replaces the reservation policy of the charger, which is used for all following offers.<br>
+/// It is called when a message is received on the `CONFIG_CHARGER_RESERVATION` topic.<br>
+///
+/// # Arguments
+/// - `handler`: A shared reference to the charger handler, which contains the charger instance.
+/// - `payload`: The incoming payload containing the reservation policy in JSON format.
+pub async fn reservation_policy_handler(handler: SharedCharger, payload: Bytes) {
+    let policy: ReservationPolicy = match serde_json::from_slice(&payload) {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Invalid reservation policy: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    debug!("{} reservation policy set to: {:?}", handler.charger.get_name(), policy);
+    handler.reservation_policy = policy;
+}
+
+/// # Description
+/// The `storage_policy_handler` function processes incoming storage configuration messages.<br>
+/// It replaces the storage policy of the charger, which decides whether and down to which reserve
+/// the charger sells its battery to the grid.<br>
+/// It is called when a message is received on the `CONFIG_CHARGER_STORAGE` topic.<br>
+///
+/// # Arguments
+/// - `handler`: A shared reference to the charger handler, which contains the charger instance.
+/// - `payload`: The incoming payload containing the storage policy in JSON format.
+pub async fn storage_policy_handler(handler: SharedCharger, payload: Bytes) {
+    let policy: StoragePolicy = match serde_json::from_slice(&payload) {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Invalid storage policy: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    debug!("{} storage policy set to: {:?}", handler.charger.get_name(), policy);
+    handler.storage_policy = policy;
+}
+
+/// # Description
+/// The grid configuration as received on the `CONFIG_CHARGER_GRID` topic, e.g. `{"grid_limit": 150, "capacity": 300}`.
+///
+/// # Fields
+/// - `grid_limit`: The power limit of the grid connection in kW, unchanged if not given.
+/// - `capacity`: The capacity of the buffer battery in kWh, unchanged if not given.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct GridConfig {
+    pub grid_limit: Option<usize>,
+    pub capacity: Option<usize>,
+}
+
+/// # Description
+/// The `grid_handler` function processes incoming grid configuration messages.<br>
+/// It changes the grid connection limit and the size of the buffer battery of the charger.<br>
+/// It is called when a message is received on the `CONFIG_CHARGER_GRID` topic.<br>
+///
+/// # Arguments
+/// - `handler`: A shared reference to the charger handler, which contains the charger instance.
+/// - `payload`: The incoming payload containing the grid configuration in JSON format.
+pub async fn grid_handler(handler: SharedCharger, payload: Bytes) {
+    let config: GridConfig = match serde_json::from_slice(&payload) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid grid configuration: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    debug!("{} grid configuration set to: {:?}", handler.charger.get_name(), config);
+    if let Some(grid_limit) = config.grid_limit {
+        handler.charger.set_grid_limit(grid_limit);
+    }
+    if let Some(capacity) = config.capacity {
+        handler.charger.set_capacity(capacity);
+    }
+}
+
+/// # Description
+/// The `pricing_handler` function processes incoming pricing configuration messages.<br>
+/// It replaces the pricing strategy of the charger, which is used for all following offers and purchases.<br>
+/// It is called when a message is received on the `CONFIG_CHARGER_PRICING` topic, or on the topic of this charger
+/// `CONFIG_CHARGER_PRICING/<charger name>`. A configuration for this charger takes precedence over the one for all chargers.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the charger handler, which contains the charger instance.
+/// - `payload`: The incoming payload containing the pricing configuration in JSON format.
+/// - `own`: Whether the configuration is meant for this charger only.
+pub async fn pricing_handler(handler: SharedCharger, payload: Bytes, own: bool) {
+    let config: PricingConfig = match serde_json::from_slice(&payload) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid pricing configuration: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    if handler.has_own_pricing && !own {
+        debug!("{} keeps its own pricing, ignoring {:?}", handler.charger.get_name(), config);
+        return;
+    }
+    debug!("{} pricing set to: {:?}", handler.charger.get_name(), config);
+    handler.pricing = config.into_strategy();
+    handler.has_own_pricing = own;
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use powercable::{
+    charger::{V2gDischarge, V2gOffer},
+    Offer, ACCEPT_BUY_OFFER_TOPIC, CHARGER_V2G_DISCHARGE,
+};
+use rumqttc::QoS;
+use serde::Deserialize;
+use tracing::{debug, info, trace, warn};
+
+use crate::{ChargerHandler, SharedCharger};
+
+/// # Description
+/// The `V2gPolicy` defines whether a charger passes energy of parked vehicles on to the power market.<br>
+/// It can be configured on the `CONFIG_CHARGER_V2G` topic for all chargers or on `CONFIG_CHARGER_V2G/<charger name>` for one.
+///
+/// # Fields
+/// - `enabled`: Whether the charger takes V2G offers of vehicles at all.
+/// - `fee`: The share (0.0 to 1.0) of the market price the charger keeps for its service.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct V2gPolicy {
+    pub enabled: bool,
+    pub fee: f64,
+}
+
+impl Default for V2gPolicy {
+    fn default() -> Self {
+        V2gPolicy {
+            enabled: true,
+            fee: 0.1,
+        }
+    }
+}
+
+/// # Description
+/// The V2G offers of the vehicles plugged into the charger in the current tick
+/// and the market sales that wait for the acknowledgement of their buyer.
+///
+/// # Fields
+/// - `offers`: The V2G offers of this tick by vehicle name, with the energy that is not sold yet.
+/// - `sales`: The vehicle whose energy was offered to a buy offer, by the id of the buy offer.
+/// - `supplied`: The energy of vehicles fed into the grid since the last tick in kWh.
+#[derive(Debug, Clone, Default)]
+pub struct V2gState {
+    pub offers: HashMap<String, V2gOffer>,
+    pub sales: HashMap<String, String>,
+    pub supplied: f64,
+}
+
+impl ChargerHandler {
+    /// # Returns
+    /// The price per kWh a vehicle gets for a market price, after the fee of the charger.
+    fn vehicle_price(&self, market_price: f64) -> f64 {
+        market_price * (1.0 - self.v2g_policy.fee)
+    }
+
+    /// # Returns
+    /// The best paying buy offer on the market that pays the vehicle at least its minimum price,
+    /// `None` if there is no such offer or the vehicle can't feed in enough for it.
+    fn best_v2g_buy_offer(&self, v2g_offer: &V2gOffer) -> Option<Offer> {
+        self.sell_offer_handler
+            .get_all_offers()
+            .into_iter()
+            .filter(|offer| !self.sell_offer_handler.has_sent_offer(offer.get_id()))
+            .filter(|offer| offer.get_amount() <= v2g_offer.amount as f64)
+            .filter(|offer| self.vehicle_price(offer.get_price()) >= v2g_offer.min_price)
+            .max_by(|a, b| a.get_price().total_cmp(&b.get_price()))
+            .cloned()
+    }
+}
+
+/// # Description
+/// Sells the energy parked vehicles offered on the power market, like the charger sells its own battery.<br>
+/// The energy is booked on the grid connection of the charger and only taken from the vehicle
+/// once the buyer acknowledges us.
+///
+/// # Arguments
+/// - `handler`: The charger handler containing the charger and its state.
+pub async fn sell_v2g(handler: &mut ChargerHandler) {
+    let vehicles: Vec<String> = handler.v2g.offers.keys().cloned().collect();
+    for vehicle_name in vehicles {
+        loop {
+            let v2g_offer = handler.v2g.offers[&vehicle_name].clone();
+            let Some(mut offer) = handler.best_v2g_buy_offer(&v2g_offer) else {
+                break;
+            };
+            let amount = offer.get_amount() as usize;
+            if !handler.charger.feed_in(amount) {
+                debug!("{} can't feed in more energy of vehicles in this tick", handler.charger.get_name());
+                return;
+            }
+
+            handler.v2g.offers.get_mut(&vehicle_name).unwrap().amount -= amount;
+            handler.v2g.sales.insert(offer.get_id().to_string(), vehicle_name.clone());
+            offer.set_accepted_by(handler.charger.get_name().clone());
+            handler.sell_offer_handler.add_sent_offer(offer.clone());
+            debug!("{} offers {} kWh of {} to {} for {}€", handler.charger.get_name(), amount, vehicle_name, offer.get_id(), offer.get_price());
+
+            handler.client.publish(
+                ACCEPT_BUY_OFFER_TOPIC,
+                QoS::ExactlyOnce,
+                false,
+                offer.to_bytes(),
+            ).await.unwrap();
+        }
+    }
+}
+
+/// # Description
+/// Finishes a V2G sale after the buyer acknowledged a buy offer we offered energy of a vehicle to.<br>
+/// If the buyer chose us, the vehicle is told to discharge and gets paid with its receipt, the fee of the charger is recorded.
+/// Otherwise the grid connection is freed and the energy is offered to the vehicle again.
+///
+/// # Arguments
+/// - `handler`: The charger handler containing the charger and its state.
+/// - `offer`: The acknowledged buy offer.
+/// - `vehicle_name`: The vehicle whose energy was offered.
+/// - `sold`: Whether the buyer chose us.
+pub async fn finish_v2g_sale(handler: &mut ChargerHandler, offer: &Offer, vehicle_name: String, sold: bool) {
+    let amount = offer.get_amount() as usize;
+    if !sold {
+        debug!("Offer {} went to someone else, {} keeps its energy", offer.get_id(), vehicle_name);
+        handler.charger.cancel_feed_in(amount);
+        if let Some(v2g_offer) = handler.v2g.offers.get_mut(&vehicle_name) {
+            v2g_offer.amount += amount;
+        }
+        return;
+    }
+
+    let price = handler.vehicle_price(offer.get_price());
+    info!("{} fed {} kWh of {} into the grid for {}€", handler.charger.get_name(), amount, vehicle_name, price);
+    let current_tick = handler.current_tick;
+    let margin = (offer.get_price() - price) * offer.get_amount();
+    handler.ledger.record_v2g_margin(current_tick, margin);
+    handler.ledger.record_v2g_purchase(vehicle_name.clone(), offer.get_amount(), price);
+    handler.v2g.supplied += offer.get_amount();
+
+    let discharge = V2gDischarge {
+        charger_name: handler.charger.get_name().clone(),
+        vehicle_name,
+        amount,
+        price,
+    };
+    handler
+        .client
+        .publish(CHARGER_V2G_DISCHARGE, QoS::ExactlyOnce, false, discharge.to_bytes())
+        .await
+        .unwrap();
+    trace!("Published on topic {}: {:?}", CHARGER_V2G_DISCHARGE, discharge);
+}
+
+/// # Description
+/// Collects the V2G offer of a vehicle that is plugged into this charger.<br>
+/// Offers of vehicles without an arrived reservation are ignored, as they aren't connected to us.
+///
+/// # Arguments
+/// - `handler`: The shared charger handler containing the charger and its state.
+/// - `payload`: The payload containing the V2G offer.
+pub async fn v2g_offer_handler(handler: SharedCharger, payload: Bytes) {
+    let v2g_offer = V2gOffer::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+
+    if &v2g_offer.charger_name != handler.charger.get_name() {
+        return;
+    }
+    if !handler.v2g_policy.enabled {
+        debug!("{} doesn't take V2G offers, ignoring {}", handler.charger.get_name(), v2g_offer.vehicle_name);
+        return;
+    }
+    if !handler.keep_plugged_in(&v2g_offer.vehicle_name) {
+        warn!("{} is not plugged into {}, ignoring its V2G offer", v2g_offer.vehicle_name, handler.charger.get_name());
+        return;
+    }
+
+    trace!("Received V2G offer: {:?}", v2g_offer);
+    handler.v2g.offers.insert(v2g_offer.vehicle_name.clone(), v2g_offer);
+}
+
+/// # Description
+/// The `v2g_policy_handler` function processes incoming V2G configuration messages.<br>
+/// It is called when a message is received on the `CONFIG_CHARGER_V2G` topic, or on the topic of this charger
+/// `CONFIG_CHARGER_V2G/<charger name>`. A configuration for this charger takes precedence over the one for all chargers.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the charger handler, which contains the charger instance.
+/// - `payload`: The incoming payload containing the V2G policy in JSON format.
+/// - `own`: Whether the configuration is meant for this charger only.
+pub async fn v2g_policy_handler(handler: SharedCharger, payload: Bytes, own: bool) {
+    let policy: V2gPolicy = match serde_json::from_slice(&payload) {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Invalid V2G policy: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    if handler.has_own_v2g_policy && !own {
+        debug!("{} keeps its own V2G policy, ignoring {:?}", handler.charger.get_name(), policy);
+        return;
+    }
+    debug!("{} V2G policy set to: {:?}", handler.charger.get_name(), policy);
+    handler.v2g_policy = policy;
+    handler.has_own_v2g_policy = own;
+}
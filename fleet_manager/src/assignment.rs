@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+
+use powercable::{charger::ChargeOffer, fleet::FleetVehicleStatus, routing::road_distance, tickgen::TICK_AS_HOUR};
+use serde::Deserialize;
+
+/// Added to the cost of letting a nearly empty vehicle wait, so it gets a charger before all others
+const URGENT_WAIT_PENALTY: f64 = 1e6;
+/// Cost of a charger slot the vehicle has no offer for or can't reach
+const INFEASIBLE: f64 = 1e9;
+/// Tiny extra cost of waiting, so a vehicle charges now if waiting gains nothing
+const WAIT_TIE_BREAK: f64 = 1e-6;
+
+/// # Description
+/// What the fleet manager minimises when it assigns the vehicles to chargers.
+///
+/// # Variants
+/// - `Cost`: The money the fleet pays for the energy.
+/// - `Downtime`: The hours the vehicles are out of service, driving to the charger, waiting and charging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FleetObjective {
+    #[default]
+    Cost,
+    Downtime,
+}
+
+/// # Description
+/// The `FleetPolicy` defines how a fleet manager assigns its vehicles to chargers.<br>
+/// It can be configured on the `CONFIG_FLEET` topic.
+///
+/// # Fields
+/// - `objective`: What the fleet minimises.
+/// - `vehicles_per_charger`: How many vehicles of the fleet are sent to the same charger in a tick.
+///   The second one is served a tick after the first, as they share the power of the site.
+/// - `urgent_soc`: Below this state of charge a vehicle is assigned before all others and never waits.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FleetPolicy {
+    pub objective: FleetObjective,
+    pub vehicles_per_charger: usize,
+    pub urgent_soc: f64,
+}
+
+impl Default for FleetPolicy {
+    fn default() -> Self {
+        FleetPolicy {
+            objective: FleetObjective::Cost,
+            vehicles_per_charger: 1,
+            urgent_soc: 0.15,
+        }
+    }
+}
+
+/// # Description
+/// A vehicle of the fleet that looks for a charger, with the offers the chargers made for it.
+///
+/// # Fields
+/// - `status`: The last reported state of the vehicle.
+/// - `offers`: The offers of this tick.
+/// - `distances`: The road distance from the vehicle to the charger of each offer in km.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub status: FleetVehicleStatus,
+    pub offers: Vec<ChargeOffer>,
+    distances: Vec<f64>,
+}
+
+impl Candidate {
+    pub fn new(status: FleetVehicleStatus, offers: Vec<ChargeOffer>) -> Self {
+        let distances = offers.iter().map(|o| road_distance(status.position, o.charger_position)).collect();
+        Candidate { status, offers, distances }
+    }
+
+    /// # Returns
+    /// The offers of chargers the vehicle can reach with its battery, with their index.
+    fn reachable(&self) -> impl Iterator<Item = (usize, &ChargeOffer)> {
+        self.offers.iter().enumerate().filter(|(j, _)| self.distances[*j] <= self.status.range)
+    }
+}
+
+/// # Description
+/// What a choice costs the fleet.
+///
+/// # Fields
+/// - `cost`: The money paid for the energy in €.
+/// - `downtime`: The hours the vehicle is out of service.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Outcome {
+    pub cost: f64,
+    pub downtime: f64,
+}
+
+impl Outcome {
+    pub fn add(&mut self, other: Outcome) {
+        self.cost += other.cost;
+        self.downtime += other.downtime;
+    }
+}
+
+/// # Description
+/// The offers the vehicles of the fleet take in a tick.
+///
+/// # Fields
+/// - `choices`: The index of the offer each candidate takes, `None` if it waits for the next tick.
+/// - `outcome`: What the choices cost the fleet. A vehicle that waits is counted with its best offer a tick later.
+/// - `waiting`: The number of vehicles that wait for the next tick.
+/// - `crowded`: The number of vehicles sent to a charger that already got as many vehicles of the fleet as allowed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    pub choices: Vec<Option<usize>>,
+    pub outcome: Outcome,
+    pub waiting: usize,
+    pub crowded: usize,
+}
+
+impl FleetPolicy {
+    /// # Description
+    /// Calculates what an offer costs the fleet if the vehicle is the `slot`-th vehicle of the fleet at the charger.
+    ///
+    /// # Arguments
+    /// - `candidate`: The vehicle.
+    /// - `j`: The index of the offer.
+    /// - `slot`: How many vehicles of the fleet are served at the charger before this one.
+    pub fn outcome(&self, candidate: &Candidate, j: usize, slot: usize) -> Outcome {
+        let offer = &candidate.offers[j];
+        let drive_hours = candidate.distances[j] / candidate.status.speed.max(1.0);
+        let charge_hours = offer.charge_amount as f64 / offer.charge_power.max(1.0);
+        let wait_hours = (offer.estimated_wait_ticks + slot as u64) as f64 * TICK_AS_HOUR;
+        Outcome {
+            cost: offer.charge_price * offer.charge_amount as f64,
+            downtime: drive_hours + wait_hours + charge_hours,
+        }
+    }
+
+    fn value(&self, outcome: Outcome) -> f64 {
+        match self.objective {
+            FleetObjective::Cost => outcome.cost,
+            FleetObjective::Downtime => outcome.downtime,
+        }
+    }
+
+    /// # Returns
+    /// The index of the reachable offer the vehicle would take on its own, `None` if it can't reach any.
+    fn best_offer(&self, candidate: &Candidate) -> Option<usize> {
+        candidate
+            .reachable()
+            .map(|(j, _)| (j, self.value(self.outcome(candidate, j, 0))))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(j, _)| j)
+    }
+
+    /// # Returns
+    /// What waiting for the next tick costs the vehicle: its best offer, a tick later.
+    fn deferral(&self, candidate: &Candidate) -> Outcome {
+        match self.best_offer(candidate) {
+            Some(j) => {
+                let mut outcome = self.outcome(candidate, j, 0);
+                outcome.downtime += TICK_AS_HOUR;
+                outcome
+            }
+            None => Outcome::default(),
+        }
+    }
+
+    /// # Description
+    /// Lets every vehicle take its best offer on its own, as vehicles without a fleet manager do.<br>
+    /// Vehicles that picked the same charger are served one after the other, the closest first.
+    ///
+    /// # Arguments
+    /// - `candidates`: The vehicles that look for a charger.
+    ///
+    /// # Returns
+    /// The plan of the independent choices, to compare the joint assignment against.
+    pub fn independent(&self, candidates: &[Candidate]) -> Plan {
+        let choices: Vec<Option<usize>> = candidates.iter().map(|c| self.best_offer(c)).collect();
+
+        let mut arrivals: HashMap<&str, Vec<(f64, usize, usize)>> = HashMap::new();
+        for (i, choice) in choices.iter().enumerate() {
+            if let Some(j) = *choice {
+                let candidate = &candidates[i];
+                arrivals
+                    .entry(candidate.offers[j].charger_name.as_str())
+                    .or_default()
+                    .push((candidate.distances[j], i, j));
+            }
+        }
+
+        let mut plan = Plan { choices, ..Plan::default() };
+        for mut vehicles in arrivals.into_values() {
+            vehicles.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (slot, (_, i, j)) in vehicles.into_iter().enumerate() {
+                plan.outcome.add(self.outcome(&candidates[i], j, slot));
+                if slot >= self.vehicles_per_charger {
+                    plan.crowded += 1;
+                }
+            }
+        }
+        plan
+    }
+
+    /// # Description
+    /// Assigns the vehicles to chargers and time slots together, minimising the objective of the whole fleet.<br>
+    /// Every charger has `vehicles_per_charger` slots, each a tick after the one before. A vehicle can also wait
+    /// for the next tick, at the cost of its best offer a tick later, unless it is urgent.
+    /// The assignment is solved with the Hungarian method.
+    ///
+    /// # Arguments
+    /// - `candidates`: The vehicles that look for a charger.
+    ///
+    /// # Returns
+    /// The plan of the joint assignment.
+    pub fn joint(&self, candidates: &[Candidate]) -> Plan {
+        let mut chargers: Vec<&str> = candidates
+            .iter()
+            .flat_map(|c| c.offers.iter().map(|o| o.charger_name.as_str()))
+            .collect();
+        chargers.sort();
+        chargers.dedup();
+        let slots = self.vehicles_per_charger.max(1);
+        let charger_columns = chargers.len() * slots;
+
+        // One column per slot of a charger, and one to wait for each vehicle
+        let mut best_in_slot = vec![vec![None; charger_columns]; candidates.len()];
+        let costs: Vec<Vec<f64>> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, candidate)| {
+                let mut row = vec![INFEASIBLE; charger_columns + candidates.len()];
+                for (j, offer) in candidate.reachable() {
+                    let k = chargers.binary_search(&offer.charger_name.as_str()).unwrap();
+                    for slot in 0..slots {
+                        let value = self.value(self.outcome(candidate, j, slot));
+                        if value < row[k * slots + slot] {
+                            row[k * slots + slot] = value;
+                            best_in_slot[i][k * slots + slot] = Some(j);
+                        }
+                    }
+                }
+                let penalty = if candidate.status.soc < self.urgent_soc { URGENT_WAIT_PENALTY } else { 0.0 };
+                let wait = self.value(self.deferral(candidate)) + WAIT_TIE_BREAK + penalty;
+                row[charger_columns..].fill(wait);
+                row
+            })
+            .collect();
+
+        let mut plan = Plan::default();
+        for (i, column) in hungarian(&costs).into_iter().enumerate() {
+            let candidate = &candidates[i];
+            match best_in_slot[i].get(column).copied().flatten() {
+                Some(j) => {
+                    plan.outcome.add(self.outcome(candidate, j, column % slots));
+                    plan.choices.push(Some(j));
+                }
+                None => {
+                    if self.best_offer(candidate).is_some() {
+                        plan.waiting += 1;
+                        plan.outcome.add(self.deferral(candidate));
+                    }
+                    plan.choices.push(None);
+                }
+            }
+        }
+        plan
+    }
+}
+
+/// # Description
+/// Solves the assignment problem with the Hungarian method, in O(n² m).
+///
+/// # Arguments
+/// - `costs`: The cost of assigning each row to each column, with at least as many columns as rows.
+///
+/// # Returns
+/// The column assigned to each row, so that the sum of the costs is minimal.
+fn hungarian(costs: &[Vec<f64>]) -> Vec<usize> {
+    let rows = costs.len();
+    let columns = costs.first().map_or(0, Vec::len);
+
+    // Potentials of the rows and columns and the row matched to each column, all shifted by one
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; columns + 1];
+    let mut matched = vec![0; columns + 1];
+    let mut way = vec![0; columns + 1];
+    for row in 1..=rows {
+        matched[0] = row;
+        let mut j0 = 0;
+        let mut min_slack = vec![f64::INFINITY; columns + 1];
+        let mut used = vec![false; columns + 1];
+        loop {
+            used[j0] = true;
+            let i0 = matched[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=columns {
+                if used[j] {
+                    continue;
+                }
+                let slack = costs[i0 - 1][j - 1] - u[i0] - v[j];
+                if slack < min_slack[j] {
+                    min_slack[j] = slack;
+                    way[j] = j0;
+                }
+                if min_slack[j] < delta {
+                    delta = min_slack[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=columns {
+                if used[j] {
+                    u[matched[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_slack[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if matched[j0] == 0 {
+                break;
+            }
+        }
+        // Flip the augmenting path
+        while j0 != 0 {
+            let j1 = way[j0];
+            matched[j0] = matched[j1];
+            j0 = j1;
+        }
+    }
+
+    let mut assignment = vec![0; rows];
+    for (j, &row) in matched.iter().enumerate().skip(1) {
+        if row != 0 {
+            assignment[row - 1] = j - 1;
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use powercable::{
+        charger::{ConnectorType, ReservationTerms},
+        Position,
+    };
+
+    const TERMS: ReservationTerms = ReservationTerms { reservation_ticks: 4, no_show_fee: 0.0, cancellation_fee: 0.0 };
+
+    fn status(name: &str, latitude: f64, soc: f64) -> FleetVehicleStatus {
+        FleetVehicleStatus {
+            vehicle_name: name.to_string(),
+            fleet: "fleet".to_string(),
+            position: Position::new(latitude, 13.40),
+            soc,
+            range: 100.0,
+            speed: 50.0,
+            searching: true,
+        }
+    }
+
+    fn offer(charger: &str, latitude: f64, price: f64) -> ChargeOffer {
+        ChargeOffer::new(charger.to_string(), String::new(), price, 20, Position::new(latitude, 13.40), TERMS, ConnectorType::Ccs, 50.0, 0)
+    }
+
+    #[test]
+    fn urgent_vehicle_never_waits() {
+        let policy = FleetPolicy { objective: FleetObjective::Downtime, ..FleetPolicy::default() };
+        // The relaxed vehicle is closer, but the nearly empty one gets the only slot
+        let candidates = [
+            Candidate::new(status("relaxed", 52.51, 0.5), vec![offer("charger", 52.50, 0.4)]),
+            Candidate::new(status("urgent", 52.53, 0.1), vec![offer("charger", 52.50, 0.4)]),
+        ];
+
+        let plan = policy.joint(&candidates);
+
+        assert_eq!(plan.choices, vec![None, Some(0)]);
+        assert_eq!(plan.waiting, 1);
+    }
+
+    #[test]
+    fn crowded_charger_is_spread_over_the_fleet() {
+        let policy = FleetPolicy { objective: FleetObjective::Downtime, ..FleetPolicy::default() };
+        let offers = vec![offer("near", 52.50, 0.4), offer("far", 52.48, 0.4)];
+        let candidates = [
+            Candidate::new(status("first", 52.51, 0.5), offers.clone()),
+            Candidate::new(status("second", 52.52, 0.5), offers),
+        ];
+
+        let independent = policy.independent(&candidates);
+        assert_eq!(independent.choices, vec![Some(0), Some(0)]);
+        assert_eq!(independent.crowded, 1);
+
+        let joint = policy.joint(&candidates);
+        assert_eq!(joint.choices, vec![Some(0), Some(1)]);
+        assert_eq!(joint.waiting, 0);
+        assert_eq!(joint.crowded, 0);
+        assert!(joint.outcome.downtime < independent.outcome.downtime);
+    }
+
+    #[test]
+    fn vehicle_waits_rather_than_paying_more() {
+        let policy = FleetPolicy::default();
+        let offers = vec![offer("cheap", 52.50, 0.3), offer("expensive", 52.50, 0.4)];
+        let candidates = [
+            Candidate::new(status("first", 52.51, 0.5), offers.clone()),
+            Candidate::new(status("second", 52.52, 0.5), offers),
+        ];
+
+        let plan = policy.joint(&candidates);
+
+        assert_eq!(plan.choices.iter().filter(|c| **c == Some(0)).count(), 1);
+        assert!(plan.choices.contains(&None));
+        assert_eq!(plan.waiting, 1);
+        assert!((plan.outcome.cost - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unreachable_offers_are_ignored() {
+        let policy = FleetPolicy::default();
+        let mut stranded = status("stranded", 52.00, 0.05);
+        stranded.range = 10.0;
+        let candidates = [Candidate::new(stranded, vec![offer("charger", 52.50, 0.4)])];
+
+        assert_eq!(policy.independent(&candidates).choices, vec![None]);
+        let plan = policy.joint(&candidates);
+        assert_eq!(plan.choices, vec![None]);
+        assert_eq!(plan.waiting, 0);
+    }
+
+    #[test]
+    fn hungarian_finds_cheapest_assignment() {
+        let costs = vec![vec![4.0, 1.0, 3.0], vec![2.0, 0.0, 5.0], vec![3.0, 2.0, 2.0]];
+        assert_eq!(hungarian(&costs), vec![1, 0, 2]);
+
+        // More columns than rows
+        let costs = vec![vec![7.0, 3.0, 9.0, 1.0], vec![2.0, 8.0, 6.0, 1.5]];
+        assert_eq!(hungarian(&costs), vec![3, 0]);
+    }
+}
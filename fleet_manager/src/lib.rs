@@ -0,0 +1,119 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use assignment::{FleetPolicy, Outcome};
+use powercable::{
+    charger::ChargeOffer, fleet::FleetVehicleStatus, CHARGER_OFFER, CONFIG_FLEET, FLEET_VEHICLE_STATUS, TICK_TOPIC,
+};
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use tokio::{sync::Mutex, task};
+use tracing::{info, warn};
+
+use crate::topic_handler::{config_handler, offer_handler, status_handler, tick_handler};
+
+mod assignment;
+mod topic_handler;
+
+type SharedFleet = Arc<Mutex<FleetHandler>>;
+
+/// # Description
+/// The `FleetHandler` is a fleet manager, e.g. of taxis or delivery vans, that owns a set of vehicles.<br>
+/// It collects the charge offers for all of its vehicles and decides together which vehicle takes which offer.
+///
+/// # Fields
+/// - `name`: The name of the fleet, vehicles with the same fleet belong to it.
+/// - `client`: The MQTT client.
+/// - `vehicles`: The last reported state of each vehicle of the fleet.
+/// - `offers`: The charge offers of this tick for each vehicle of the fleet.
+/// - `policy`: How the vehicles are assigned to chargers.
+/// - `has_own_policy`: Whether the policy was meant for this fleet only.
+/// - `joint`: What the assignments of the fleet manager cost since the start.
+/// - `independent`: What the vehicles would have paid since the start, had each taken its best offer on its own.
+/// - `crowded`: How many vehicles would have been sent to a crowded charger on their own since the start.
+struct FleetHandler {
+    pub name: String,
+    pub client: AsyncClient,
+    pub vehicles: HashMap<String, FleetVehicleStatus>,
+    pub offers: HashMap<String, Vec<ChargeOffer>>,
+    pub policy: FleetPolicy,
+    pub has_own_policy: bool,
+    pub joint: Outcome,
+    pub independent: Outcome,
+    pub crowded: usize,
+}
+
+/// # Description
+/// Starts a fleet manager.
+///
+/// # Arguments
+/// - `name`: The name of the fleet, as the vehicles know it.
+pub async fn start_fleet_manager(name: String) {
+    info!("Starting fleet manager {}...", name);
+
+    let mut mqttoptions = MqttOptions::new(
+        format!("Fleet {}", name),
+        powercable::MQTT_BROKER,
+        powercable::MQTT_BROKER_PORT,
+    );
+    mqttoptions.set_keep_alive(Duration::from_secs(5));
+    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+    client
+        .subscribe(TICK_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(FLEET_VEHICLE_STATUS, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CHARGER_OFFER, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    let own_config_topic = format!("{}/{}", CONFIG_FLEET, name);
+    client
+        .subscribe(CONFIG_FLEET, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(&own_config_topic, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    info!("Connected to MQTT broker");
+
+    let shared_fleet = Arc::new(Mutex::new(FleetHandler {
+        name,
+        client: client.clone(),
+        vehicles: HashMap::new(),
+        offers: HashMap::new(),
+        policy: FleetPolicy::default(),
+        has_own_policy: false,
+        joint: Outcome::default(),
+        independent: Outcome::default(),
+        crowded: 0,
+    }));
+
+    while let Ok(notification) = eventloop.poll().await {
+        if let rumqttc::Event::Incoming(rumqttc::Packet::Publish(p)) = notification {
+            match p.topic.as_str() {
+                TICK_TOPIC => {
+                    task::spawn(tick_handler(shared_fleet.clone(), p.payload));
+                }
+                FLEET_VEHICLE_STATUS => {
+                    task::spawn(status_handler(shared_fleet.clone(), p.payload));
+                }
+                CHARGER_OFFER => {
+                    task::spawn(offer_handler(shared_fleet.clone(), p.payload));
+                }
+                CONFIG_FLEET => {
+                    task::spawn(config_handler(shared_fleet.clone(), p.payload, false));
+                }
+                topic if topic == own_config_topic => {
+                    task::spawn(config_handler(shared_fleet.clone(), p.payload, true));
+                }
+                _ => {
+                    warn!("Unknown topic: {}", p.topic);
+                }
+            }
+        }
+    }
+    println!("Exiting fleet manager simulation...");
+}
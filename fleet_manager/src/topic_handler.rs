@@ -0,0 +1,180 @@
+use bytes::Bytes;
+use powercable::{
+    charger::ChargeOffer,
+    fleet::{FleetAssignment, FleetVehicleStatus},
+    tickgen::{Phase, TickPayload},
+    ChartEntry, FLEET_ASSIGN, FLEET_HEARTBEAT, FLEET_STATS,
+};
+use rumqttc::QoS;
+use tracing::{debug, info, trace, warn};
+
+use crate::{
+    assignment::{Candidate, FleetPolicy},
+    FleetHandler, SharedFleet,
+};
+
+/**
+ * This function delegates the tick event handling based on the phase of the tick.
+ * In the process phase the manager claims its vehicles and publishes its statistics,
+ * in the commerce phase it assigns the vehicles that got offers to chargers.
+ */
+pub async fn tick_handler(handler: SharedFleet, payload: Bytes) {
+    let payload: TickPayload = serde_json::from_slice(&payload).unwrap();
+    let mut handler = handler.lock().await;
+    match payload.phase {
+        Phase::Process => {
+            handler
+                .client
+                .publish(FLEET_HEARTBEAT, QoS::ExactlyOnce, false, handler.name.clone())
+                .await
+                .unwrap();
+            publish_stats(&handler, payload.timestamp).await;
+        }
+        Phase::Commerce => assign_vehicles(&mut handler).await,
+        Phase::PowerImport => {}
+    }
+}
+
+/// # Description
+/// Assigns the vehicles of the fleet that look for a charger to the offers they got in this tick.<br>
+/// The joint assignment is compared with the choices the vehicles would have made on their own,
+/// and both are added up for the statistics. Every vehicle gets its assignment, or is told to wait.
+///
+/// # Arguments
+/// - `handler`: The fleet handler.
+async fn assign_vehicles(handler: &mut FleetHandler) {
+    let offers = std::mem::take(&mut handler.offers);
+    let mut candidates: Vec<Candidate> = offers
+        .into_iter()
+        .filter_map(|(vehicle_name, offers)| {
+            let status = handler.vehicles.get(&vehicle_name)?;
+            status.searching.then(|| Candidate::new(status.clone(), offers))
+        })
+        .collect();
+    if candidates.is_empty() {
+        return;
+    }
+    candidates.sort_by(|a, b| a.status.vehicle_name.cmp(&b.status.vehicle_name));
+
+    let joint = handler.policy.joint(&candidates);
+    let independent = handler.policy.independent(&candidates);
+    info!(
+        "{} assigned {} vehicles for {:.2}€ and {:.1} h downtime, {} wait. On their own: {:.2}€ and {:.1} h, {} crowded",
+        handler.name, candidates.len() - joint.waiting, joint.outcome.cost, joint.outcome.downtime, joint.waiting,
+        independent.outcome.cost, independent.outcome.downtime, independent.crowded
+    );
+    handler.joint.add(joint.outcome);
+    handler.independent.add(independent.outcome);
+    handler.crowded += independent.crowded;
+
+    for (candidate, choice) in candidates.iter().zip(joint.choices) {
+        let assignment = FleetAssignment {
+            fleet: handler.name.clone(),
+            vehicle_name: candidate.status.vehicle_name.clone(),
+            charger_name: choice.map(|j| candidate.offers[j].charger_name.clone()),
+        };
+        debug!("Assigning {:?}", assignment);
+        handler
+            .client
+            .publish(FLEET_ASSIGN, QoS::ExactlyOnce, false, assignment.to_bytes())
+            .await
+            .unwrap();
+    }
+}
+
+/// # Description
+/// Publishes what the assignments of the fleet cost since the start, compared to independent choices,
+/// money in cents and downtime in minutes.
+///
+/// # Arguments
+/// - `handler`: The fleet handler.
+/// - `timestamp`: The timestamp of the tick.
+async fn publish_stats(handler: &FleetHandler, timestamp: usize) {
+    for (topic, value) in [
+        ("Joint Cost", handler.joint.cost * 100.0),
+        ("Independent Cost", handler.independent.cost * 100.0),
+        ("Joint Downtime", handler.joint.downtime * 60.0),
+        ("Independent Downtime", handler.independent.downtime * 60.0),
+        ("Crowded", handler.crowded as f64),
+    ] {
+        handler
+            .client
+            .publish(
+                FLEET_STATS,
+                QoS::ExactlyOnce,
+                true,
+                ChartEntry::new(format!("{} {}", handler.name, topic), value as isize, timestamp).to_string(),
+            )
+            .await
+            .unwrap();
+    }
+}
+
+/// # Description
+/// Keeps track of the state of our vehicles.
+///
+/// # Arguments
+/// - `handler`: The shared fleet handler.
+/// - `payload`: The payload containing the vehicle status.
+pub async fn status_handler(handler: SharedFleet, payload: Bytes) {
+    let status = FleetVehicleStatus::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+
+    if status.fleet != handler.name {
+        return;
+    }
+    trace!("Status of {}: {:?}", status.vehicle_name, status);
+
+    let vehicle_name = status.vehicle_name.clone();
+    if handler.vehicles.insert(vehicle_name.clone(), status).is_none() {
+        info!("{} manages {} now", handler.name, vehicle_name);
+    }
+}
+
+/// # Description
+/// Collects the charge offers for our vehicles. A newer offer of a charger replaces its older one.
+///
+/// # Arguments
+/// - `handler`: The shared fleet handler.
+/// - `payload`: The payload containing the charge offer.
+pub async fn offer_handler(handler: SharedFleet, payload: Bytes) {
+    let offer = ChargeOffer::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+
+    if !handler.vehicles.contains_key(&offer.vehicle_name) {
+        return;
+    }
+    trace!("Offer for {}: {:?}", offer.vehicle_name, offer);
+
+    let offers = handler.offers.entry(offer.vehicle_name.clone()).or_default();
+    offers.retain(|o| o.charger_name != offer.charger_name);
+    offers.push(offer);
+}
+
+/// # Description
+/// The `config_handler` function processes incoming fleet configuration messages.<br>
+/// It replaces the policy the vehicles of the fleet are assigned with.<br>
+/// It is called when a message is received on the `CONFIG_FLEET` topic, or on the topic of this fleet
+/// `CONFIG_FLEET/<fleet name>`. A configuration for this fleet takes precedence over the one for all fleets.
+///
+/// # Arguments
+/// - `handler`: The shared fleet handler.
+/// - `payload`: The incoming payload containing the policy in JSON format.
+/// - `own`: Whether the configuration is meant for this fleet only.
+pub async fn config_handler(handler: SharedFleet, payload: Bytes, own: bool) {
+    let policy: FleetPolicy = match serde_json::from_slice(&payload) {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Invalid fleet configuration: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    if handler.has_own_policy && !own {
+        debug!("{} keeps its own policy, ignoring {:?}", handler.name, policy);
+        return;
+    }
+    debug!("{} policy set to: {:?}", handler.name, policy);
+    handler.policy = policy;
+    handler.has_own_policy = own;
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use powercable::{
+    operator::ChargerStatus, OfferHandler, ACCEPT_BUY_OFFER_TOPIC, CHARGER_REQUEST, CONFIG_OPERATOR,
+    OPERATOR_CHARGER_STATUS, TICK_TOPIC,
+};
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use serde_json::{json, Value};
+use tokio::{sync::Mutex, task};
+use tracing::{info, warn};
+
+use crate::topic_handler::{accept_offer_handler, config_handler, request_handler, status_handler, tick_handler};
+
+mod procurement;
+mod topic_handler;
+
+type SharedOperator = Arc<Mutex<OperatorHandler>>;
+
+/// # Description
+/// The `OperatorHandler` is a charge point operator, that owns a set of chargers.<br>
+/// It buys energy for all of them on the market, balances their batteries, sets their prices
+/// and routes charge requests to its best charger.
+///
+/// # Fields
+/// - `name`: The name of the operator, chargers with the same operator belong to it.
+/// - `client`: The MQTT client.
+/// - `chargers`: The last reported state of each charger of the operator.
+/// - `offer_handler`: The buy offers the operator placed on the market.
+/// - `pricing`: The pricing configuration all chargers of the operator use.
+/// - `has_own_config`: Whether the configuration was meant for this operator only.
+/// - `bought`: The energy bought on the market since the start in kWh.
+/// - `purchase_cost`: The money paid for the energy since the start in €.
+struct OperatorHandler {
+    pub name: String,
+    pub client: AsyncClient,
+    pub chargers: HashMap<String, ChargerStatus>,
+    pub offer_handler: OfferHandler,
+    pub pricing: Value,
+    pub has_own_config: bool,
+    pub bought: f64,
+    pub purchase_cost: f64,
+}
+
+impl OperatorHandler {
+    /// # Returns
+    /// The average price per kWh the operator paid, 0.0 if it didn't buy anything yet.
+    pub fn avg_purchase_price(&self) -> f64 {
+        if self.bought > 0.0 {
+            self.purchase_cost / self.bought
+        } else {
+            0.0
+        }
+    }
+}
+
+/// # Description
+/// Starts a charge point operator.
+///
+/// # Arguments
+/// - `name`: The name of the operator, as the chargers know it.
+pub async fn start_operator(name: String) {
+    info!("Starting charge point operator {}...", name);
+
+    let mut mqttoptions = MqttOptions::new(
+        format!("Operator {}", name),
+        powercable::MQTT_BROKER,
+        powercable::MQTT_BROKER_PORT,
+    );
+    mqttoptions.set_keep_alive(Duration::from_secs(5));
+    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+    client
+        .subscribe(TICK_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(ACCEPT_BUY_OFFER_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(OPERATOR_CHARGER_STATUS, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CHARGER_REQUEST, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    let own_config_topic = format!("{}/{}", CONFIG_OPERATOR, name);
+    client
+        .subscribe(CONFIG_OPERATOR, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(&own_config_topic, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    info!("Connected to MQTT broker");
+
+    let shared_operator = Arc::new(Mutex::new(OperatorHandler {
+        name,
+        client: client.clone(),
+        chargers: HashMap::new(),
+        offer_handler: OfferHandler::new(),
+        pricing: json!({"strategy": "cost_plus", "margin": 0.2}),
+        has_own_config: false,
+        bought: 0.0,
+        purchase_cost: 0.0,
+    }));
+
+    while let Ok(notification) = eventloop.poll().await {
+        if let rumqttc::Event::Incoming(rumqttc::Packet::Publish(p)) = notification {
+            match p.topic.as_str() {
+                TICK_TOPIC => {
+                    task::spawn(tick_handler(shared_operator.clone(), p.payload));
+                }
+                ACCEPT_BUY_OFFER_TOPIC => {
+                    task::spawn(accept_offer_handler(shared_operator.clone(), p.payload));
+                }
+                OPERATOR_CHARGER_STATUS => {
+                    task::spawn(status_handler(shared_operator.clone(), p.payload));
+                }
+                CHARGER_REQUEST => {
+                    task::spawn(request_handler(shared_operator.clone(), p.payload));
+                }
+                CONFIG_OPERATOR => {
+                    task::spawn(config_handler(shared_operator.clone(), p.payload, false));
+                }
+                topic if topic == own_config_topic => {
+                    task::spawn(config_handler(shared_operator.clone(), p.payload, true));
+                }
+                _ => {
+                    warn!("Unknown topic: {}", p.topic);
+                }
+            }
+        }
+    }
+    println!("Exiting charge point operator simulation...");
+}
@@ -0,0 +1,150 @@
+use powercable::{offer::structure::OFFER_PACKAGE_SIZE, operator::ChargeTransfer, Offer, BUY_OFFER_TOPIC, OPERATOR_TRANSFER};
+use rumqttc::QoS;
+use tracing::{debug, info};
+
+use crate::OperatorHandler;
+
+/// How far the state of charge of a charger may be off the average of the operator, before energy is moved
+const BALANCE_THRESHOLD: f64 = 0.2;
+/// The lowest price the operator offers to buy energy for, like a single charger
+const MIN_BUY_PRICE: f64 = 0.1;
+/// The most packages the operator asks for in one tick
+const MAX_PACKAGES: usize = 100;
+
+impl OperatorHandler {
+    /// # Description
+    /// Moves energy from chargers with a battery well above the average of the operator to those well below it,
+    /// in whole packages. The receiving chargers pay what the operator paid for the energy on average.<br>
+    /// The copies of the charger states are updated, so no grid connection is overbooked until the next status arrives.
+    pub async fn balance_chargers(&mut self) {
+        let charge: usize = self.chargers.values().map(|c| c.current_charge).sum();
+        let capacity: usize = self.chargers.values().map(|c| c.capacity).sum();
+        if capacity == 0 {
+            return;
+        }
+        let avg_soc = charge as f64 / capacity as f64;
+        let package = OFFER_PACKAGE_SIZE as usize;
+
+        let mut receivers: Vec<String> = self.chargers.values()
+            .filter(|c| c.soc() < avg_soc - BALANCE_THRESHOLD)
+            .map(|c| c.charger_name.clone())
+            .collect();
+        receivers.sort_by(|a, b| self.chargers[a].soc().total_cmp(&self.chargers[b].soc()));
+
+        for to in receivers {
+            loop {
+                let receiver = &self.chargers[&to];
+                if receiver.soc() >= avg_soc || receiver.grid_energy_per_tick < package {
+                    break;
+                }
+                let donor = self.chargers.values()
+                    .filter(|c| c.soc() > avg_soc + BALANCE_THRESHOLD / 2.0)
+                    .filter(|c| c.available_charge >= package && c.grid_energy_per_tick >= package)
+                    .max_by(|a, b| a.soc().total_cmp(&b.soc()))
+                    .map(|c| c.charger_name.clone());
+                let from = match donor {
+                    Some(from) => from,
+                    None => return,
+                };
+
+                let transfer = ChargeTransfer {
+                    operator: self.name.clone(),
+                    from: Some(from.clone()),
+                    to: to.clone(),
+                    amount: package,
+                    price: self.avg_purchase_price(),
+                };
+                debug!("{} moves {} kWh from {} to {}", self.name, package, from, to);
+                self.send_transfer(&transfer).await;
+
+                let donor = self.chargers.get_mut(&from).unwrap();
+                donor.current_charge -= package;
+                donor.available_charge -= package;
+                donor.grid_energy_per_tick -= package;
+                let receiver = self.chargers.get_mut(&to).unwrap();
+                receiver.current_charge += package;
+                receiver.available_charge += package;
+                receiver.grid_energy_per_tick -= package;
+            }
+        }
+    }
+
+    /// # Description
+    /// Places buy offers for the energy all chargers of the operator need, as far as their grid connections allow.<br>
+    /// Like a single charger, the operator pays less the fuller its chargers would be after the purchase.
+    pub async fn place_buy_offers(&mut self) {
+        self.offer_handler.remove_all_offers();
+        let needed: usize = self.chargers.values().map(|c| c.needed_charge().min(c.grid_energy_per_tick)).sum();
+        let packages = ((needed as f64 / OFFER_PACKAGE_SIZE) as usize).min(MAX_PACKAGES);
+        if packages == 0 {
+            info!("{} doesn't need any energy", self.name);
+            return;
+        }
+
+        let charge: usize = self.chargers.values().map(|c| c.current_charge).sum();
+        let capacity: usize = self.chargers.values().map(|c| c.capacity).sum();
+        let count = self.chargers.len() as f64;
+        let latitude = self.chargers.values().map(|c| c.position.latitude).sum::<f64>() / count;
+        let longitude = self.chargers.values().map(|c| c.position.longitude).sum::<f64>() / count;
+
+        for i in 0..packages {
+            let soc_after = (charge as f64 + i as f64 * OFFER_PACKAGE_SIZE) / capacity as f64;
+            let offer = Offer::new(
+                format!("{}-{}", self.name, i),
+                (1.0 - soc_after).max(MIN_BUY_PRICE),
+                OFFER_PACKAGE_SIZE,
+                latitude,
+                longitude,
+            );
+            self.offer_handler.add_offer(offer.clone());
+
+            self.client
+                .publish(BUY_OFFER_TOPIC, QoS::ExactlyOnce, false, offer.to_bytes())
+                .await
+                .unwrap();
+        }
+        debug!("{} asked for {} packages for {} chargers", self.name, packages, self.chargers.len());
+    }
+
+    /// # Description
+    /// Hands energy the operator bought to the charger that needs it the most and can still take it.<br>
+    /// The copy of its state is updated, so the next package goes to the next charger in need.
+    ///
+    /// # Arguments
+    /// - `amount`: The energy bought in kWh.
+    /// - `price`: The price per kWh the operator paid.
+    pub async fn allocate(&mut self, amount: usize, price: f64) {
+        let to = self.chargers.values()
+            .filter(|c| c.grid_energy_per_tick >= amount)
+            .max_by_key(|c| c.needed_charge())
+            .map(|c| c.charger_name.clone());
+        let to = match to {
+            Some(to) => to,
+            None => {
+                info!("{} has no charger left that can take {} kWh", self.name, amount);
+                return;
+            }
+        };
+
+        let transfer = ChargeTransfer {
+            operator: self.name.clone(),
+            from: None,
+            to: to.clone(),
+            amount,
+            price,
+        };
+        self.send_transfer(&transfer).await;
+
+        let receiver = self.chargers.get_mut(&to).unwrap();
+        receiver.current_charge += amount;
+        receiver.available_charge += amount;
+        receiver.grid_energy_per_tick -= amount;
+    }
+
+    async fn send_transfer(&self, transfer: &ChargeTransfer) {
+        self.client
+            .publish(OPERATOR_TRANSFER, QoS::ExactlyOnce, false, transfer.to_bytes())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use bytes::Bytes;
+use powercable::{
+    charger::ChargeRequest,
+    operator::{ChargerStatus, RoutedRequest},
+    tickgen::{Phase, TickPayload},
+    Offer, ACK_ACCEPT_BUY_OFFER_TOPIC, CONFIG_CHARGER_PRICING, OPERATOR_HEARTBEAT, OPERATOR_ROUTE,
+};
+use rumqttc::QoS;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, info, trace, warn};
+
+use crate::{OperatorHandler, SharedOperator};
+
+/**
+ * This function delegates the tick event handling based on the phase of the tick.
+ * The operator only acts in the process phase, in which buy offers are placed.
+ */
+pub async fn tick_handler(handler: SharedOperator, payload: Bytes) {
+    let payload: TickPayload = serde_json::from_slice(&payload).unwrap();
+    if payload.phase != Phase::Process {
+        return;
+    }
+
+    let mut handler = handler.lock().await;
+    handler
+        .client
+        .publish(OPERATOR_HEARTBEAT, QoS::ExactlyOnce, false, handler.name.clone())
+        .await
+        .unwrap();
+
+    if handler.chargers.is_empty() {
+        debug!("{} has no chargers yet", handler.name);
+        return;
+    }
+    handler.balance_chargers().await;
+    handler.place_buy_offers().await;
+}
+
+/**
+ * This function handles the acceptance of one of our buy offers in the MQTT topic `ACCEPT_BUY_OFFER_TOPIC`.
+ * The first seller gets the ACK and the energy is handed to the charger that needs it the most.
+ */
+pub async fn accept_offer_handler(handler: SharedOperator, payload: Bytes) {
+    let mut offer: Offer = Offer::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+
+    if !handler.offer_handler.has_offer(offer.get_id()) || handler.offer_handler.has_sent_offer(offer.get_id()) {
+        return;
+    }
+    let seller = match offer.get_accepted_by() {
+        Some(seller) => seller.clone(),
+        None => {
+            warn!("Received accept for offer {} without accepted_by field", offer.get_id());
+            return;
+        }
+    };
+
+    offer.set_ack_for(seller);
+    handler.offer_handler.add_sent_offer(offer.clone());
+    handler
+        .client
+        .publish(ACK_ACCEPT_BUY_OFFER_TOPIC, QoS::ExactlyOnce, false, offer.to_bytes())
+        .await
+        .unwrap();
+    debug!("ACK for offer {} sent", offer.get_id());
+
+    handler.bought += offer.get_amount();
+    handler.purchase_cost += offer.get_amount() * offer.get_price();
+    handler.allocate(offer.get_amount() as usize, offer.get_price()).await;
+}
+
+/// # Description
+/// Keeps track of the state of our chargers.<br>
+/// A charger that reports for the first time gets the pricing of the operator.
+///
+/// # Arguments
+/// - `handler`: The shared operator handler.
+/// - `payload`: The payload containing the charger status.
+pub async fn status_handler(handler: SharedOperator, payload: Bytes) {
+    let status = ChargerStatus::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+
+    if status.operator != handler.name {
+        return;
+    }
+    trace!("Status of {}: {:?}", status.charger_name, status);
+
+    let charger_name = status.charger_name.clone();
+    if handler.chargers.insert(charger_name.clone(), status).is_none() {
+        info!("{} manages {} now", handler.name, charger_name);
+        publish_pricing(&handler, &charger_name).await;
+    }
+}
+
+/// # Description
+/// Routes a charge request to the best charger of the operator.<br>
+/// The best charger is the closest one with a free port the vehicle can plug into and charge left.
+/// If all of them are busy, the request goes to the closest compatible charger, which puts the vehicle on its waitlist.
+/// Vehicles that can't plug into any of our chargers are ignored.
+///
+/// # Arguments
+/// - `handler`: The shared operator handler.
+/// - `payload`: The payload containing the charge request.
+pub async fn request_handler(handler: SharedOperator, payload: Bytes) {
+    let request = ChargeRequest::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+
+    let distance = |status: &ChargerStatus| status.position.distance_to(request.vehicle_position);
+    let best = handler.chargers.values()
+        .filter(|c| c.available_charge > 0 && c.free_connectors.iter().any(|f| request.connectors.contains(f)))
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .or_else(|| handler.chargers.values()
+            .filter(|c| c.connectors.iter().any(|f| request.connectors.contains(f)))
+            .min_by(|a, b| distance(a).total_cmp(&distance(b))))
+        .map(|c| c.charger_name.clone());
+    let charger_name = match best {
+        Some(charger_name) => charger_name,
+        None => {
+            trace!("{} has no charger for {}", handler.name, request.vehicle_name);
+            return;
+        }
+    };
+
+    // The port is taken until the charger reports again
+    let status = handler.chargers.get_mut(&charger_name).unwrap();
+    if let Some(i) = status.free_connectors.iter().position(|f| request.connectors.contains(f)) {
+        status.free_connectors.remove(i);
+    }
+
+    info!("{} routes the request of {} to {}", handler.name, request.vehicle_name, charger_name);
+    let routed = RoutedRequest { charger_name, request };
+    handler
+        .client
+        .publish(OPERATOR_ROUTE, QoS::ExactlyOnce, false, routed.to_bytes())
+        .await
+        .unwrap();
+}
+
+/// # Description
+/// The configuration of an operator as received on the `CONFIG_OPERATOR` topic,
+/// e.g. `{"pricing": {"strategy": "cost_plus", "margin": 0.2}}`.
+///
+/// # Fields
+/// - `pricing`: The pricing configuration for all chargers of the operator, like on `CONFIG_CHARGER_PRICING`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OperatorConfig {
+    pub pricing: Value,
+}
+
+/// # Description
+/// The `config_handler` function processes incoming operator configuration messages.<br>
+/// It sets the network-wide pricing and passes it on to all chargers of the operator.<br>
+/// It is called when a message is received on the `CONFIG_OPERATOR` topic, or on the topic of this operator
+/// `CONFIG_OPERATOR/<operator name>`. A configuration for this operator takes precedence over the one for all operators.
+///
+/// # Arguments
+/// - `handler`: The shared operator handler.
+/// - `payload`: The incoming payload containing the configuration in JSON format.
+/// - `own`: Whether the configuration is meant for this operator only.
+pub async fn config_handler(handler: SharedOperator, payload: Bytes, own: bool) {
+    let config: OperatorConfig = match serde_json::from_slice(&payload) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid operator configuration: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    if handler.has_own_config && !own {
+        debug!("{} keeps its own configuration, ignoring {:?}", handler.name, config);
+        return;
+    }
+    debug!("{} configuration set to: {:?}", handler.name, config);
+    handler.pricing = config.pricing;
+    handler.has_own_config = own;
+
+    let chargers: Vec<String> = handler.chargers.keys().cloned().collect();
+    for charger_name in chargers {
+        publish_pricing(&handler, &charger_name).await;
+    }
+}
+
+/// # Description
+/// Sets the pricing of a charger to the one of the operator, on the pricing topic of the charger.
+async fn publish_pricing(handler: &OperatorHandler, charger_name: &str) {
+    let topic = format!("{}/{}", CONFIG_CHARGER_PRICING, charger_name);
+    handler
+        .client
+        .publish(&topic, QoS::ExactlyOnce, false, handler.pricing.to_string())
+        .await
+        .unwrap();
+    trace!("Published on topic {}: {}", topic, handler.pricing);
+}
@@ -1,7 +1,9 @@
+use charger::registry::ChargerSite;
 use consumer::consumer::ConsumerType;
 use tokio::task::{JoinHandle};
 use tracing_subscriber::fmt::writer::MakeWriterExt;
 
+mod registry;
 mod shutdown;
 mod spawn_tasks;
 mod watchdog;
@@ -12,8 +14,15 @@ struct PowerGrid {
     turbine: Vec<JoinHandle<()>>,
     charger: Vec<JoinHandle<()>>,
     fusion_charger: JoinHandle<()>,
+    rescue_service: JoinHandle<()>,
     consumer: Vec<(JoinHandle<()>, ConsumerType)>,
     vehicle: Vec<JoinHandle<()>>,
+    operator: Vec<(JoinHandle<()>, String)>,
+    fleet_manager: Vec<(JoinHandle<()>, String)>,
+    charger_sites: Vec<ChargerSite>,
+    charger_operators: usize,
+    mixed_ports: bool,
+    vehicle_fleets: usize,
 }
 
 #[tokio::main]
@@ -39,7 +48,11 @@ async fn main() {
 
     tracing::debug!("PowerGrid starting up...");
   
-    let power_grid = PowerGrid::spawn_new(20, 3, 30).await;
+    let charger_sites = registry::charger_sites_from_env();
+    let charger_operators = registry::charger_operators_from_env();
+    let mixed_ports = registry::charger_mixed_ports_from_env();
+    let vehicle_fleets = registry::vehicle_fleets_from_env();
+    let power_grid = PowerGrid::spawn_new(20, 3, 30, charger_sites, charger_operators, mixed_ports, vehicle_fleets).await;
 
     tracing::debug!("PowerGrid spawned with {} turbines, {} chargers, and {} consumers.", 
         power_grid.turbine.len(), 
@@ -0,0 +1,68 @@
+use charger::registry::{read_registry, BoundingBox, ChargerSite, RegistryFilter};
+use powercable::Position;
+
+/// Path to a CSV export of the Ladesäulenregister, chargers are placed randomly if not set
+const CHARGER_REGISTRY: &str = "CHARGER_REGISTRY";
+/// Only use sites inside "south,west,north,east"
+const CHARGER_REGISTRY_BBOX: &str = "CHARGER_REGISTRY_BBOX";
+/// Only use every n-th site
+const CHARGER_REGISTRY_EVERY_NTH: &str = "CHARGER_REGISTRY_EVERY_NTH";
+/// Use at most this many sites
+const CHARGER_REGISTRY_LIMIT: &str = "CHARGER_REGISTRY_LIMIT";
+/// Number of operators the randomly placed chargers are shared among, they run on their own if not set
+const CHARGER_OPERATORS: &str = "CHARGER_OPERATORS";
+/// Whether the randomly placed chargers get the mixed ports of a typical site instead of the basic ones
+const CHARGER_MIXED_PORTS: &str = "CHARGER_MIXED_PORTS";
+/// Number of fleets the vehicles are shared among, they run on their own if not set
+const VEHICLE_FLEETS: &str = "VEHICLE_FLEETS";
+
+/// Parses a bounding box in the form "south,west,north,east".
+fn parse_bounding_box(value: &str) -> Option<BoundingBox> {
+    let corners: Vec<f64> = value.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+    match corners[..] {
+        [south, west, north, east] => Some(BoundingBox {
+            south_west: Position::new(south, west),
+            north_east: Position::new(north, east),
+        }),
+        _ => None,
+    }
+}
+
+/// Reads the charger sites from the registry configured in the environment.
+/// Returns no sites if no registry is configured or it can't be read, so the chargers are placed randomly.
+pub fn charger_sites_from_env() -> Vec<ChargerSite> {
+    let path = match std::env::var(CHARGER_REGISTRY) {
+        Ok(path) => path,
+        Err(_) => return Vec::new(),
+    };
+
+    let filter = RegistryFilter {
+        bounding_box: std::env::var(CHARGER_REGISTRY_BBOX).ok().and_then(|v| parse_bounding_box(&v)),
+        every_nth: std::env::var(CHARGER_REGISTRY_EVERY_NTH).ok().and_then(|v| v.parse().ok()).unwrap_or(1),
+        limit: std::env::var(CHARGER_REGISTRY_LIMIT).ok().and_then(|v| v.parse().ok()),
+    };
+    tracing::info!("Reading charger registry {} with {:?}", path, filter);
+
+    match read_registry(&path, &filter) {
+        Ok(sites) => sites,
+        Err(e) => {
+            tracing::error!("Could not read charger registry {}: {}", path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Reads the number of operators for randomly placed chargers from the environment, 0 if not set.
+pub fn charger_operators_from_env() -> usize {
+    std::env::var(CHARGER_OPERATORS).ok().and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+/// Reads from the environment whether the randomly placed chargers get mixed ports, false if not set.
+pub fn charger_mixed_ports_from_env() -> bool {
+    std::env::var(CHARGER_MIXED_PORTS).ok().and_then(|v| v.parse().ok()).unwrap_or(false)
+}
+
+/// Reads the number of fleets for the vehicles from the environment, 0 if not set.
+pub fn vehicle_fleets_from_env() -> usize {
+    std::env::var(VEHICLE_FLEETS).ok().and_then(|v| v.parse().ok()).unwrap_or(0)
+}
@@ -9,6 +9,12 @@ impl PowerGrid {
         for charger in &self.charger {
             charger.abort();
         }
+        for operator in &self.operator {
+            operator.0.abort();
+        }
+        for fleet_manager in &self.fleet_manager {
+            fleet_manager.0.abort();
+        }
         for consumer in &self.consumer {
             consumer.0.abort();
         }
@@ -18,6 +24,7 @@ impl PowerGrid {
         self.transformer.abort();
         self.tickgen.abort();
         self.fusion_charger.abort();
+        self.rescue_service.abort();
 
         tracing::info!("PowerGrid has been shut down gracefully.");
     }
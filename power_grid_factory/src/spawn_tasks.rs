@@ -1,8 +1,66 @@
+use std::collections::HashMap;
+
+use charger::registry::ChargerSite;
 use consumer::consumer::ConsumerType;
 use tokio::task::{self, JoinHandle};
 
 use crate::PowerGrid;
 
+/// Spawns the charger with the given index, at its site from the charger registry if there is one.
+/// Randomly placed chargers are shared in turns among the given number of operators, they get mixed ports if asked for.
+pub fn spawn_charger(i: usize, charger_sites: &[ChargerSite], charger_operators: usize, mixed_ports: bool) -> JoinHandle<()> {
+    match charger_sites.get(i) {
+        Some(site) => task::spawn(charger::start_charger_at(i as u64, site.clone())),
+        None => {
+            let operator = (charger_operators > 0).then(|| random_operator_name(i % charger_operators));
+            task::spawn(charger::start_charger(i as u64, operator, mixed_ports))
+        }
+    }
+}
+
+/// Spawns the vehicle with the given index. The vehicles are shared in turns among the given number of fleets.
+pub fn spawn_vehicle(i: usize, vehicle_fleets: usize) -> JoinHandle<()> {
+    let fleet = (vehicle_fleets > 0).then(|| fleet_name(i % vehicle_fleets));
+    task::spawn(vehicle::start_vehicle(i as u64, fleet))
+}
+
+fn fleet_name(i: usize) -> String {
+    format!("Fleet {}", i + 1)
+}
+
+/// Returns the fleets that get a fleet manager, those with at least two vehicles.
+fn fleet_names(amount_of_cars: usize, vehicle_fleets: usize) -> Vec<String> {
+    (0..vehicle_fleets.min(amount_of_cars / 2))
+        .map(fleet_name)
+        .collect()
+}
+
+fn random_operator_name(i: usize) -> String {
+    format!("Operator {}", i + 1)
+}
+
+/// Returns the operators that get an agent of their own, those with at least two chargers.
+/// An operator of a single charger has nothing to balance or route, so the charger runs on its own.
+fn operator_names(amount_of_chargers: usize, charger_sites: &[ChargerSite], charger_operators: usize) -> Vec<String> {
+    if charger_sites.is_empty() {
+        return (0..charger_operators.min(amount_of_chargers / 2))
+            .map(random_operator_name)
+            .collect();
+    }
+
+    let mut chargers_per_operator: HashMap<&str, usize> = HashMap::new();
+    for site in charger_sites {
+        *chargers_per_operator.entry(site.operator.as_str()).or_default() += 1;
+    }
+    let mut names: Vec<String> = chargers_per_operator
+        .into_iter()
+        .filter(|(name, count)| !name.is_empty() && *count >= 2)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    names.sort();
+    names
+}
+
 impl PowerGrid {
     /// Restarts the PowerGrid by shutting down all tasks and spawning new ones.
     pub async fn restart(
@@ -15,15 +73,33 @@ impl PowerGrid {
         self.shutdown().await;
 
         // Spawn new tasks overwriting itself
-        *self = PowerGrid::spawn_new(amount_of_chargers, amount_of_turbines, amount_of_cars).await;
+        let charger_sites = std::mem::take(&mut self.charger_sites);
+        *self = PowerGrid::spawn_new(
+            amount_of_chargers,
+            amount_of_turbines,
+            amount_of_cars,
+            charger_sites,
+            self.charger_operators,
+            self.mixed_ports,
+            self.vehicle_fleets,
+        )
+        .await;
     }
 
     /// Spawns a new PowerGrid with the specified number of chargers, turbines, and cars.
+    /// If charger sites from the registry are given, one charger is spawned per site instead.
+    /// Every operator with at least two chargers gets an operator agent, every fleet with at least two vehicles a fleet manager.
     pub async fn spawn_new(
         amount_of_chargers: usize,
         amount_of_turbines: usize,
         amount_of_cars: usize,
+        charger_sites: Vec<ChargerSite>,
+        charger_operators: usize,
+        mixed_ports: bool,
+        vehicle_fleets: usize,
     ) -> PowerGrid {
+        let amount_of_chargers = if charger_sites.is_empty() { amount_of_chargers } else { charger_sites.len() };
+
         let mut consumers: Vec<(JoinHandle<()>, ConsumerType)> = Vec::new();
 
         consumers.push((
@@ -46,13 +122,26 @@ impl PowerGrid {
                 .map(|i| task::spawn(turbine::start_turbine(i)))
                 .collect(),
             charger: (0..amount_of_chargers)
-                .map(|i| task::spawn(charger::start_charger(i as u64)))
+                .map(|i| spawn_charger(i, &charger_sites, charger_operators, mixed_ports))
                 .collect(),
             fusion_charger: task::spawn(fusion_reactor::start_fusion_gen()),
+            rescue_service: task::spawn(rescue_service::start_rescue_service()),
             consumer: consumers,
             vehicle: (0..amount_of_cars)
-                .map(|i| task::spawn(vehicle::start_vehicle(i as u64)))
+                .map(|i| spawn_vehicle(i, vehicle_fleets))
+                .collect(),
+            operator: operator_names(amount_of_chargers, &charger_sites, charger_operators)
+                .into_iter()
+                .map(|name| (task::spawn(operator::start_operator(name.clone())), name))
+                .collect(),
+            fleet_manager: fleet_names(amount_of_cars, vehicle_fleets)
+                .into_iter()
+                .map(|name| (task::spawn(fleet_manager::start_fleet_manager(name.clone())), name))
                 .collect(),
+            charger_sites,
+            charger_operators,
+            mixed_ports,
+            vehicle_fleets,
         }
     }
 }
\ No newline at end of file
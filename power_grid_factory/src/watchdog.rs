@@ -1,4 +1,4 @@
-use crate::PowerGrid;
+use crate::{spawn_tasks::{spawn_charger, spawn_vehicle}, PowerGrid};
 
 impl PowerGrid {
     /// Checks whether all the tasks are running and restarts them if they are not.
@@ -29,7 +29,23 @@ impl PowerGrid {
         for (i, charger) in self.charger.iter_mut().enumerate() {
             if charger.is_finished() {
                 tracing::warn!("Charger {} task has stopped. Restarting...", i);
-                *charger = tokio::task::spawn(charger::start_charger(i as u64));
+                *charger = spawn_charger(i, &self.charger_sites, self.charger_operators, self.mixed_ports);
+            }
+        }
+
+        // Check each operator task
+        for (operator_task, name) in self.operator.iter_mut() {
+            if operator_task.is_finished() {
+                tracing::warn!("Operator {} task has stopped. Restarting...", name);
+                *operator_task = tokio::task::spawn(operator::start_operator(name.clone()));
+            }
+        }
+
+        // Check each fleet manager task
+        for (fleet_task, name) in self.fleet_manager.iter_mut() {
+            if fleet_task.is_finished() {
+                tracing::warn!("Fleet manager {} task has stopped. Restarting...", name);
+                *fleet_task = tokio::task::spawn(fleet_manager::start_fleet_manager(name.clone()));
             }
         }
 
@@ -39,6 +55,12 @@ impl PowerGrid {
             self.fusion_charger = tokio::task::spawn(fusion_reactor::start_fusion_gen());
         }
 
+        // Check the rescue service task
+        if self.rescue_service.is_finished() {
+            tracing::warn!("Rescue service task has stopped. Restarting...");
+            self.rescue_service = tokio::task::spawn(rescue_service::start_rescue_service());
+        }
+
         // Check each consumer task
         for (i, (consumer_task, consumer_type)) in self.consumer.iter_mut().enumerate() {
             if consumer_task.is_finished() {
@@ -51,7 +73,7 @@ impl PowerGrid {
         for (i, vehicle) in self.vehicle.iter_mut().enumerate() {
             if vehicle.is_finished() {
                 tracing::warn!("Vehicle {} task has stopped. Restarting...", i);
-                *vehicle = tokio::task::spawn(vehicle::start_vehicle(i as u64));
+                *vehicle = spawn_vehicle(i, self.vehicle_fleets);
             }
         }
     }
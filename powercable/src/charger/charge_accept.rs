@@ -10,6 +10,7 @@ use bytes::Bytes;
 /// - `charge_price`: The price per unit of charge, which is calculated based on the distance to the vehicle and the current price of electricity.
 /// - `distance`: The distance from the charger to the vehicle.
 /// - `cost`: The total cost for the charging service, calculated as `charge_price * charge_amount`.
+/// - `eta_ticks`: In how many ticks the vehicle expects to arrive at the charger.
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct ChargeAccept {
     pub charger_name: String,
@@ -17,7 +18,7 @@ pub struct ChargeAccept {
     pub charge_price: f64,
     pub distance: f64,
     pub cost: f64,
-    // TODO: estimated arrival time
+    pub eta_ticks: u64,
 }
 
 impl ChargeAccept {
@@ -30,6 +31,7 @@ impl ChargeAccept {
     /// - `charge_price`: The price per unit of charge, which is calculated based on the distance to the vehicle and the current price of electricity.
     /// - `distance`: The distance from the charger to the vehicle.
     /// - `cost`: The total price for the charging service, calculated as `charge_price * charge_amount`.
+    /// - `eta_ticks`: In how many ticks the vehicle expects to arrive at the charger.
     /// 
     /// # Returns
     /// A new ChargeAccept instance with the specified charger and vehicle names and price.
@@ -39,6 +41,7 @@ impl ChargeAccept {
         charge_price: f64,
         distance: f64,
         cost: f64,
+        eta_ticks: u64,
     ) -> Self {
         ChargeAccept {
             charger_name,
@@ -46,6 +49,7 @@ impl ChargeAccept {
             charge_price,
             distance,
             cost,
+            eta_ticks,
         }
     }
 
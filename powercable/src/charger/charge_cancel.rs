@@ -0,0 +1,73 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+/// # Description
+/// CancelReason describes why a reservation at a charger was ended before the vehicle charged.
+///
+/// # Variants
+/// - `Vehicle`: The vehicle cancelled the reservation itself.
+/// - `NoShow`: The vehicle did not arrive before the reservation expired, the charger released it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum CancelReason {
+    Vehicle,
+    NoShow,
+}
+
+/// # Description
+/// ChargeCancel is sent by a vehicle to cancel its reservation, or by a charger to report a no-show.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger the reservation was made at.
+/// - `vehicle_name`: The name of the vehicle the reservation was made for.
+/// - `reason`: Why the reservation was cancelled.
+/// - `fee`: The fee that is charged to the vehicle for the cancellation, in €.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ChargeCancel {
+    pub charger_name: String,
+    pub vehicle_name: String,
+    pub reason: CancelReason,
+    pub fee: f64,
+}
+
+impl ChargeCancel {
+    /// # Description
+    /// Creates a new ChargeCancel instance.
+    ///
+    /// # Arguments
+    /// - `charger_name`: The name of the charger the reservation was made at.
+    /// - `vehicle_name`: The name of the vehicle the reservation was made for.
+    /// - `reason`: Why the reservation was cancelled.
+    /// - `fee`: The fee that is charged to the vehicle for the cancellation, in €.
+    ///
+    /// # Returns
+    /// A new ChargeCancel instance with the specified parameters.
+    pub fn new(charger_name: String, vehicle_name: String, reason: CancelReason, fee: f64) -> Self {
+        ChargeCancel {
+            charger_name,
+            vehicle_name,
+            reason,
+            fee,
+        }
+    }
+
+    /// # Description
+    /// Creates a ChargeCancel instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded ChargeCancel message.
+    ///
+    /// # Returns
+    /// A Result containing the ChargeCancel instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the ChargeCancel instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded ChargeCancel message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
@@ -0,0 +1,47 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+/// # Description
+/// ChargeCounter is exchanged while a vehicle and a charger negotiate the price of an offer.<br>
+/// The vehicle sends it on `CHARGER_COUNTER` with the price it is willing to pay,
+/// the charger answers on `CHARGER_REVISE` with its revised offer.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger that made the offer.
+/// - `vehicle_name`: The name of the vehicle the offer was made for.
+/// - `charge_price`: The proposed price per kWh.
+/// - `charge_amount`: The amount of charge the price is for, in kWh.
+/// - `round`: The round of the negotiation, starting at 1. The answer of the charger has the round of the counter offer.
+/// - `is_final`: Whether the charger won't revise its offer again, always `false` for counter offers of the vehicle.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ChargeCounter {
+    pub charger_name: String,
+    pub vehicle_name: String,
+    pub charge_price: f64,
+    pub charge_amount: usize,
+    pub round: u32,
+    pub is_final: bool,
+}
+
+impl ChargeCounter {
+    /// # Description
+    /// Creates a ChargeCounter instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded ChargeCounter message.
+    ///
+    /// # Returns
+    /// A Result containing the ChargeCounter instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the ChargeCounter instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded ChargeCounter message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
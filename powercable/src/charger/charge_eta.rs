@@ -0,0 +1,40 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+/// # Description
+/// ChargeEta is sent every tick by a vehicle on its way to the charger it booked, with its estimated arrival.<br>
+/// The charger holds the port from shortly before the arrival and can lend it to other vehicles until then.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger the vehicle booked.
+/// - `vehicle_name`: The name of the vehicle.
+/// - `eta_ticks`: In how many ticks the vehicle expects to arrive at the charger.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ChargeEta {
+    pub charger_name: String,
+    pub vehicle_name: String,
+    pub eta_ticks: u64,
+}
+
+impl ChargeEta {
+    /// # Description
+    /// Creates a ChargeEta instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded ChargeEta message.
+    ///
+    /// # Returns
+    /// A Result containing the ChargeEta instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the ChargeEta instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded ChargeEta message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
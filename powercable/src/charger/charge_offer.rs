@@ -2,6 +2,22 @@ use bitcode::{Decode, Encode};
 use bytes::Bytes;
 use crate ::Position;
 
+use super::ConnectorType;
+
+/// # Description
+/// The terms under which a charger holds a reservation for a vehicle after its offer was accepted.
+/// 
+/// # Fields
+/// - `reservation_ticks`: For how many ticks after the acceptance the charger holds the port and the charge.
+/// - `no_show_fee`: The fee charged to the vehicle if it does not arrive before the reservation expires, in €.
+/// - `cancellation_fee`: The fee charged to the vehicle if it cancels the accepted reservation, in €.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct ReservationTerms {
+    pub reservation_ticks: u64,
+    pub no_show_fee: f64,
+    pub cancellation_fee: f64,
+}
+
 /// # Description
 /// This module defines the `ChargeOffer` struct, which represents an offer made by a charger to a vehicle for charging services.
 /// 
@@ -11,6 +27,11 @@ use crate ::Position;
 /// - `charge_price`: The price per unit of charge offered by the charger.
 /// - `charge_amount`: The amount of charge offered by the charger, in kWh.
 /// - `charger_position`: The position of the charger in the world map, represented as a `Position` struct.
+/// - `terms`: The reservation terms the charger applies if the offer is accepted.
+/// - `connector`: The connector type of the port that is reserved for the vehicle.
+/// - `charge_power`: The power in kW the vehicle will be charged with, limited by the port, the vehicle and the site.
+/// - `estimated_wait_ticks`: The estimated amount of ticks the vehicle waits at the charger for the part of its request
+///   the charger has to buy first, 0 if the charger has all of it.
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct ChargeOffer {
     pub charger_name: String,
@@ -18,6 +39,10 @@ pub struct ChargeOffer {
     pub charge_price: f64,
     pub charge_amount: usize,
     pub charger_position: Position,
+    pub terms: ReservationTerms,
+    pub connector: ConnectorType,
+    pub charge_power: f64,
+    pub estimated_wait_ticks: u64,
 }
 
 impl ChargeOffer {
@@ -30,15 +55,24 @@ impl ChargeOffer {
     /// - `charge_price`: The price per unit of charge offered by the charger.
     /// - `charge_amount`: The amount of charge offered by the charger, in kWh.
     /// - `charger_position`: The position of the charger in the world map, represented as a `Position` struct.
+    /// - `terms`: The reservation terms the charger applies if the offer is accepted.
+    /// - `connector`: The connector type of the port that is reserved for the vehicle.
+    /// - `charge_power`: The power in kW the vehicle will be charged with.
+    /// - `estimated_wait_ticks`: The estimated amount of ticks the vehicle waits at the charger for the rest of its request.
     /// 
     /// # Returns
     /// A new `ChargeOffer` instance with the specified parameters.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         charger_name: String,
         vehicle_name: String,
         charge_price: f64,
         charge_amount: usize,
         charger_position: Position,
+        terms: ReservationTerms,
+        connector: ConnectorType,
+        charge_power: f64,
+        estimated_wait_ticks: u64,
     ) -> Self {
         ChargeOffer {
             charger_name,
@@ -46,6 +80,10 @@ impl ChargeOffer {
             charge_price,
             charge_amount,
             charger_position,
+            terms,
+            connector,
+            charge_power,
+            estimated_wait_ticks,
         }
     }
 
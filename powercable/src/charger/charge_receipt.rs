@@ -0,0 +1,41 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+/// # Description
+/// ChargeReceipt is published by a charger when a vehicle releases its port or its reservation runs out,
+/// for the whole session from the first get request to the release.<br>
+/// The vehicle pays the cost from its wallet and gets what it earned with V2G,
+/// the charger already booked both with every get request and V2G sale.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger.
+/// - `vehicle_name`: The name of the vehicle.
+/// - `tick`: The tick in which the vehicle released the port.
+/// - `energy`: The energy delivered in the session, in kWh.
+/// - `price`: The average price per kWh of the session, in €.
+/// - `cost`: The money the vehicle paid for the session, in €.
+/// - `fed_in`: The energy the vehicle fed into the grid through the charger in the session, in kWh.
+/// - `earned`: The money the vehicle earned for the energy it fed in, in €.
+/// - `duration_ticks`: The number of ticks from the first get request to the release.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChargeReceipt {
+    pub charger_name: String,
+    pub vehicle_name: String,
+    pub tick: u64,
+    pub energy: f64,
+    pub price: f64,
+    pub cost: f64,
+    pub fed_in: f64,
+    pub earned: f64,
+    pub duration_ticks: u64,
+}
+
+impl ChargeReceipt {
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(&bytes)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
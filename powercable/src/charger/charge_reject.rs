@@ -0,0 +1,56 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+/// # Description
+/// RejectReason describes why a charger could not make an offer for a charge request.
+///
+/// # Variants
+/// - `NoFreePorts`: All charging ports of the charger are reserved.
+/// - `NotEnoughCharge`: The charger has no charge left that is not reserved yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum RejectReason {
+    NoFreePorts,
+    NotEnoughCharge,
+}
+
+/// # Description
+/// ChargeReject is sent by a charger to a vehicle, if it can't answer a charge request with an offer.<br>
+/// The vehicle is put on the waitlist of the charger and gets an offer as soon as a port is free again.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger rejecting the request.
+/// - `vehicle_name`: The name of the vehicle whose request was rejected.
+/// - `reason`: Why the request was rejected.
+/// - `estimated_wait_ticks`: The estimated amount of ticks until the charger can make an offer to the vehicle.
+/// - `waitlist_position`: The position of the vehicle on the waitlist, starting at 0.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ChargeReject {
+    pub charger_name: String,
+    pub vehicle_name: String,
+    pub reason: RejectReason,
+    pub estimated_wait_ticks: u64,
+    pub waitlist_position: usize,
+}
+
+impl ChargeReject {
+    /// # Description
+    /// Creates a ChargeReject instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded ChargeReject message.
+    ///
+    /// # Returns
+    /// A Result containing the ChargeReject instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the ChargeReject instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded ChargeReject message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
@@ -2,6 +2,8 @@ use bitcode::{Decode, Encode};
 use bytes::Bytes;
 use crate::Position;
 
+use super::ConnectorType;
+
 /// # Description
 /// ChargeRequest represents a request from a vehicle to a charger for charging.
 /// 
@@ -10,12 +12,24 @@ use crate::Position;
 /// - `charge_amount`: The amount of charge requested in kWh.
 /// - `vehicle_position`: The position of the vehicle making the request.
 /// - `vehicle_consumption`: The vehicle's consumption rate in kWh/100km.
+/// - `connectors`: The connector types the vehicle can be charged with.
+/// - `max_charge_power`: The power in kW the vehicle can currently be charged with, following its charging curve.
+/// - `max_ac_power`: The power limit of the vehicle's on-board charger in kW, for AC ports.
+/// - `cold_derating`: The share of `max_charge_power` a cold battery takes at DC ports, 1.0 for a warm one.
+/// - `max_price`: The highest price per kWh the vehicle is willing to pay.
+/// - `budget`: The most the vehicle is willing to spend on the charge in total, in €.
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct ChargeRequest {
     pub vehicle_name: String,
     pub charge_amount: usize,
     pub vehicle_position: Position,
     pub vehicle_consumption: f64,
+    pub connectors: Vec<ConnectorType>,
+    pub max_charge_power: f64,
+    pub max_ac_power: f64,
+    pub cold_derating: f64,
+    pub max_price: f64,
+    pub budget: f64,
 }
 
 impl ChargeRequest {
@@ -27,15 +41,70 @@ impl ChargeRequest {
     /// - `charge_amount`: The amount of charge requested in kWh.
     /// - `vehicle_position`: The position of the vehicle making the request.
     /// - `vehicle_consumption`: The vehicle's consumption rate in kWh/100km.
+    /// - `connectors`: The connector types the vehicle can be charged with.
+    /// - `max_charge_power`: The power in kW the vehicle can currently be charged with.
+    /// - `max_ac_power`: The power limit of the vehicle's on-board charger in kW.
+    /// - `cold_derating`: The share of `max_charge_power` a cold battery takes at DC ports.
+    /// - `max_price`: The highest price per kWh the vehicle is willing to pay.
+    /// - `budget`: The most the vehicle is willing to spend on the charge in total, in €.
     /// 
     /// # Returns
     /// A new ChargeRequest instance with the specified parameters.
-    pub fn new(vehicle_name: String, charge_amount: usize, vehicle_position: Position, vehicle_consumption: f64) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        vehicle_name: String,
+        charge_amount: usize,
+        vehicle_position: Position,
+        vehicle_consumption: f64,
+        connectors: Vec<ConnectorType>,
+        max_charge_power: f64,
+        max_ac_power: f64,
+        cold_derating: f64,
+        max_price: f64,
+        budget: f64,
+    ) -> Self {
         ChargeRequest {
             vehicle_name,
             charge_amount,
             vehicle_position,
             vehicle_consumption,
+            connectors,
+            max_charge_power,
+            max_ac_power,
+            cold_derating,
+            max_price,
+            budget,
+        }
+    }
+
+    /// # Returns
+    /// Whether the vehicle is nearly empty and pays any price, regardless of its budget.
+    pub fn is_urgent(&self) -> bool {
+        self.max_price == f64::MAX
+    }
+
+    /// # Returns
+    /// How much charge in kWh the vehicle can afford at the given price per kWh, `None` if it won't pay the price at all.
+    /// An urgent request is not limited by its budget.
+    pub fn affordable_charge(&self, price: f64) -> Option<usize> {
+        if self.is_urgent() {
+            return Some(usize::MAX);
+        }
+        if price > self.max_price {
+            return None;
+        }
+        let charge = (self.budget / price.max(f64::EPSILON)).floor();
+        (charge >= 1.0).then_some(charge as usize)
+    }
+
+    /// # Returns
+    /// The power in kW the vehicle can be charged with at a port with the given connector.
+    /// Only fast DC charging is limited by a cold battery.
+    pub fn power_for(&self, connector: ConnectorType) -> f64 {
+        if connector.is_dc() {
+            self.max_charge_power * self.cold_derating
+        } else {
+            self.max_charge_power.min(self.max_ac_power)
         }
     }
 
@@ -0,0 +1,66 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+/// # Description
+/// ChargerStats is the financial summary a charger publishes on every tick, taken from its ledger.<br>
+/// All energy amounts are in kWh, all money in €.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger.
+/// - `tick`: The tick the summary is for.
+/// - `timestamp`: The timestamp of the tick.
+/// - `bought`: The energy bought in the tick.
+/// - `purchase_cost`: The money paid for the energy bought in the tick.
+/// - `sold`: The energy sold to vehicles in the tick.
+/// - `revenue`: The money received for the energy sold in the tick.
+/// - `fees`: The cancellation and no-show fees received in the tick.
+/// - `sold_to_grid`: The energy sold back to the grid from the battery in the tick.
+/// - `grid_revenue`: The money received for the energy sold back to the grid in the tick.
+/// - `v2g_margin`: The share kept of the price of energy of parked vehicles passed on to the grid in the tick.
+/// - `margin`: The revenue of the tick minus what the sold energy cost on average.
+/// - `sellers`: The names of everyone the charger bought energy from in the tick.
+/// - `customers`: The names of all vehicles that bought energy or paid a fee in the tick.
+/// - `total_purchase_cost`: The money paid for energy since the start.
+/// - `total_revenue`: The money received for energy since the start, from vehicles and the grid.
+/// - `total_fees`: The fees received since the start.
+/// - `total_v2g_margin`: The V2G margins kept since the start.
+/// - `total_margin`: The margins of all ticks since the start.
+/// - `total_profit`: The revenue, fees and V2G margins minus the purchase costs since the start.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChargerStats {
+    pub charger_name: String,
+    pub tick: u64,
+    pub timestamp: usize,
+    pub bought: f64,
+    pub purchase_cost: f64,
+    pub sold: f64,
+    pub revenue: f64,
+    pub fees: f64,
+    #[serde(default)]
+    pub sold_to_grid: f64,
+    #[serde(default)]
+    pub grid_revenue: f64,
+    #[serde(default)]
+    pub v2g_margin: f64,
+    pub margin: f64,
+    pub sellers: Vec<String>,
+    pub customers: Vec<String>,
+    pub total_purchase_cost: f64,
+    pub total_revenue: f64,
+    pub total_fees: f64,
+    #[serde(default)]
+    pub total_v2g_margin: f64,
+    #[serde(default)]
+    pub total_margin: f64,
+    pub total_profit: f64,
+}
+
+impl ChargerStats {
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(&bytes)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
@@ -0,0 +1,24 @@
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// # Description
+/// ConnectorType describes the plug of a charging port and of a vehicle inlet.
+///
+/// # Variants
+/// - `Type2`: Type 2 (Mennekes) plug for AC charging.
+/// - `Ccs`: Combined Charging System plug for DC fast charging.
+/// - `Chademo`: CHAdeMO plug for DC fast charging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub enum ConnectorType {
+    Type2,
+    Ccs,
+    Chademo,
+}
+
+impl ConnectorType {
+    /// # Returns
+    /// Whether the connector is used for DC fast charging.
+    pub fn is_dc(&self) -> bool {
+        matches!(self, ConnectorType::Ccs | ConnectorType::Chademo)
+    }
+}
@@ -2,8 +2,24 @@ mod charge_request;
 mod charge_offer;
 mod charge_accept;
 mod charge_port;
+mod charge_cancel;
+mod charge_counter;
+mod charge_eta;
+mod charge_receipt;
+mod charge_reject;
+mod charger_stats;
+mod connector;
+mod v2g;
 
 pub use self::charge_request::ChargeRequest;
-pub use self::charge_offer::ChargeOffer;
+pub use self::charge_offer::{ChargeOffer, ReservationTerms};
 pub use self::charge_accept::ChargeAccept;
-pub use self::charge_port::Get;
\ No newline at end of file
+pub use self::charge_port::Get;
+pub use self::charge_cancel::{CancelReason, ChargeCancel};
+pub use self::charge_counter::ChargeCounter;
+pub use self::charge_eta::ChargeEta;
+pub use self::charge_receipt::ChargeReceipt;
+pub use self::charge_reject::{ChargeReject, RejectReason};
+pub use self::charger_stats::ChargerStats;
+pub use self::connector::ConnectorType;
+pub use self::v2g::{V2gDischarge, V2gOffer};
\ No newline at end of file
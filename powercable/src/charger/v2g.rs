@@ -0,0 +1,82 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+/// # Description
+/// V2gOffer is sent every tick by a vehicle that is parked at a charger in V2G mode.<br>
+/// It offers energy of its battery to the power market, through the charger it is plugged into.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger the vehicle is plugged into.
+/// - `vehicle_name`: The name of the vehicle offering the energy.
+/// - `amount`: The energy the vehicle can feed into the grid in this tick in kWh, after discharge losses.
+/// - `min_price`: The lowest price per kWh the vehicle sells for, its degradation cost included.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct V2gOffer {
+    pub charger_name: String,
+    pub vehicle_name: String,
+    pub amount: usize,
+    pub min_price: f64,
+}
+
+impl V2gOffer {
+    /// # Description
+    /// Creates a V2gOffer instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded V2gOffer message.
+    ///
+    /// # Returns
+    /// A Result containing the V2gOffer instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the V2gOffer instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded V2gOffer message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
+
+/// # Description
+/// V2gDischarge is sent by a charger to a vehicle, after it sold energy of the vehicle on the power market.<br>
+/// The vehicle takes the energy out of its battery and gets paid.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger that sold the energy.
+/// - `vehicle_name`: The name of the vehicle the energy is taken from.
+/// - `amount`: The energy fed into the grid in kWh.
+/// - `price`: The price per kWh the vehicle gets, after the fee of the charger.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct V2gDischarge {
+    pub charger_name: String,
+    pub vehicle_name: String,
+    pub amount: usize,
+    pub price: f64,
+}
+
+impl V2gDischarge {
+    /// # Description
+    /// Creates a V2gDischarge instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded V2gDischarge message.
+    ///
+    /// # Returns
+    /// A Result containing the V2gDischarge instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the V2gDischarge instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded V2gDischarge message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
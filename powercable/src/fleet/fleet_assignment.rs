@@ -0,0 +1,40 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+/// # Description
+/// FleetAssignment is sent by a fleet manager to a vehicle of its fleet, with the charger whose offer it takes.
+///
+/// # Fields
+/// - `fleet`: The name of the fleet.
+/// - `vehicle_name`: The name of the vehicle.
+/// - `charger_name`: The name of the charger whose offer the vehicle accepts,
+///   `None` if the vehicle waits for the next tick because the chargers are taken by the rest of the fleet.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct FleetAssignment {
+    pub fleet: String,
+    pub vehicle_name: String,
+    pub charger_name: Option<String>,
+}
+
+impl FleetAssignment {
+    /// # Description
+    /// Creates a FleetAssignment instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded FleetAssignment message.
+    ///
+    /// # Returns
+    /// A Result containing the FleetAssignment instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the FleetAssignment instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded FleetAssignment message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
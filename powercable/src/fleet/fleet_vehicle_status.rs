@@ -0,0 +1,50 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+use crate::Position;
+
+/// # Description
+/// FleetVehicleStatus is sent by a vehicle of a fleet to its fleet manager on every tick,
+/// so the manager can choose the chargers for all vehicles of the fleet together.
+///
+/// # Fields
+/// - `vehicle_name`: The name of the vehicle.
+/// - `fleet`: The name of the fleet the vehicle belongs to.
+/// - `position`: The position of the vehicle.
+/// - `soc`: The state of charge of the battery (0.0 to 1.0).
+/// - `range`: How far the vehicle gets with the energy left in its battery, in km.
+/// - `speed`: The speed the vehicle drives with in km/h.
+/// - `searching`: Whether the vehicle is looking for a charger and waits for offers.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct FleetVehicleStatus {
+    pub vehicle_name: String,
+    pub fleet: String,
+    pub position: Position,
+    pub soc: f64,
+    pub range: f64,
+    pub speed: f64,
+    pub searching: bool,
+}
+
+impl FleetVehicleStatus {
+    /// # Description
+    /// Creates a FleetVehicleStatus instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded FleetVehicleStatus message.
+    ///
+    /// # Returns
+    /// A Result containing the FleetVehicleStatus instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the FleetVehicleStatus instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded FleetVehicleStatus message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
@@ -0,0 +1,5 @@
+mod fleet_assignment;
+mod fleet_vehicle_status;
+
+pub use self::fleet_assignment::FleetAssignment;
+pub use self::fleet_vehicle_status::FleetVehicleStatus;
@@ -2,12 +2,17 @@ use bitcode::{Decode, Encode};
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 use rand::Rng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub mod charger;
 pub mod chart_entry;
+pub mod fleet;
 pub mod offer;
+pub mod operator;
+pub mod rescue;
+pub mod routing;
 pub mod tickgen;
+pub mod weather;
 
 pub use chart_entry::ChartEntry;
 pub use offer::offer_handler::OfferHandler;
@@ -20,6 +25,14 @@ pub const CONFIG_TURBINE_SCALE: &str = "config/turbine/scale";
 pub const CONFIG_CONSUMER_SCALE: &str = "config/consumer/scale";
 pub const CONFIG_VEHICLE_SCALE: &str = "config/vehicle/scale";
 pub const CONFIG_VEHICLE_ALGORITHM: &str = "config/vehicle/algorithm";
+pub const CONFIG_CHARGER_RESERVATION: &str = "config/charger/reservation";
+pub const CONFIG_CHARGER_STORAGE: &str = "config/charger/storage";
+pub const CONFIG_CHARGER_GRID: &str = "config/charger/grid";
+pub const CONFIG_CHARGER_PRICING: &str = "config/charger/pricing"; // append "/<charger name>" to configure a single charger
+pub const CONFIG_CHARGER_V2G: &str = "config/charger/v2g"; // append "/<charger name>" to configure a single charger
+pub const CONFIG_CHARGER_NEGOTIATION: &str = "config/charger/negotiation";
+pub const CONFIG_VEHICLE_V2G: &str = "config/vehicle/v2g"; // append "/<vehicle name>" to configure a single vehicle
+pub const CONFIG_VEHICLE_SCORING: &str = "config/vehicle/scoring"; // append "/<vehicle name>" to configure a single vehicle
 pub const BUY_OFFER_TOPIC: &str = "market/buy_offer";
 pub const ACCEPT_BUY_OFFER_TOPIC: &str = "market/accept_buy_offer";
 pub const ACK_ACCEPT_BUY_OFFER_TOPIC: &str = "market/ack_accept_buy_offer";
@@ -30,12 +43,15 @@ pub const TICK_CONFIGURE_AMOUNT_TO_RUN: &str = "tickgen/configure_amount_to_run"
 pub const POWER_TRANSFORMER_CONSUMPTION_TOPIC: &str = "power/transformer/consumption";
 pub const POWER_TRANSFORMER_CONSUMPTION_TOPIC_FORMATTED: &str = "power/transformer/consumption/transformed";
 pub const POWER_TRANSFORMER_GENERATION_TOPIC: &str = "power/transformer/generation";
+pub const POWER_TRANSFORMER_GENERATION_TOPIC_FORMATTED: &str = "power/transformer/generation/transformed";
 pub const POWER_TRANSFORMER_STATS_TOPIC: &str = "power/transformer/stats";
 pub const POWER_TRANSFORMER_DIFF_TOPIC: &str = "power/transformer/diff";
 pub const POWER_TRANSFORMER_PRICE_TOPIC: &str = "power/transformer/stats/price";
 pub const POWER_TRANSFORMER_EARNED_TOPIC: &str = "power/transformer/stats/earnings";
 pub const POWER_CHARGER_TOPIC: &str = "power/charger";
 pub const POWER_CHARGER_TRANSFORMED_TOPIC: &str = "power/charger/transformed";
+pub const POWER_CHARGER_GRID_LOAD_TOPIC: &str = "power/charger/grid_load";// charger publishes the load on its grid connection every tick
+pub const POWER_CHARGER_GRID_LOAD_TRANSFORMED_TOPIC: &str = "power/charger/grid_load/transformed";// transformer sends the summed up and peak load of all chargers to frontend
 pub const POWER_CONSUMER_TOPIC: &str = "power/consumer";
 pub const POWER_LOCATION_TOPIC: &str = "power/location";
 pub const WORLDMAP_EVENT_TOPIC: &str = "worldmap/event";
@@ -44,13 +60,50 @@ pub const CHARGER_OFFER: &str = "charger/offer";// charger sends offer to vehicl
 pub const CHARGER_OFFER_AVG_PRICE: &str = "charger/offer/avg/price"; // transformer sends average offer price to frontend
 pub const CHARGER_OFFER_AVG_DISTANCE: &str = "charger/offer/avg/distance"; // transformer sends average offer distance to frontend
 pub const CHARGER_OFFER_AVG_COST: &str = "charger/offer/avg/cost"; // transformer sends average offer cost to frontend
+pub const CHARGER_REJECT: &str = "charger/reject";// charger can't make an offer and puts the vehicle on its waitlist
 pub const CHARGER_ACCEPT: &str = "charger/accept";// vehicle accepts offer from charger
+pub const CHARGER_COUNTER: &str = "charger/counter";// vehicle counters an offer with the price it is willing to pay
+pub const CHARGER_REVISE: &str = "charger/revise";// charger answers a counter offer with a revised offer
+pub const CHARGER_ETA: &str = "charger/eta";// vehicle on its way to the charger it booked sends its estimated arrival every tick
 pub const CHARGER_CHARGING_GET: &str = "charger/charging/get";// vehicle requests energy from the charger
 pub const CHARGER_CHARGING_ACK: &str = "charger/charging/ack";// charger responds with energy to vehicle
 pub const CHARGER_CHARGING_RELEASE: &str = "charger/charging/release";
+pub const CHARGER_RECEIPT: &str = "charger/receipt";// charger sends the receipt of a charging session when the vehicle releases its port
+pub const CHARGER_RECEIPT_TOTAL: &str = "charger/receipt/total";// transformer sends the sessions and the spending of all vehicles to frontend
+pub const CHARGER_STATS: &str = "charger/stats";// charger publishes its ledger summary every tick
+pub const CHARGER_STATS_TOTAL: &str = "charger/stats/total";// transformer sends the summed up ledgers of all chargers to frontend
+pub const CHARGER_CANCEL: &str = "charger/cancel";// vehicle cancels its reservation or charger reports a no-show
+pub const CHARGER_V2G_OFFER: &str = "charger/v2g/offer";// parked vehicle offers energy of its battery through its charger
+pub const CHARGER_V2G_DISCHARGE: &str = "charger/v2g/discharge";// charger sold energy of a vehicle and takes it from its battery
+pub const OPERATOR_HEARTBEAT: &str = "operator/heartbeat";// operator claims its chargers every tick, payload is its name
+pub const OPERATOR_CHARGER_STATUS: &str = "operator/charger/status";// charger of an operator reports its state every tick
+pub const OPERATOR_TRANSFER: &str = "operator/transfer";// operator moves energy to one of its chargers
+pub const OPERATOR_ROUTE: &str = "operator/route";// operator passes a charge request on to its best charger
+pub const CONFIG_OPERATOR: &str = "config/operator";// append "/<operator name>" to configure a single operator
+pub const FLEET_HEARTBEAT: &str = "fleet/heartbeat";// fleet manager claims its vehicles every tick, payload is its name
+pub const FLEET_VEHICLE_STATUS: &str = "fleet/vehicle/status";// vehicle of a fleet reports its state every tick
+pub const FLEET_ASSIGN: &str = "fleet/assign";// fleet manager tells a vehicle which offer to accept
+pub const FLEET_STATS: &str = "fleet/stats";// fleet manager sends the cost of its assignments and of independent choices to frontend
+pub const CONFIG_FLEET: &str = "config/fleet";// append "/<fleet name>" to configure a single fleet
 pub const VEHICLE_TOPIC: &str = "vehicle";
+pub const VEHICLE_DEADLINE_TOPIC: &str = "vehicle/deadline";// vehicle reports by how much it missed its deadline, 0 if it met it
+pub const VEHICLE_DEADLINE_TRANSFORMED_TOPIC: &str = "vehicle/deadline/transformed";// transformer sends the met and missed deadlines of all vehicles to frontend
+pub const VEHICLE_STRANDED_TOPIC: &str = "vehicle/stranded";// stranded vehicle reports every tick for how many ticks it is stranded
+pub const VEHICLE_STRANDED_TRANSFORMED_TOPIC: &str = "vehicle/stranded/transformed";// transformer sends the number of stranded vehicles to frontend
+pub const RESCUE_REQUEST_TOPIC: &str = "rescue/request";// stranded or broken vehicle asks to be towed to a charger
+pub const RESCUE_CANCEL_TOPIC: &str = "rescue/cancel";// vehicle got going again on its own, payload is its name
+pub const RESCUE_TOW_TOPIC: &str = "rescue/tow";// rescue service dropped a vehicle off at a charger
+pub const RESCUE_STATS_TOPIC: &str = "rescue/stats";// rescue service sends its pending and completed rescues to frontend
+pub const CONFIG_RESCUE: &str = "config/rescue";
+pub const CONFIG_VEHICLE_FAILURE: &str = "config/vehicle/failure";
+pub const CONFIG_VEHICLE_SCHEDULE: &str = "config/vehicle/schedule"; // append "/<vehicle name>" to configure a single vehicle
+pub const CONFIG_VEHICLE_TRIP: &str = "config/vehicle/trip";
+pub const CONFIG_VEHICLE_NEGOTIATION: &str = "config/vehicle/negotiation";
+pub const CONFIG_VEHICLE_WALLET: &str = "config/vehicle/wallet"; // append "/<vehicle name>" to configure a single vehicle
+pub const WEATHER_TOPIC: &str = "weather";// turbines send the air temperature at their location every tick
 pub const MQTT_BROKER: &str = "mosquitto_broker";
 pub const MQTT_BROKER_PORT: u16 = 1883;
+pub const MQTT_REQUEST_CAPACITY: usize = 64; // must exceed the subscriptions an agent makes before it polls its event loop
 pub const MAP_UPDATE_SPEED_IN_SECS: u64 = 1;
 pub const RANDOM_SEED: u64 = 02_07_25; // Seed for random number generation
 
@@ -88,7 +141,7 @@ const NEAR_THRESHOLD: f64 = 6.0;
 /// # Fields
 /// - `latitude`: The latitude of the position in degrees.
 /// - `longitude`: The longitude of the position in degrees.
-#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode, Serialize, Deserialize)]
 pub struct Position {
     pub latitude: f64,
     pub longitude: f64,
@@ -0,0 +1,45 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+/// # Description
+/// ChargeTransfer is sent by a charge point operator to move energy to one of its chargers.<br>
+/// The energy is either bought by the operator on the market, or taken from another charger of the operator
+/// to balance their batteries.
+///
+/// # Fields
+/// - `operator`: The name of the charge point operator.
+/// - `from`: The name of the charger giving the energy, `None` if the operator bought it on the market.
+/// - `to`: The name of the charger receiving the energy.
+/// - `amount`: The amount of energy in kWh.
+/// - `price`: The price per kWh the receiving charger pays.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ChargeTransfer {
+    pub operator: String,
+    pub from: Option<String>,
+    pub to: String,
+    pub amount: usize,
+    pub price: f64,
+}
+
+impl ChargeTransfer {
+    /// # Description
+    /// Creates a ChargeTransfer instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded ChargeTransfer message.
+    ///
+    /// # Returns
+    /// A Result containing the ChargeTransfer instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the ChargeTransfer instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded ChargeTransfer message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
@@ -0,0 +1,66 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+use crate::{charger::ConnectorType, Position};
+
+/// # Description
+/// ChargerStatus is sent by a charger to its charge point operator on every tick,
+/// so the operator can buy energy, balance the batteries and route requests for it.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger.
+/// - `operator`: The name of the charge point operator the charger belongs to.
+/// - `position`: The position of the charger.
+/// - `current_charge`: The charge in the buffer battery in kWh.
+/// - `capacity`: The capacity of the buffer battery in kWh.
+/// - `available_charge`: The charge that is not reserved for vehicles in kWh.
+/// - `grid_energy_per_tick`: The energy the grid connection can take in a tick in kWh.
+/// - `connectors`: The connector types of all ports.
+/// - `free_connectors`: The connector types of all free ports.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ChargerStatus {
+    pub charger_name: String,
+    pub operator: String,
+    pub position: Position,
+    pub current_charge: usize,
+    pub capacity: usize,
+    pub available_charge: usize,
+    pub grid_energy_per_tick: usize,
+    pub connectors: Vec<ConnectorType>,
+    pub free_connectors: Vec<ConnectorType>,
+}
+
+impl ChargerStatus {
+    /// # Returns
+    /// The state of charge of the buffer battery (0.0 to 1.0).
+    pub fn soc(&self) -> f64 {
+        self.current_charge as f64 / self.capacity.max(1) as f64
+    }
+
+    /// # Returns
+    /// The charge in kWh that is missing to fill the buffer battery.
+    pub fn needed_charge(&self) -> usize {
+        self.capacity.saturating_sub(self.current_charge)
+    }
+
+    /// # Description
+    /// Creates a ChargerStatus instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded ChargerStatus message.
+    ///
+    /// # Returns
+    /// A Result containing the ChargerStatus instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the ChargerStatus instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded ChargerStatus message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
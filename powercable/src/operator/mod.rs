@@ -0,0 +1,7 @@
+mod charger_status;
+mod charge_transfer;
+mod routed_request;
+
+pub use self::charger_status::ChargerStatus;
+pub use self::charge_transfer::ChargeTransfer;
+pub use self::routed_request::RoutedRequest;
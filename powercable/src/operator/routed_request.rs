@@ -0,0 +1,40 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+use crate::charger::ChargeRequest;
+
+/// # Description
+/// RoutedRequest is sent by a charge point operator to pass a charge request on to the charger
+/// that should answer it.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger that should answer the request.
+/// - `request`: The charge request of the vehicle.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct RoutedRequest {
+    pub charger_name: String,
+    pub request: ChargeRequest,
+}
+
+impl RoutedRequest {
+    /// # Description
+    /// Creates a RoutedRequest instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded RoutedRequest message.
+    ///
+    /// # Returns
+    /// A Result containing the RoutedRequest instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the RoutedRequest instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded RoutedRequest message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
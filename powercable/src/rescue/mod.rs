@@ -0,0 +1,5 @@
+mod rescue_request;
+mod rescue_tow;
+
+pub use self::rescue_request::{RescueReason, RescueRequest};
+pub use self::rescue_tow::RescueTow;
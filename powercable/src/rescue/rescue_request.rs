@@ -0,0 +1,53 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+use crate::Position;
+
+/// # Description
+/// RescueReason describes why a vehicle can't continue on its own.
+///
+/// # Variants
+/// - `Stranded`: The battery is empty or no charger is in range.
+/// - `Broken`: The vehicle had a mechanical failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum RescueReason {
+    Stranded,
+    Broken,
+}
+
+/// # Description
+/// RescueRequest is sent by a vehicle that can't continue on its own, so the rescue service tows it to a charger.
+///
+/// # Fields
+/// - `vehicle_name`: The name of the vehicle.
+/// - `position`: The position the vehicle is stuck at.
+/// - `reason`: Why the vehicle needs to be rescued.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct RescueRequest {
+    pub vehicle_name: String,
+    pub position: Position,
+    pub reason: RescueReason,
+}
+
+impl RescueRequest {
+    /// # Description
+    /// Creates a RescueRequest instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded RescueRequest message.
+    ///
+    /// # Returns
+    /// A Result containing the RescueRequest instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the RescueRequest instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded RescueRequest message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
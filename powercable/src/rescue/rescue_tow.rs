@@ -0,0 +1,43 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+use crate::Position;
+
+/// # Description
+/// RescueTow is sent by the rescue service when it dropped a vehicle off at a charger.
+///
+/// # Fields
+/// - `vehicle_name`: The name of the towed vehicle.
+/// - `charger_name`: The name of the charger the vehicle was towed to.
+/// - `position`: The position of the charger.
+/// - `rescue_ticks`: The ticks from the request until the vehicle was at the charger.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct RescueTow {
+    pub vehicle_name: String,
+    pub charger_name: String,
+    pub position: Position,
+    pub rescue_ticks: u64,
+}
+
+impl RescueTow {
+    /// # Description
+    /// Creates a RescueTow instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded RescueTow message.
+    ///
+    /// # Returns
+    /// A Result containing the RescueTow instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the RescueTow instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded RescueTow message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
@@ -0,0 +1,610 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    error::Error,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use serde_json::Value;
+
+use crate::Position;
+
+/// Path to a GeoJSON road network, vehicles drive in straight lines if not set
+const ROAD_NETWORK: &str = "ROAD_NETWORK";
+/// Speed in km/h on the way between a position and the closest node of the road network
+const ACCESS_SPEED: f64 = 30.0;
+/// Speed in km/h on roads of an unknown class
+const DEFAULT_ROAD_SPEED: f64 = 50.0;
+/// Edge length in degrees of a cell of the spatial index of the nodes
+const CELL_SIZE: f64 = 0.01;
+/// Length in km of one degree of latitude
+const KM_PER_DEGREE: f64 = 6371.0 * std::f64::consts::PI / 180.0;
+/// Number of routes between node pairs that are kept, the least recently used one is dropped first
+const ROUTE_CACHE_SIZE: usize = 1024;
+
+static NETWORK: OnceLock<Option<RoadNetwork>> = OnceLock::new();
+
+/// # Description
+/// Returns the typical speed on a road of an OpenStreetMap `highway` class.
+///
+/// # Arguments
+/// - `class`: The road class, e.g. `motorway` or `residential`.
+///
+/// # Returns
+/// The speed in km/h.
+pub fn road_class_speed(class: &str) -> f64 {
+    match class.trim_end_matches("_link") {
+        "motorway" => 120.0,
+        "trunk" => 100.0,
+        "primary" => 80.0,
+        "secondary" => 70.0,
+        "tertiary" => 60.0,
+        "unclassified" => 50.0,
+        "residential" | "living_street" | "service" => 30.0,
+        _ => DEFAULT_ROAD_SPEED,
+    }
+}
+
+/// # Description
+/// A route through the road network that a vehicle follows point by point.
+///
+/// # Fields
+/// - `points`: The points of the route, from the start to the destination.
+/// - `speeds`: The speed in km/h on the leg from each point to the next one.
+/// - `length`: The length of the route in km.
+/// - `next`: The index of the next point the vehicle drives to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    points: Vec<Position>,
+    speeds: Vec<f64>,
+    pub length: f64,
+    next: usize,
+}
+
+impl Route {
+    /// # Description
+    /// Creates a route from its points and the speeds of the legs in between.
+    ///
+    /// # Arguments
+    /// - `points`: The points of the route, at least the start and the destination.
+    /// - `speeds`: The speed in km/h of each leg, one less than points.
+    pub fn new(points: Vec<Position>, speeds: Vec<f64>) -> Self {
+        let length = points.windows(2).map(|leg| leg[0].distance_to(leg[1])).sum();
+        Route { points, speeds, length, next: 1 }
+    }
+
+    /// # Description
+    /// Creates a straight route, used if the road network doesn't connect the positions.
+    ///
+    /// # Arguments
+    /// - `from`: The start of the route.
+    /// - `to`: The destination of the route.
+    /// - `speed`: The speed in km/h.
+    pub fn straight(from: Position, to: Position, speed: f64) -> Self {
+        Route::new(vec![from, to], vec![speed])
+    }
+
+    /// # Returns
+    /// The destination of the route.
+    pub fn destination(&self) -> Position {
+        *self.points.last().unwrap()
+    }
+
+    /// # Returns
+    /// The next point to drive to and the speed on the way there, `None` if the destination is reached.
+    pub fn next_leg(&self) -> Option<(Position, f64)> {
+        Some((*self.points.get(self.next)?, self.speeds[self.next - 1]))
+    }
+
+    /// # Description
+    /// Estimates how long it takes to drive the rest of the route.
+    ///
+    /// # Arguments
+    /// - `from`: The current position on the route.
+    /// - `speed`: The speed in km/h the vehicle drives on a road with the given speed.
+    ///
+    /// # Returns
+    /// The time in hours.
+    pub fn hours_left(&self, from: Position, speed: impl Fn(f64) -> f64) -> f64 {
+        let mut position = from;
+        let mut hours = 0.0;
+        for next in self.next..self.points.len() {
+            hours += position.distance_to(self.points[next]) / speed(self.speeds[next - 1]).max(1.0);
+            position = self.points[next];
+        }
+        hours
+    }
+
+    /// # Description
+    /// Marks the next point as reached.
+    pub fn advance(&mut self) {
+        self.next += 1;
+    }
+}
+
+/// # Description
+/// An entry of the priority queue of the A* search, ordered so the lowest estimate is popped first.
+///
+/// # Fields
+/// - `hours`: The time to the node plus the lowest possible time from there to the goal.
+#[derive(Debug, PartialEq)]
+struct Visit {
+    hours: f64,
+    node: usize,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.hours.total_cmp(&self.hours).then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// # Description
+/// The fastest way between two nodes of the road network.
+///
+/// # Fields
+/// - `nodes`: The nodes from the start to the goal.
+/// - `speeds`: The speed in km/h on the road from each node to the next one.
+#[derive(Debug, PartialEq)]
+struct NodePath {
+    nodes: Vec<usize>,
+    speeds: Vec<f64>,
+}
+
+/// A cached path, `None` if the nodes are not connected
+type CachedPath = Option<Arc<NodePath>>;
+
+/// # Description
+/// The recently found paths between node pairs, `None` if the nodes are not connected.
+///
+/// # Fields
+/// - `paths`: The paths with the use they were last looked up in.
+/// - `uses`: The number of lookups and insertions so far.
+#[derive(Debug, Default)]
+struct RouteCache {
+    paths: HashMap<(usize, usize), (u64, CachedPath)>,
+    uses: u64,
+}
+
+impl RouteCache {
+    /// Returns the cached path between the nodes, `None` if it isn't cached.
+    fn get(&mut self, key: (usize, usize)) -> Option<CachedPath> {
+        self.uses += 1;
+        let (last_use, path) = self.paths.get_mut(&key)?;
+        *last_use = self.uses;
+        Some(path.clone())
+    }
+
+    /// Caches the path between the nodes, dropping the least recently used one if the cache is full.
+    fn insert(&mut self, key: (usize, usize), path: CachedPath) {
+        if self.paths.len() >= ROUTE_CACHE_SIZE && !self.paths.contains_key(&key) {
+            if let Some(oldest) = self.paths.iter().min_by_key(|(_, (last_use, _))| *last_use).map(|(k, _)| *k) {
+                self.paths.remove(&oldest);
+            }
+        }
+        self.uses += 1;
+        self.paths.insert(key, (self.uses, path));
+    }
+}
+
+/// Returns the cell of the spatial index a position lies in.
+fn cell_of(position: Position) -> (i64, i64) {
+    ((position.latitude / CELL_SIZE).floor() as i64, (position.longitude / CELL_SIZE).floor() as i64)
+}
+
+/// # Description
+/// A road network as an undirected graph, built from the line strings of a GeoJSON file.<br>
+/// Line strings that share a coordinate are connected there.
+///
+/// # Fields
+/// - `nodes`: The positions of all nodes.
+/// - `edges`: For every node, the neighbouring nodes with the length in km and the speed in km/h of the road in between.
+/// - `cells`: The nodes in each cell of a grid over the network, to find the closest node without checking all of them.
+/// - `cell_bounds`: The lowest and the highest cell that contains a node.
+/// - `max_latitude`: The highest absolute latitude of a node, where a degree of longitude is shortest.
+/// - `max_speed`: The highest speed in km/h on any road.
+/// - `route_cache`: The recently found paths between node pairs.
+#[derive(Debug, Default)]
+pub struct RoadNetwork {
+    nodes: Vec<Position>,
+    edges: Vec<Vec<(usize, f64, f64)>>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    cell_bounds: Option<((i64, i64), (i64, i64))>,
+    max_latitude: f64,
+    max_speed: f64,
+    route_cache: Mutex<RouteCache>,
+}
+
+impl RoadNetwork {
+    /// # Description
+    /// Reads a road network from a GeoJSON file.
+    ///
+    /// # Arguments
+    /// - `path`: The path to the GeoJSON file.
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_geojson(&std::fs::read_to_string(path)?)
+    }
+
+    /// # Description
+    /// Builds a road network from a GeoJSON feature collection.<br>
+    /// Every `LineString` and `MultiLineString` is a road. Its speed is taken from the `maxspeed` property,
+    /// or from the road class in the `highway` property.
+    ///
+    /// # Arguments
+    /// - `geojson`: The GeoJSON text.
+    pub fn from_geojson(geojson: &str) -> Result<Self, Box<dyn Error>> {
+        let json: Value = serde_json::from_str(geojson)?;
+        let features = json["features"].as_array().ok_or("GeoJSON has no features")?;
+
+        let mut network = RoadNetwork::default();
+        let mut node_ids: HashMap<(i64, i64), usize> = HashMap::new();
+        for feature in features {
+            let properties = &feature["properties"];
+            let speed = match &properties["maxspeed"] {
+                Value::Number(n) => n.as_f64(),
+                Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            }
+            .unwrap_or_else(|| road_class_speed(properties["highway"].as_str().unwrap_or_default()));
+
+            let geometry = &feature["geometry"];
+            let lines: Vec<&Value> = match geometry["type"].as_str() {
+                Some("LineString") => vec![&geometry["coordinates"]],
+                Some("MultiLineString") => geometry["coordinates"].as_array().map(|l| l.iter().collect()).unwrap_or_default(),
+                _ => continue,
+            };
+            for line in lines {
+                let Some(coordinates) = line.as_array() else { continue };
+                let ids: Vec<usize> = coordinates
+                    .iter()
+                    .filter_map(|c| Some(Position::new(c[1].as_f64()?, c[0].as_f64()?)))
+                    .map(|position| network.node_id(&mut node_ids, position))
+                    .collect();
+                for leg in ids.windows(2) {
+                    network.connect(leg[0], leg[1], speed);
+                }
+            }
+        }
+
+        if network.nodes.is_empty() {
+            return Err("GeoJSON contains no roads".into());
+        }
+        Ok(network)
+    }
+
+    /// Returns the node at the position, adding it if it is new.
+    fn node_id(&mut self, node_ids: &mut HashMap<(i64, i64), usize>, position: Position) -> usize {
+        let key = ((position.latitude * 1e6).round() as i64, (position.longitude * 1e6).round() as i64);
+        *node_ids.entry(key).or_insert_with(|| {
+            let id = self.nodes.len();
+            self.nodes.push(position);
+            self.edges.push(Vec::new());
+
+            let cell = cell_of(position);
+            self.cells.entry(cell).or_default().push(id);
+            self.cell_bounds = Some(match self.cell_bounds {
+                Some((low, high)) => ((low.0.min(cell.0), low.1.min(cell.1)), (high.0.max(cell.0), high.1.max(cell.1))),
+                None => (cell, cell),
+            });
+            self.max_latitude = self.max_latitude.max(position.latitude.abs());
+            id
+        })
+    }
+
+    /// Adds a road in both directions.
+    fn connect(&mut self, a: usize, b: usize, speed: f64) {
+        if a == b {
+            return;
+        }
+        let length = self.nodes[a].distance_to(self.nodes[b]);
+        let speed = speed.max(1.0);
+        self.max_speed = self.max_speed.max(speed);
+        self.edges[a].push((b, length, speed));
+        self.edges[b].push((a, length, speed));
+    }
+
+    /// # Returns
+    /// The number of nodes of the road network.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// # Returns
+    /// Whether the road network has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// # Description
+    /// Returns the node closest to the position.<br>
+    /// The cells around the position are searched ring by ring,
+    /// until the closest node found so far is nearer than any node in the next ring can be.
+    fn nearest_node(&self, position: Position) -> Option<usize> {
+        let ((low_row, low_col), (high_row, high_col)) = self.cell_bounds?;
+        let (row, col) = cell_of(position);
+        let last_ring = (row - low_row).max(high_row - row).max(col - low_col).max(high_col - col).max(0);
+        // A degree of longitude is shortest at the highest latitude
+        let cell_km = CELL_SIZE * KM_PER_DEGREE * self.max_latitude.max(position.latitude.abs()).to_radians().cos();
+
+        let mut nearest: Option<(usize, f64)> = None;
+        for ring in 0..=last_ring {
+            if nearest.is_some_and(|(_, distance)| distance <= (ring - 1) as f64 * cell_km) {
+                break;
+            }
+            for r in (row - ring).max(low_row)..=(row + ring).min(high_row) {
+                // Inner rows of the ring only have a cell on each side
+                let cols: Vec<i64> = if (r - row).abs() == ring {
+                    ((col - ring).max(low_col)..=(col + ring).min(high_col)).collect()
+                } else {
+                    [col - ring, col + ring].into_iter().filter(|c| (low_col..=high_col).contains(c)).collect()
+                };
+                for c in cols {
+                    for &node in self.cells.get(&(r, c)).into_iter().flatten() {
+                        let distance = position.distance_to(self.nodes[node]);
+                        if nearest.is_none_or(|(_, best)| distance < best) {
+                            nearest = Some((node, distance));
+                        }
+                    }
+                }
+            }
+        }
+        nearest.map(|(node, _)| node)
+    }
+
+    /// # Description
+    /// Finds the fastest path between two nodes with the A* algorithm.<br>
+    /// The straight distance at the highest speed of the network is the lowest possible time to the goal.
+    fn node_path(&self, start: usize, goal: usize) -> Option<NodePath> {
+        let estimate = |node: usize| self.nodes[node].distance_to(self.nodes[goal]) / self.max_speed.max(1.0);
+        let mut hours = vec![f64::INFINITY; self.nodes.len()];
+        let mut previous: Vec<Option<(usize, f64)>> = vec![None; self.nodes.len()];
+        let mut done = vec![false; self.nodes.len()];
+        let mut queue = BinaryHeap::new();
+        hours[start] = 0.0;
+        queue.push(Visit { hours: estimate(start), node: start });
+
+        while let Some(Visit { node, .. }) = queue.pop() {
+            if node == goal {
+                break;
+            }
+            if done[node] {
+                continue;
+            }
+            done[node] = true;
+            for &(next, length, speed) in &self.edges[node] {
+                let arrival = hours[node] + length / speed;
+                if arrival < hours[next] {
+                    hours[next] = arrival;
+                    previous[next] = Some((node, speed));
+                    queue.push(Visit { hours: arrival + estimate(next), node: next });
+                }
+            }
+        }
+        if hours[goal].is_infinite() {
+            return None;
+        }
+
+        // Walk back from the goal
+        let mut nodes = vec![goal];
+        let mut speeds = Vec::new();
+        while let Some((prev, speed)) = previous[*nodes.last().unwrap()] {
+            nodes.push(prev);
+            speeds.push(speed);
+        }
+        nodes.reverse();
+        speeds.reverse();
+        Some(NodePath { nodes, speeds })
+    }
+
+    /// # Description
+    /// Finds the fastest route between two positions with the A* algorithm.<br>
+    /// The route starts and ends at the given positions and joins the road network at the closest nodes.
+    /// The paths between the closest nodes are cached, so routes between nearby positions are only searched once.
+    ///
+    /// # Arguments
+    /// - `from`: The start of the route.
+    /// - `to`: The destination of the route.
+    ///
+    /// # Returns
+    /// The route, or `None` if the closest nodes are not connected.
+    pub fn route(&self, from: Position, to: Position) -> Option<Route> {
+        let start = self.nearest_node(from)?;
+        let goal = self.nearest_node(to)?;
+
+        let cached = self.route_cache.lock().unwrap().get((start, goal));
+        let path = match cached {
+            Some(path) => path,
+            None => {
+                let path = self.node_path(start, goal).map(Arc::new);
+                self.route_cache.lock().unwrap().insert((start, goal), path.clone());
+                path
+            }
+        }?;
+
+        // Add the ways on and off the road network
+        let mut points = vec![from];
+        points.extend(path.nodes.iter().map(|node| self.nodes[*node]));
+        points.push(to);
+        let mut speeds = vec![ACCESS_SPEED];
+        speeds.extend(&path.speeds);
+        speeds.push(ACCESS_SPEED);
+        Some(Route::new(points, speeds))
+    }
+}
+
+/// # Description
+/// Returns the road network configured in the `ROAD_NETWORK` environment variable.<br>
+/// It is loaded once, on the first call.
+///
+/// # Returns
+/// The road network, or `None` if none is configured or it can't be read.
+pub fn road_network() -> Option<&'static RoadNetwork> {
+    NETWORK
+        .get_or_init(|| {
+            let path = std::env::var(ROAD_NETWORK).ok()?;
+            match RoadNetwork::load(&path) {
+                Ok(network) => {
+                    tracing::info!("Loaded road network {} with {} nodes", path, network.len());
+                    Some(network)
+                }
+                Err(e) => {
+                    tracing::error!("Could not read road network {}: {}", path, e);
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+/// # Description
+/// Returns the distance between two positions on the road network,
+/// or in a straight line if no road network is configured or it doesn't connect them.
+///
+/// # Arguments
+/// - `from`: The start.
+/// - `to`: The destination.
+///
+/// # Returns
+/// The distance in km.
+pub fn road_distance(from: Position, to: Position) -> f64 {
+    road_network()
+        .and_then(|network| network.route(from, to))
+        .map(|route| route.length)
+        .unwrap_or_else(|| from.distance_to(to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: Position = Position { latitude: 52.0, longitude: 13.0 };
+    const B: Position = Position { latitude: 52.0, longitude: 13.1 };
+    const C: Position = Position { latitude: 52.05, longitude: 13.05 };
+
+    fn road(points: &[Position], properties: &str) -> String {
+        let coordinates: Vec<String> = points.iter().map(|p| format!("[{}, {}]", p.longitude, p.latitude)).collect();
+        format!(
+            r#"{{"type": "Feature", "properties": {}, "geometry": {{"type": "LineString", "coordinates": [{}]}}}}"#,
+            properties,
+            coordinates.join(", ")
+        )
+    }
+
+    fn network(roads: &[String]) -> RoadNetwork {
+        RoadNetwork::from_geojson(&format!(r#"{{"type": "FeatureCollection", "features": [{}]}}"#, roads.join(", "))).unwrap()
+    }
+
+    #[test]
+    fn speeds_follow_road_class() {
+        assert_eq!(road_class_speed("motorway"), 120.0);
+        assert_eq!(road_class_speed("motorway_link"), 120.0);
+        assert_eq!(road_class_speed("living_street"), 30.0);
+        assert_eq!(road_class_speed("track"), DEFAULT_ROAD_SPEED);
+    }
+
+    #[test]
+    fn roads_are_joined_at_shared_coordinates() {
+        let network = network(&[road(&[A, C], r#"{"highway": "primary"}"#), road(&[C, B], r#"{"maxspeed": "70"}"#)]);
+        assert_eq!(network.len(), 3);
+        assert_eq!(network.edges[1].len(), 2);
+        assert_eq!(network.edges[0][0].2, 80.0);
+        assert_eq!(network.edges[2][0].2, 70.0);
+    }
+
+    #[test]
+    fn fastest_route_takes_the_motorway() {
+        let network = network(&[
+            road(&[A, B], r#"{"highway": "residential"}"#),
+            road(&[A, C, B], r#"{"highway": "motorway"}"#),
+        ]);
+
+        let route = network.route(A, B).unwrap();
+        assert_eq!(route.points, vec![A, A, C, B, B]);
+        assert_eq!(route.speeds, vec![ACCESS_SPEED, 120.0, 120.0, ACCESS_SPEED]);
+        assert!(route.length > A.distance_to(B));
+    }
+
+    #[test]
+    fn nearest_node_matches_a_full_search() {
+        // A grid of roads spanning many cells of the spatial index
+        let roads: Vec<String> = (0..20)
+            .map(|i| {
+                let points: Vec<Position> = (0..20).map(|j| Position::new(52.0 + i as f64 * 0.013, 13.0 + j as f64 * 0.017)).collect();
+                road(&points, "{}")
+            })
+            .collect();
+        let network = network(&roads);
+
+        for position in [A, B, C, Position::new(52.1234, 13.2345), Position::new(51.5, 12.0), Position::new(53.0, 14.5)] {
+            let closest = (0..network.len())
+                .min_by(|a, b| position.distance_to(network.nodes[*a]).total_cmp(&position.distance_to(network.nodes[*b])))
+                .unwrap();
+            assert_eq!(network.nearest_node(position), Some(closest));
+        }
+    }
+
+    #[test]
+    fn routes_between_node_pairs_are_cached() {
+        let network = network(&[road(&[A, C, B], r#"{"highway": "motorway"}"#)]);
+        let route = network.route(A, B).unwrap();
+        assert_eq!(network.route_cache.lock().unwrap().paths.len(), 1);
+
+        // A start close to the same node takes the cached path
+        let near_a = Position::new(52.0001, 13.0001);
+        let cached = network.route(near_a, B).unwrap();
+        assert_eq!(network.route_cache.lock().unwrap().paths.len(), 1);
+        assert_eq!(cached.points[1..], route.points[1..]);
+        assert_eq!(cached.points[0], near_a);
+    }
+
+    #[test]
+    fn least_recently_used_route_is_dropped() {
+        let mut cache = RouteCache::default();
+        for i in 0..ROUTE_CACHE_SIZE {
+            cache.insert((i, i), None);
+        }
+        assert!(cache.get((0, 0)).is_some());
+        cache.insert((ROUTE_CACHE_SIZE, 0), None);
+        assert_eq!(cache.paths.len(), ROUTE_CACHE_SIZE);
+        assert!(cache.get((0, 0)).is_some());
+        assert!(cache.get((1, 1)).is_none());
+    }
+
+    #[test]
+    fn disconnected_roads_have_no_route() {
+        let far = Position::new(53.0, 14.0);
+        let network = network(&[road(&[A, B], "{}"), road(&[far, Position::new(53.0, 14.1)], "{}")]);
+        assert!(network.route(A, far).is_none());
+        assert!(network.route(A, B).is_some());
+    }
+
+    #[test]
+    fn geojson_without_roads_is_rejected() {
+        assert!(RoadNetwork::from_geojson(r#"{"type": "FeatureCollection", "features": []}"#).is_err());
+        assert!(RoadNetwork::from_geojson("not json").is_err());
+    }
+
+    #[test]
+    fn route_is_driven_leg_by_leg() {
+        let mut route = Route::new(vec![A, C, B], vec![60.0, 120.0]);
+        assert_eq!(route.destination(), B);
+        assert_eq!(route.next_leg(), Some((C, 60.0)));
+
+        let hours = route.hours_left(A, |speed| speed);
+        let expected = A.distance_to(C) / 60.0 + C.distance_to(B) / 120.0;
+        assert!((hours - expected).abs() < 1e-9);
+
+        route.advance();
+        assert_eq!(route.next_leg(), Some((B, 120.0)));
+        route.advance();
+        assert_eq!(route.next_leg(), None);
+    }
+}
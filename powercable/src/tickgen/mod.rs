@@ -9,6 +9,16 @@ pub const PHASE_AS_MIN: usize = TICK_AS_MIN / 3;// our phase in minutes
 pub const PHASE_AS_SEC: usize = PHASE_AS_MIN * 60;// our phase in seconds
 pub const PHASE_AS_HOUR: f64 = PHASE_AS_MIN as f64 / 60.0;// our phase in hours
 
+/// Returns the minute of the day (0 to 1439) of a timestamp of the tick generator in milliseconds
+pub fn minute_of_day(timestamp: usize) -> usize {
+    (timestamp / 60_000) % (24 * 60)
+}
+
+/// Returns the hour of the day (0 to 23) of a timestamp of the tick generator in milliseconds
+pub fn hour_of_day(timestamp: usize) -> usize {
+    minute_of_day(timestamp) / 60
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum Phase {
     Process,
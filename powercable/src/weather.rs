@@ -0,0 +1,42 @@
+use bitcode::{Decode, Encode};
+use bytes::Bytes;
+
+use crate::Position;
+
+/// # Description
+/// WeatherReport is sent every tick by each turbine with the air temperature at its location,
+/// approximated from the closest DWD weather stations.
+///
+/// # Fields
+/// - `station`: The name of the reporting turbine.
+/// - `position`: The position the temperature applies to.
+/// - `air_temperature`: The air temperature in °C.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct WeatherReport {
+    pub station: String,
+    pub position: Position,
+    pub air_temperature: f64,
+}
+
+impl WeatherReport {
+    /// # Description
+    /// Creates a WeatherReport instance from a byte array.
+    ///
+    /// # Arguments
+    /// - `bytes`: A byte array containing the encoded WeatherReport message.
+    ///
+    /// # Returns
+    /// A Result containing the WeatherReport instance if decoding is successful, or an error if it fails.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, bitcode::Error> {
+        bitcode::decode(&bytes)
+    }
+
+    /// # Description
+    /// Converts the WeatherReport instance to a byte array.
+    ///
+    /// # Returns
+    /// A Bytes instance containing the encoded WeatherReport message.
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(bitcode::encode(self))
+    }
+}
@@ -0,0 +1,166 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use powercable::{
+    rescue::RescueRequest, Position, CONFIG_RESCUE, POWER_LOCATION_TOPIC, RESCUE_CANCEL_TOPIC, RESCUE_REQUEST_TOPIC,
+    TICK_TOPIC,
+};
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use serde::Deserialize;
+use tokio::{sync::Mutex, task};
+use tracing::{info, warn};
+
+use crate::topic_handler::{cancel_handler, config_handler, location_handler, request_handler, tick_handler};
+
+mod topic_handler;
+
+type SharedRescue = Arc<Mutex<RescueHandler>>;
+
+/// # Description
+/// The configuration of the rescue service, set on the `CONFIG_RESCUE` topic.
+///
+/// # Fields
+/// - `response_ticks`: The ticks until a tow truck is at the vehicle.
+/// - `tow_speed`: The speed of the tow truck in km/h, on the way from the vehicle to the charger.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RescueConfig {
+    pub response_ticks: u64,
+    pub tow_speed: f64,
+}
+
+impl Default for RescueConfig {
+    fn default() -> Self {
+        RescueConfig {
+            response_ticks: 4,
+            tow_speed: 60.0,
+        }
+    }
+}
+
+/// # Description
+/// A vehicle waiting for the rescue service.
+///
+/// # Fields
+/// - `request`: The request of the vehicle.
+/// - `requested_tick`: The tick the request was received in.
+#[derive(Debug, Clone)]
+struct PendingRescue {
+    pub request: RescueRequest,
+    pub requested_tick: u64,
+}
+
+/// # Description
+/// The `RescueHandler` is a roadside rescue service.<br>
+/// It tows stranded and broken vehicles to the nearest charger, after a response time and the time of the tow.
+///
+/// # Fields
+/// - `client`: The MQTT client.
+/// - `current_tick`: The current tick.
+/// - `config`: The configuration of the rescue service.
+/// - `chargers`: The positions of all chargers, as they appear on the world map.
+/// - `pending`: The vehicles waiting to be rescued, by name.
+/// - `breakdowns`: The rescue requests of broken vehicles since the start.
+/// - `strandings`: The rescue requests of stranded vehicles since the start.
+/// - `completed`: The vehicles towed to a charger since the start.
+/// - `rescue_ticks`: The ticks all completed rescues took in total.
+struct RescueHandler {
+    pub client: AsyncClient,
+    pub current_tick: u64,
+    pub config: RescueConfig,
+    pub chargers: HashMap<String, Position>,
+    pub pending: HashMap<String, PendingRescue>,
+    pub breakdowns: usize,
+    pub strandings: usize,
+    pub completed: usize,
+    pub rescue_ticks: u64,
+}
+
+impl RescueHandler {
+    /// # Returns
+    /// The name and position of the charger closest to the position, `None` if no charger is known yet.
+    pub fn nearest_charger(&self, position: Position) -> Option<(String, Position)> {
+        self.chargers
+            .iter()
+            .min_by(|a, b| position.distance_to(*a.1).total_cmp(&position.distance_to(*b.1)))
+            .map(|(name, position)| (name.clone(), *position))
+    }
+
+    /// # Returns
+    /// The average ticks from the request until the vehicle was at a charger, 0.0 if no rescue is completed yet.
+    pub fn avg_rescue_ticks(&self) -> f64 {
+        if self.completed > 0 {
+            self.rescue_ticks as f64 / self.completed as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// # Description
+/// Starts the roadside rescue service.
+pub async fn start_rescue_service() {
+    info!("Starting rescue service...");
+
+    let mut mqttoptions = MqttOptions::new("Rescue Service", powercable::MQTT_BROKER, powercable::MQTT_BROKER_PORT);
+    mqttoptions.set_keep_alive(Duration::from_secs(5));
+    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+    client
+        .subscribe(TICK_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(POWER_LOCATION_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(RESCUE_REQUEST_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(RESCUE_CANCEL_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CONFIG_RESCUE, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    info!("Connected to MQTT broker");
+
+    let shared_rescue = Arc::new(Mutex::new(RescueHandler {
+        client: client.clone(),
+        current_tick: 0,
+        config: RescueConfig::default(),
+        chargers: HashMap::new(),
+        pending: HashMap::new(),
+        breakdowns: 0,
+        strandings: 0,
+        completed: 0,
+        rescue_ticks: 0,
+    }));
+
+    while let Ok(notification) = eventloop.poll().await {
+        if let rumqttc::Event::Incoming(rumqttc::Packet::Publish(p)) = notification {
+            match p.topic.as_str() {
+                TICK_TOPIC => {
+                    task::spawn(tick_handler(shared_rescue.clone(), p.payload));
+                }
+                POWER_LOCATION_TOPIC => {
+                    task::spawn(location_handler(shared_rescue.clone(), p.payload));
+                }
+                RESCUE_REQUEST_TOPIC => {
+                    task::spawn(request_handler(shared_rescue.clone(), p.payload));
+                }
+                RESCUE_CANCEL_TOPIC => {
+                    task::spawn(cancel_handler(shared_rescue.clone(), p.payload));
+                }
+                CONFIG_RESCUE => {
+                    task::spawn(config_handler(shared_rescue.clone(), p.payload));
+                }
+                _ => {
+                    warn!("Unknown topic: {}", p.topic);
+                }
+            }
+        }
+    }
+    println!("Exiting rescue service simulation...");
+}
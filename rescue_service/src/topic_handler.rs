@@ -0,0 +1,162 @@
+use bytes::Bytes;
+use powercable::{
+    rescue::{RescueReason, RescueRequest, RescueTow},
+    tickgen::{Phase, TickPayload, TICK_AS_HOUR, TICK_AS_MIN},
+    ChartEntry, Position, RESCUE_STATS_TOPIC, RESCUE_TOW_TOPIC,
+};
+use rumqttc::QoS;
+use serde::Deserialize;
+use tracing::{debug, info, trace, warn};
+
+use crate::{PendingRescue, RescueConfig, SharedRescue};
+
+/// # Description
+/// The part of a world map entry the rescue service needs to know where the chargers are.
+#[derive(Debug, Deserialize)]
+struct LocationPayload {
+    name: String,
+    lat: f64,
+    lon: f64,
+    icon: String,
+    #[serde(default)]
+    deleted: bool,
+}
+
+/**
+ * This function delegates the tick event handling based on the phase of the tick.
+ * The rescue service only acts in the process phase, in which due vehicles are dropped off at a charger
+ * and the statistics are published.
+ */
+pub async fn tick_handler(handler: SharedRescue, payload: Bytes) {
+    let payload: TickPayload = serde_json::from_slice(&payload).unwrap();
+    let mut handler = handler.lock().await;
+    handler.current_tick = payload.tick;
+    if payload.phase != Phase::Process {
+        return;
+    }
+
+    // Tow every vehicle whose truck arrived and drove it to the nearest charger
+    let mut due = Vec::new();
+    for (vehicle_name, pending) in handler.pending.iter() {
+        let Some((charger_name, position)) = handler.nearest_charger(pending.request.position) else {
+            debug!("No charger known yet to tow {} to", vehicle_name);
+            continue;
+        };
+        let tow_hours = pending.request.position.distance_to(position) / handler.config.tow_speed.max(1.0);
+        let due_tick = pending.requested_tick + handler.config.response_ticks + (tow_hours / TICK_AS_HOUR).ceil() as u64;
+        if handler.current_tick >= due_tick {
+            due.push(RescueTow {
+                vehicle_name: vehicle_name.clone(),
+                charger_name,
+                position,
+                rescue_ticks: handler.current_tick - pending.requested_tick,
+            });
+        }
+    }
+    for tow in due {
+        info!("Towed {} to {} after {} ticks", tow.vehicle_name, tow.charger_name, tow.rescue_ticks);
+        handler.pending.remove(&tow.vehicle_name);
+        handler.completed += 1;
+        handler.rescue_ticks += tow.rescue_ticks;
+        handler
+            .client
+            .publish(RESCUE_TOW_TOPIC, QoS::ExactlyOnce, false, tow.to_bytes())
+            .await
+            .unwrap();
+    }
+
+    for (topic, value) in [
+        ("Pending Rescues", handler.pending.len() as isize),
+        ("Completed Rescues", handler.completed as isize),
+        ("Breakdowns", handler.breakdowns as isize),
+        ("Strandings", handler.strandings as isize),
+        ("Avg Rescue Minutes", (handler.avg_rescue_ticks() * TICK_AS_MIN as f64) as isize),
+    ] {
+        handler
+            .client
+            .publish(
+                RESCUE_STATS_TOPIC,
+                QoS::ExactlyOnce,
+                true,
+                ChartEntry::new(topic.to_string(), value, payload.timestamp).to_string(),
+            )
+            .await
+            .unwrap();
+    }
+}
+
+/// # Description
+/// Keeps track of the positions of the chargers from the world map.
+///
+/// # Arguments
+/// - `handler`: The shared rescue handler.
+/// - `payload`: The payload of a world map entry in JSON format.
+pub async fn location_handler(handler: SharedRescue, payload: Bytes) {
+    let Ok(location) = serde_json::from_slice::<LocationPayload>(&payload) else {
+        return;
+    };
+    if location.icon != ":battery:" {
+        return;
+    }
+
+    let mut handler = handler.lock().await;
+    if location.deleted {
+        handler.chargers.remove(&location.name);
+    } else if handler.chargers.insert(location.name.clone(), Position::new(location.lat, location.lon)).is_none() {
+        trace!("Rescue service knows charger {} now", location.name);
+    }
+}
+
+/// # Description
+/// Accepts the rescue request of a vehicle. A vehicle that already waits keeps its place.
+///
+/// # Arguments
+/// - `handler`: The shared rescue handler.
+/// - `payload`: The payload containing the rescue request.
+pub async fn request_handler(handler: SharedRescue, payload: Bytes) {
+    let request = RescueRequest::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+    if handler.pending.contains_key(&request.vehicle_name) {
+        return;
+    }
+
+    info!("{} needs a rescue: {:?}", request.vehicle_name, request.reason);
+    match request.reason {
+        RescueReason::Broken => handler.breakdowns += 1,
+        RescueReason::Stranded => handler.strandings += 1,
+    }
+    let requested_tick = handler.current_tick;
+    handler.pending.insert(request.vehicle_name.clone(), PendingRescue { request, requested_tick });
+}
+
+/// # Description
+/// Drops the rescue of a vehicle that got going again on its own.
+///
+/// # Arguments
+/// - `handler`: The shared rescue handler.
+/// - `payload`: The name of the vehicle.
+pub async fn cancel_handler(handler: SharedRescue, payload: Bytes) {
+    let vehicle_name = String::from_utf8_lossy(&payload).to_string();
+    let mut handler = handler.lock().await;
+    if handler.pending.remove(&vehicle_name).is_some() {
+        debug!("Rescue of {} cancelled", vehicle_name);
+    }
+}
+
+/// # Description
+/// Sets the configuration of the rescue service.
+///
+/// # Arguments
+/// - `handler`: The shared rescue handler.
+/// - `payload`: The incoming payload containing the configuration in JSON format.
+pub async fn config_handler(handler: SharedRescue, payload: Bytes) {
+    let config: RescueConfig = match serde_json::from_slice(&payload) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid rescue configuration: {}", e);
+            return;
+        }
+    };
+    debug!("Rescue configuration set to: {:?}", config);
+    handler.lock().await.config = config;
+}
@@ -1,8 +1,9 @@
 use powercable::{
-    charger::ChargeAccept,
+    charger::{ChargeAccept, ChargeReceipt, ChargerStats},
     tickgen::{Phase, TickPayload, TICK_AS_SEC},
-    ChartEntry, Offer, ACK_ACCEPT_BUY_OFFER_TOPIC, CHARGER_ACCEPT, CHARGER_OFFER_AVG_COST,
-    CHARGER_OFFER_AVG_DISTANCE, CHARGER_OFFER_AVG_PRICE, POWER_TRANSFORMER_PRICE_TOPIC,
+    ChartEntry, Offer, ACK_ACCEPT_BUY_OFFER_TOPIC, CHARGER_ACCEPT, CHARGER_OFFER_AVG_COST, CHARGER_RECEIPT, CHARGER_RECEIPT_TOTAL, CHARGER_STATS, CHARGER_STATS_TOTAL,
+    CHARGER_OFFER_AVG_DISTANCE, CHARGER_OFFER_AVG_PRICE, POWER_CHARGER_GRID_LOAD_TOPIC, POWER_CHARGER_GRID_LOAD_TRANSFORMED_TOPIC,
+    POWER_TRANSFORMER_PRICE_TOPIC, VEHICLE_DEADLINE_TOPIC, VEHICLE_DEADLINE_TRANSFORMED_TOPIC, VEHICLE_STRANDED_TOPIC, VEHICLE_STRANDED_TRANSFORMED_TOPIC,
 };
 use rumqttc::{AsyncClient, MqttOptions, QoS};
 use std::time::Duration;
@@ -128,6 +129,34 @@ pub async fn send_all_topics(
         )
         .await
         .unwrap();
+    client
+        .publish(
+            powercable::POWER_TRANSFORMER_GENERATION_TOPIC_FORMATTED,
+            QoS::ExactlyOnce,
+            true,
+            ChartEntry::new(
+                "Producers".to_string(),
+                (transformer.get_current_power() - transformer.get_current_v2g_power()) as isize,
+                tick_payload.timestamp - TICK_AS_SEC,
+            )
+            .to_string(),
+        )
+        .await
+        .unwrap();
+    client
+        .publish(
+            powercable::POWER_TRANSFORMER_GENERATION_TOPIC_FORMATTED,
+            QoS::ExactlyOnce,
+            true,
+            ChartEntry::new(
+                "V2G".to_string(),
+                transformer.get_current_v2g_power() as isize,
+                tick_payload.timestamp - TICK_AS_SEC,
+            )
+            .to_string(),
+        )
+        .await
+        .unwrap();
     client
         .publish(
             powercable::POWER_CHARGER_TRANSFORMED_TOPIC,
@@ -157,6 +186,115 @@ pub async fn send_all_topics(
         .await
         .unwrap();
 
+    // Money of all chargers since the start, in cents like the prices
+    for (topic, value) in [
+        ("Revenue", transformer.get_charger_revenue()),
+        ("Purchase Cost", transformer.get_charger_purchase_cost()),
+        ("Margin", transformer.get_charger_margin()),
+        ("Total Profit", transformer.get_charger_total_profit()),
+    ] {
+        client
+            .publish(
+                CHARGER_STATS_TOTAL,
+                QoS::ExactlyOnce,
+                true,
+                ChartEntry::new(
+                    topic.to_string(),
+                    (value * 100.0) as isize,
+                    tick_payload.timestamp - TICK_AS_SEC,
+                )
+                .to_string(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Charging sessions the vehicles paid for since the start, money in cents like the prices
+    for (topic, value) in [
+        ("Sessions", transformer.get_sessions() as f64),
+        ("Avg. Session Cost", transformer.get_avg_session_cost() * 100.0),
+        ("Avg. Price per kWh", transformer.get_avg_session_price() * 100.0),
+        ("Avg. Spent per Driver", transformer.get_avg_driver_spending() * 100.0),
+    ] {
+        client
+            .publish(
+                CHARGER_RECEIPT_TOTAL,
+                QoS::ExactlyOnce,
+                true,
+                ChartEntry::new(
+                    topic.to_string(),
+                    value as isize,
+                    tick_payload.timestamp - TICK_AS_SEC,
+                )
+                .to_string(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Load on the grid connections of all chargers in kW
+    for (topic, value) in [
+        ("Grid Load", transformer.get_charger_grid_load()),
+        ("Peak Load", transformer.get_peak_charger_grid_load()),
+    ] {
+        client
+            .publish(
+                POWER_CHARGER_GRID_LOAD_TRANSFORMED_TOPIC,
+                QoS::ExactlyOnce,
+                true,
+                ChartEntry::new(
+                    topic.to_string(),
+                    value as isize,
+                    tick_payload.timestamp - TICK_AS_SEC,
+                )
+                .to_string(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Deadlines of all vehicles since the start
+    for (topic, value) in [
+        ("Met Deadlines", transformer.get_deadlines_met()),
+        ("Missed Deadlines", transformer.get_deadlines_missed()),
+    ] {
+        client
+            .publish(
+                VEHICLE_DEADLINE_TRANSFORMED_TOPIC,
+                QoS::ExactlyOnce,
+                true,
+                ChartEntry::new(
+                    topic.to_string(),
+                    value as isize,
+                    tick_payload.timestamp - TICK_AS_SEC,
+                )
+                .to_string(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Vehicles stranded this tick and strandings since the start
+    for (topic, value) in [
+        ("Stranded Vehicles", transformer.get_current_stranded()),
+        ("Strandings", transformer.get_strandings()),
+    ] {
+        client
+            .publish(
+                VEHICLE_STRANDED_TRANSFORMED_TOPIC,
+                QoS::ExactlyOnce,
+                true,
+                ChartEntry::new(
+                    topic.to_string(),
+                    value as isize,
+                    tick_payload.timestamp - TICK_AS_SEC,
+                )
+                .to_string(),
+            )
+            .await
+            .unwrap();
+    }
+
     if sell_amount == 0.0 && sells_total == 0.0 {
         debug!("No sells this tick, skipping price calculations");
         return ;
@@ -216,7 +354,7 @@ pub async fn start_transformer() {
     let mut total_accepted_charge_offer_cost: f64 = 0.0;
 
     mqttoptions.set_keep_alive(Duration::from_secs(5));
-    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+    let (client, mut eventloop) = AsyncClient::new(mqttoptions, powercable::MQTT_REQUEST_CAPACITY);
     client
         .subscribe(powercable::TICK_TOPIC, QoS::ExactlyOnce)
         .await
@@ -247,6 +385,26 @@ pub async fn start_transformer() {
         .subscribe(CHARGER_ACCEPT, QoS::ExactlyOnce)
         .await
         .unwrap();
+    client
+        .subscribe(CHARGER_STATS, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(CHARGER_RECEIPT, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(POWER_CHARGER_GRID_LOAD_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(VEHICLE_DEADLINE_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
+    client
+        .subscribe(VEHICLE_STRANDED_TOPIC, QoS::ExactlyOnce)
+        .await
+        .unwrap();
     warn!("Sub to ...");
     info!("Connected to MQTT broker");
 
@@ -309,6 +467,41 @@ pub async fn start_transformer() {
                     );
                 }
 
+                CHARGER_STATS => {
+                    let stats = ChargerStats::from_bytes(p.payload).unwrap();
+                    debug!("Received charger stats: {:?}", stats);
+
+                    transformer.add_charger_stats(&stats);
+                }
+
+                CHARGER_RECEIPT => {
+                    let receipt = ChargeReceipt::from_bytes(p.payload).unwrap();
+                    debug!("Received charge receipt: {:?}", receipt);
+
+                    transformer.add_receipt(&receipt);
+                }
+
+                POWER_CHARGER_GRID_LOAD_TOPIC => {
+                    let payload = ChartEntry::from_bytes(p.payload).unwrap();
+                    debug!("Received charger grid load: {:?}", payload);
+
+                    transformer.add_charger_grid_load(payload.payload as f64);
+                }
+
+                VEHICLE_DEADLINE_TOPIC => {
+                    let payload = ChartEntry::from_bytes(p.payload).unwrap();
+                    debug!("Received deadline result: {:?}", payload);
+
+                    transformer.add_deadline_result(payload.payload);
+                }
+
+                VEHICLE_STRANDED_TOPIC => {
+                    let payload = ChartEntry::from_bytes(p.payload).unwrap();
+                    debug!("Received stranded vehicle: {:?}", payload);
+
+                    transformer.add_stranded(payload.payload);
+                }
+
                 powercable::POWER_CHARGER_TOPIC => {
                     let payload = ChartEntry::from_bytes(p.payload).unwrap();
                     if payload.topic == OWN_TOPIC {
@@ -326,7 +519,11 @@ pub async fn start_transformer() {
                     }
                     debug!("Received generation data: {:?}", payload);
 
-                    transformer.add_power(payload.payload as f64);
+                    if payload.topic.starts_with("V2G") {
+                        transformer.add_v2g_power(payload.payload as f64);
+                    } else {
+                        transformer.add_power(payload.payload as f64);
+                    }
                 }
 
                 powercable::POWER_TRANSFORMER_CONSUMPTION_TOPIC => {
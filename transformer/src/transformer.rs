@@ -1,3 +1,7 @@
+use std::collections::{HashMap, HashSet};
+
+use powercable::charger::{ChargeReceipt, ChargerStats};
+
 #[derive(Debug, Clone)]
 pub struct Transformer {
     total_consumption: f64,
@@ -6,6 +10,18 @@ pub struct Transformer {
     current_charger_consumption: f64,
     current_power: f64,
     battery: f64,
+    charger_stats: HashMap<String, ChargerStats>,
+    charger_grid_load: f64,
+    peak_charger_grid_load: f64,
+    deadlines_met: usize,
+    deadlines_missed: usize,
+    current_v2g_power: f64,
+    current_stranded: usize,
+    strandings: usize,
+    sessions: usize,
+    session_energy: f64,
+    session_cost: f64,
+    paying_drivers: HashSet<String>,
 }
 
 impl Transformer {
@@ -17,6 +33,18 @@ impl Transformer {
             current_charger_consumption: 0.0,
             current_power: 0.0,
             battery: 100.0, // Start with a full battery
+            charger_stats: HashMap::new(),
+            charger_grid_load: 0.0,
+            peak_charger_grid_load: 0.0,
+            deadlines_met: 0,
+            deadlines_missed: 0,
+            current_v2g_power: 0.0,
+            current_stranded: 0,
+            strandings: 0,
+            sessions: 0,
+            session_energy: 0.0,
+            session_cost: 0.0,
+            paying_drivers: HashSet::new(),
         }
     }
 
@@ -56,6 +84,9 @@ impl Transformer {
         self.current_charger_consumption = 0.0;
         self.current_power = 0.0;
         self.battery = 0.0;
+        self.charger_grid_load = 0.0;
+        self.current_v2g_power = 0.0;
+        self.current_stranded = 0;
     }
 
     pub fn get_difference(&self) -> f64 {
@@ -69,4 +100,125 @@ impl Transformer {
     pub fn get_battery(&self) -> f64 {
         self.battery
     }
-}
\ No newline at end of file
+
+    /// Keeps the latest stats of a charger, the money of all chargers is summed up from their totals since the start
+    pub fn add_charger_stats(&mut self, stats: &ChargerStats) {
+        if self.charger_stats.get(&stats.charger_name).is_none_or(|last| last.tick <= stats.tick) {
+            self.charger_stats.insert(stats.charger_name.clone(), stats.clone());
+        }
+    }
+
+    fn sum_charger_stats(&self, value: impl Fn(&ChargerStats) -> f64) -> f64 {
+        self.charger_stats.values().map(value).sum()
+    }
+
+    pub fn get_charger_revenue(&self) -> f64 {
+        self.sum_charger_stats(|stats| stats.total_revenue + stats.total_fees + stats.total_v2g_margin)
+    }
+
+    pub fn get_charger_purchase_cost(&self) -> f64 {
+        self.sum_charger_stats(|stats| stats.total_purchase_cost)
+    }
+
+    pub fn get_charger_margin(&self) -> f64 {
+        self.sum_charger_stats(|stats| stats.total_margin)
+    }
+
+    pub fn get_charger_total_profit(&self) -> f64 {
+        self.sum_charger_stats(|stats| stats.total_profit)
+    }
+
+    /// Adds the load on the grid connection of a charger in kW and keeps track of the highest load of all chargers
+    pub fn add_charger_grid_load(&mut self, load: f64) {
+        self.charger_grid_load += load;
+        self.peak_charger_grid_load = self.peak_charger_grid_load.max(self.charger_grid_load);
+    }
+
+    pub fn get_charger_grid_load(&self) -> f64 {
+        self.charger_grid_load
+    }
+
+    pub fn get_peak_charger_grid_load(&self) -> f64 {
+        self.peak_charger_grid_load
+    }
+
+    /// Adds energy of parked vehicles fed into the grid, it is part of the generation as well
+    pub fn add_v2g_power(&mut self, power: f64) {
+        self.current_v2g_power += power;
+        self.add_power(power);
+    }
+
+    pub fn get_current_v2g_power(&self) -> f64 {
+        self.current_v2g_power
+    }
+
+    /// Counts the deadline of a vehicle as met or missed, by how much state of charge it missed its target
+    pub fn add_deadline_result(&mut self, missed_by: isize) {
+        if missed_by > 0 {
+            self.deadlines_missed += 1;
+        } else {
+            self.deadlines_met += 1;
+        }
+    }
+
+    pub fn get_deadlines_met(&self) -> usize {
+        self.deadlines_met
+    }
+
+    pub fn get_deadlines_missed(&self) -> usize {
+        self.deadlines_missed
+    }
+
+    /// Counts a vehicle that is stranded this tick, and a new stranding if it just got stranded
+    pub fn add_stranded(&mut self, stranded_ticks: isize) {
+        self.current_stranded += 1;
+        if stranded_ticks == 1 {
+            self.strandings += 1;
+        }
+    }
+
+    pub fn get_current_stranded(&self) -> usize {
+        self.current_stranded
+    }
+
+    pub fn get_strandings(&self) -> usize {
+        self.strandings
+    }
+
+    /// Adds a paid charging session, the spending is summed up since the start. Sessions without charging, only V2G, are skipped
+    pub fn add_receipt(&mut self, receipt: &ChargeReceipt) {
+        if receipt.energy <= 0.0 {
+            return;
+        }
+        self.sessions += 1;
+        self.session_energy += receipt.energy;
+        self.session_cost += receipt.cost;
+        self.paying_drivers.insert(receipt.vehicle_name.clone());
+    }
+
+    pub fn get_sessions(&self) -> usize {
+        self.sessions
+    }
+
+    pub fn get_avg_session_cost(&self) -> f64 {
+        if self.sessions == 0 {
+            return 0.0;
+        }
+        self.session_cost / self.sessions as f64
+    }
+
+    pub fn get_avg_session_price(&self) -> f64 {
+        if self.session_energy == 0.0 {
+            return 0.0;
+        }
+        self.session_cost / self.session_energy
+    }
+
+    /// What the drivers that charged spent on average since the start
+    pub fn get_avg_driver_spending(&self) -> f64 {
+        if self.paying_drivers.is_empty() {
+            return 0.0;
+        }
+        self.session_cost / self.paying_drivers.len() as f64
+    }
+}
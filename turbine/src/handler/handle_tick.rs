@@ -1,23 +1,36 @@
 use std::sync::Arc;
 use bytes::Bytes;
 use tracing::{debug, warn};
-use powercable::{offer::structure::OFFER_PACKAGE_SIZE, tickgen::{Phase, TickPayload}, ChartEntry, ACCEPT_BUY_OFFER_TOPIC, POWER_TRANSFORMER_EARNED_TOPIC, POWER_TRANSFORMER_GENERATION_TOPIC};
+use powercable::{offer::structure::OFFER_PACKAGE_SIZE, tickgen::{Phase, TickPayload}, weather::WeatherReport, ChartEntry, Position, ACCEPT_BUY_OFFER_TOPIC, POWER_TRANSFORMER_EARNED_TOPIC, POWER_TRANSFORMER_GENERATION_TOPIC, WEATHER_TOPIC};
 use rumqttc::QoS;
 use tokio::sync::Mutex;
 
 use crate::{init, SharedTurbine, TurbineHandler};
 
 pub async fn process_tick(handler: SharedTurbine, payload: TickPayload) {
-    let (client, power, name) = {
+    let (client, power, name, weather) = {
         let mut handler = handler.lock().await;
         handler.turbine.tick();
         handler.offer_handler.remove_all_offers();
         handler.remaining_power = handler.turbine.get_power_output();
         debug!("Current power output: {} Watt", handler.remaining_power);
 
-        (handler.client.clone(), handler.remaining_power, handler.name.clone())
+        let weather = handler.turbine.get_air_temperature().map(|air_temperature| WeatherReport {
+            station: handler.name.clone(),
+            position: Position::new(handler.turbine.get_latitude(), handler.turbine.get_longitude()),
+            air_temperature,
+        });
+        (handler.client.clone(), handler.remaining_power, handler.name.clone(), weather)
     };
 
+    // Vehicles around use the temperature for their heating and battery
+    if let Some(weather) = weather {
+        client
+            .publish(WEATHER_TOPIC, QoS::ExactlyOnce, false, weather.to_bytes())
+            .await
+            .unwrap();
+    }
+
     let power_minus_leftovers = power - (power % OFFER_PACKAGE_SIZE);
 
     let _ = client
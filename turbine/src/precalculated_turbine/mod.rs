@@ -10,6 +10,8 @@ pub struct PrecalculatedTurbine {
     latitude: f64,  // in degrees
     longitude: f64, // in degrees
     cached_power_output: Vec<f64>,
+    #[serde(default)]
+    cached_air_temperature: Vec<f64>,
     ticker: usize,
     scale: f64,
     pub visible: bool,
@@ -28,12 +30,14 @@ impl PrecalculatedTurbine {
         let latitude = turbine.get_latitude();
         let longitude = turbine.get_longitude();
         let mut cached_power_output = vec![0.0; CACHED_ENTRIES];
+        let mut cached_air_temperature = vec![0.0; CACHED_ENTRIES];
 
         for i in 0..CACHED_ENTRIES {
             turbine.tick();
             turbine.approximate_wind_data().await;
             turbine.approximate_temperature_data().await;
             cached_power_output[i] = turbine.get_power_output();
+            cached_air_temperature[i] = turbine.approximate_temperature.as_ref().unwrap().air_temperature;
         }
 
         PrecalculatedTurbine {
@@ -41,6 +45,7 @@ impl PrecalculatedTurbine {
             latitude,
             longitude,
             cached_power_output,
+            cached_air_temperature,
             ticker: turbine.get_tick(),
             scale: 1.0, // Default scale is 1.0
             visible: true,
@@ -51,6 +56,12 @@ impl PrecalculatedTurbine {
         self.cached_power_output[self.ticker % CACHED_ENTRIES] * self.scale as f64
     }
 
+    /// # Returns
+    /// The air temperature in °C at the turbine, `None` if the turbine was loaded from a dump without temperatures.
+    pub fn get_air_temperature(&self) -> Option<f64> {
+        self.cached_air_temperature.get(self.ticker % CACHED_ENTRIES).copied()
+    }
+
     pub fn get_latitude(&self) -> f64 {
         self.latitude
     }
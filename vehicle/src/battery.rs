@@ -1,20 +1,59 @@
+use powercable::tickgen::TICK_AS_HOUR;
 use serde::Serialize;
 
-pub const CHARGE_EFFICIENCY: f64 = 0.9;// 90% charge efficiency
-pub const DISCHARGE_EFFICIENCY: f64 = 0.94;// 94% discharge efficiency
+const CHARGE_EFFICIENCY: f64 = 0.9;// 90% charge efficiency
+const DISCHARGE_EFFICIENCY: f64 = 0.94;// 94% discharge efficiency
+/// Capacity lost per equivalent full cycle at gentle C-rates, about 20% after 3000 cycles
+const CYCLE_FADE: f64 = 0.00007;
+/// Capacity lost per hour spent above `HIGH_SOC`, about 2.5% per year
+const HIGH_SOC_FADE: f64 = 0.000003;
+/// Above this state of charge the cells age faster
+const HIGH_SOC: f64 = 0.8;
+/// Up to this C-rate charging doesn't wear the cells more than a normal cycle
+const GENTLE_C_RATE: f64 = 0.5;
+/// Additional cycle wear per C above `GENTLE_C_RATE`
+const C_RATE_STRESS: f64 = 1.0;
+/// At this state of health the battery is worn out and has to be replaced
+const END_OF_LIFE_SOH: f64 = 0.7;
+/// Price of a replacement battery in € per kWh of capacity
+const REPLACEMENT_COST: f64 = 130.0;
+/// Loss of charge and discharge efficiency of a worn out battery
+const WORN_EFFICIENCY_LOSS: f64 = 0.05;
+/// The pack temperature in °C at which the battery charges at full power
+const FULL_POWER_TEMPERATURE: f64 = 20.0;
+/// The pack temperature in °C at which the battery charges at `MIN_COLD_POWER` only
+const MIN_POWER_TEMPERATURE: f64 = -10.0;
+/// Share of the charging power a freezing pack still takes
+const MIN_COLD_POWER: f64 = 0.2;
+/// The pack temperature in °C the thermal management keeps while driving
+const OPERATING_TEMPERATURE: f64 = 25.0;
+/// Share of the difference to the target temperature the pack makes up in one phase
+const HEAT_EXCHANGE: f64 = 0.05;
+/// Warming of the pack in °C per kWh charged
+const CHARGE_HEATING: f64 = 0.1;
 
 /// # Description
 /// The `Battery` struct represents an electric vehicle's battery.
 /// 
 /// # Fields
-/// - `max_capacity`: The maximum capacity of the battery in kWh.
+/// - `nominal_capacity`: The capacity of the new battery in kWh.
 /// - `level`: The current level of charge in the battery in kWh.
 /// - `max_charge_rate`: The maximum charge rate of the battery in kW. !! 150 kW means 150 kWh can be added in one hour. !!
+/// - `temperature`: The temperature of the battery pack in °C, a cold pack charges slower.
+/// - `state_of_health`: The share of the nominal capacity that is left (0.0 to 1.0).
+/// - `full_cycles`: The equivalent full cycles the battery went through.
+/// - `charge_curve`: The charging power in kW over the state of charge, as sorted `(soc, kW)` points.
+///   If empty, a generic curve is used.
 #[derive(Clone, Debug, Serialize)]
 pub struct Battery {
-    max_capacity: f64,
+    nominal_capacity: f64,
     level: f64,
     max_charge_rate: usize,
+    #[serde(skip)]
+    charge_curve: Vec<(f64, f64)>,
+    temperature: f64,
+    state_of_health: f64,
+    full_cycles: f64,
 }
 
 impl Battery {
@@ -25,6 +64,7 @@ impl Battery {
     /// - `max_capacity`: The maximum capacity of the battery in kWh.
     /// - `soc`: The state of charge of the battery as a fraction (0.0 to 1.0).
     /// - `max_charge_rate`: The maximum charge rate of the battery in kW.
+    /// - `charge_curve`: The charging power in kW over the state of charge, sorted by state of charge.
     /// 
     /// # Returns
     /// A new Battery instance with the specified parameters.
@@ -32,18 +72,92 @@ impl Battery {
         max_capacity: f64,
         soc: f64,
         max_charge_rate: usize,
+        charge_curve: Vec<(f64, f64)>,
     ) -> Self {
         Battery {
-            max_capacity,
+            nominal_capacity: max_capacity,
             level: max_capacity * soc,
             max_charge_rate,
+            charge_curve,
+            temperature: FULL_POWER_TEMPERATURE,
+            state_of_health: 1.0,
+            full_cycles: 0.0,
         }
     }
 
     /// # Returns
-    /// The total capacity of the battery in kWh.
+    /// The total capacity of the battery in kWh, which shrinks as the battery ages.
     pub fn get_max_capacity(&self) -> f64 {
-        self.max_capacity
+        self.nominal_capacity * self.state_of_health
+    }
+
+    /// # Returns
+    /// The state of health of the battery, the share of the nominal capacity that is left (0.0 to 1.0).
+    pub fn get_state_of_health(&self) -> f64 {
+        self.state_of_health
+    }
+
+    /// # Returns
+    /// How far the battery is worn towards its end of life (0.0 new to 1.0 worn out).
+    fn wear(&self) -> f64 {
+        ((1.0 - self.state_of_health) / (1.0 - END_OF_LIFE_SOH)).clamp(0.0, 1.0)
+    }
+
+    /// # Returns
+    /// The share of the energy from the charger that ends up in the battery, worn cells lose more as heat.
+    pub fn charge_efficiency(&self) -> f64 {
+        CHARGE_EFFICIENCY - WORN_EFFICIENCY_LOSS * self.wear()
+    }
+
+    /// # Returns
+    /// The share of the energy taken from the battery that can be used, worn cells lose more as heat.
+    pub fn discharge_efficiency(&self) -> f64 {
+        DISCHARGE_EFFICIENCY - WORN_EFFICIENCY_LOSS * self.wear()
+    }
+
+    /// # Returns
+    /// The additional cycle wear when charging at the given C-rate, 1.0 for gentle charging.
+    fn c_rate_stress(c_rate: f64) -> f64 {
+        1.0 + C_RATE_STRESS * (c_rate - GENTLE_C_RATE).max(0.0)
+    }
+
+    /// # Description
+    /// Ages the battery by the energy that went in or out of it.
+    ///
+    /// # Arguments
+    /// - `energy`: The energy in kWh.
+    /// - `c_rate`: The power relative to the nominal capacity, 1.0 charges the battery in one hour.
+    fn cycle(&mut self, energy: f64, c_rate: f64) {
+        let cycles = energy / (2.0 * self.nominal_capacity);// one full cycle is charging and discharging the capacity
+        self.full_cycles += cycles;
+        self.state_of_health = (self.state_of_health - cycles * CYCLE_FADE * Self::c_rate_stress(c_rate)).max(0.0);
+        self.level = self.level.min(self.get_max_capacity());
+    }
+
+    /// # Description
+    /// Ages the battery by the time it spends at a high state of charge.
+    ///
+    /// # Arguments
+    /// - `hours`: The time in hours that passed.
+    pub fn age(&mut self, hours: f64) {
+        if self.get_soc() > HIGH_SOC {
+            self.state_of_health = (self.state_of_health - HIGH_SOC_FADE * hours).max(0.0);
+            self.level = self.level.min(self.get_max_capacity());
+        }
+    }
+
+    /// # Description
+    /// Estimates the cost of the wear of charging or discharging energy, as the share of the replacement battery it uses up.
+    ///
+    /// # Arguments
+    /// - `power`: The charging power in kW, fast charging wears the battery more.
+    ///
+    /// # Returns
+    /// The cost of the wear in € per kWh.
+    pub fn wear_cost_per_kwh(&self, power: f64) -> f64 {
+        let c_rate = power.min(self.max_charge_rate as f64) / self.nominal_capacity;
+        let fade_per_kwh = CYCLE_FADE * Self::c_rate_stress(c_rate) / (2.0 * self.nominal_capacity);
+        fade_per_kwh / (1.0 - END_OF_LIFE_SOH) * REPLACEMENT_COST * self.nominal_capacity
     }
 
     /// # Returns
@@ -76,18 +190,45 @@ impl Battery {
         self.get_max_capacity() - self.get_level()
     }
 
+    /// # Description
+    /// Moves the pack temperature one phase closer to the air temperature.
+    /// While driving, the thermal management heats the pack to its operating temperature instead.
+    ///
+    /// # Arguments
+    /// - `air_temperature`: The air temperature in °C.
+    /// - `driving`: Whether the vehicle is driving.
+    pub fn update_temperature(&mut self, air_temperature: f64, driving: bool) {
+        let target = if driving { air_temperature.max(OPERATING_TEMPERATURE) } else { air_temperature };
+        self.temperature += HEAT_EXCHANGE * (target - self.temperature);
+    }
+
+    /// # Returns
+    /// The share of the DC charging power a pack at its current temperature takes, between `MIN_COLD_POWER` and 1.0.
+    /// The slow AC charging power is taken in full.
+    pub fn cold_derating(&self) -> f64 {
+        let warmth = (self.temperature - MIN_POWER_TEMPERATURE) / (FULL_POWER_TEMPERATURE - MIN_POWER_TEMPERATURE);
+        (MIN_COLD_POWER + (1.0 - MIN_COLD_POWER) * warmth).clamp(MIN_COLD_POWER, 1.0)
+    }
+
+    /// # Returns
+    /// The power in kW the battery can currently be charged with, following its charging curve.
+    /// A cold pack takes less at DC ports, see `cold_derating`.
+    pub fn get_charge_power(&self) -> f64 {
+        self.max_charge_rate as f64 * self.charge_scaling()
+    }
+
     /// Calculates the maximum amount of charge that can be added to the battery.
     /// # Arguments
     /// `charge`: An optional parameter that specifies the amount of charge to be added.
-    /// If `None`, the maximum charge rate of the battery is used.
+    /// If `None`, the charge the battery can take at its maximum charge rate in one tick is used.
     pub fn max_addable_charge(&self, charge: Option<usize>) -> usize {
         // apply scaling
-        let charge = charge.unwrap_or(self.max_charge_rate);
+        let charge = charge.unwrap_or((self.max_charge_rate as f64 * TICK_AS_HOUR) as usize);
         let applied_charge = charge.min(self.get_free_capacity() as usize);
         let charge_rate = applied_charge as f64 * self.charge_scaling();
 
         // calculate energy that could be added
-        let energy_added = charge_rate * CHARGE_EFFICIENCY;
+        let energy_added = charge_rate * self.charge_efficiency();
         (energy_added.max(1.0) as usize).min(self.get_free_capacity() as usize)
     }
 
@@ -96,31 +237,81 @@ impl Battery {
         let energy_added = self.max_addable_charge(Some(charge));
 
         self.level = (self.level + energy_added as f64).min(self.get_max_capacity());
+        self.cycle(energy_added as f64, energy_added as f64 / TICK_AS_HOUR / self.nominal_capacity);
+        // charging warms the pack up to its operating temperature
+        if self.temperature < OPERATING_TEMPERATURE {
+            self.temperature = (self.temperature + energy_added as f64 * CHARGE_HEATING).min(OPERATING_TEMPERATURE);
+        }
         energy_added
     }
     
+    /// # Description
+    /// Takes the energy for driving out of the battery, the discharge losses come on top like in `discharge`.
+    ///
+    /// # Returns
+    /// The usable energy in kWh, less than asked for if the battery runs empty.
     pub fn remove_charge(&mut self, charge: f64) -> f64 {
-        let energy_demand = charge * DISCHARGE_EFFICIENCY;
+        let efficiency = self.discharge_efficiency();
+        let energy_demand = charge / efficiency;
         
-        if self.level >= energy_demand {
+        let removed = if self.level >= energy_demand {
             self.level -= energy_demand;
             energy_demand
         } else {
-            let actual_energy = self.level * DISCHARGE_EFFICIENCY;
+            let actual_energy = self.level;
             self.level = 0.0;
-            if actual_energy > 0.0 {
-                actual_energy
-            } else {
-                1.0
-            }
+            actual_energy
+        };
+        self.cycle(removed, 0.0);
+        removed * efficiency
+    }
+
+    /// # Returns
+    /// The energy in kWh the battery can feed into the grid without falling below the given state of charge,
+    /// after discharge losses.
+    pub fn dischargeable_above(&self, soc: f64) -> f64 {
+        (self.level - self.get_max_capacity() * soc).max(0.0) * self.discharge_efficiency()
+    }
+
+    /// # Description
+    /// Takes energy out of the battery to feed it into the grid, the discharge losses come on top.
+    ///
+    /// # Returns
+    /// The energy in kWh that was taken out of the battery.
+    pub fn discharge(&mut self, energy: f64) -> f64 {
+        let drawn = (energy / self.discharge_efficiency()).min(self.level);
+        self.level -= drawn;
+        self.cycle(drawn, 0.0);
+        drawn
+    }
+
+    /// # Returns
+    /// The charging power in kW of the charge curve at the state of charge, linearly interpolated between its points.
+    fn curve_power(&self, soc: f64) -> Option<f64> {
+        let (first, last) = (self.charge_curve.first()?, self.charge_curve.last()?);
+        if soc <= first.0 {
+            return Some(first.1);
         }
+        let power = self.charge_curve
+            .windows(2)
+            .find(|points| soc <= points[1].0)
+            .map(|points| {
+                let ((soc_a, power_a), (soc_b, power_b)) = (points[0], points[1]);
+                power_a + (power_b - power_a) * (soc - soc_a) / (soc_b - soc_a).max(f64::EPSILON)
+            })
+            .unwrap_or(last.1);
+        Some(power)
     }
 
     fn charge_scaling(&self) -> f64 {
         let soc = self.get_soc();
 
+        // the charge curve of the model
+        if let Some(power) = self.curve_power(soc) {
+            (power / self.max_charge_rate.max(1) as f64).clamp(0.0, 1.0)
+        }
         // trickle charging (0..10%)
-        if soc < 0.1 {
+        else if soc < 0.1 {
             0.1
         }
         // constant current (10..80%)
@@ -133,4 +324,116 @@ impl Battery {
             linear_scale.powf(1.5)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worn(mut battery: Battery) -> Battery {
+        battery.state_of_health = END_OF_LIFE_SOH;
+        battery.level = battery.get_max_capacity() * 0.5;
+        battery
+    }
+
+    #[test]
+    fn worn_battery_drains_faster() {
+        let mut new = Battery::new(60.0, 0.5, 150, Vec::new());
+        let mut old = worn(Battery::new(60.0, 0.5, 150, Vec::new()));
+        let (new_level, old_level) = (new.get_level(), old.get_level());
+
+        assert_eq!(new.remove_charge(10.0), 10.0);
+        assert_eq!(old.remove_charge(10.0), 10.0);
+        let new_drawn = new_level - new.get_level();
+        let old_drawn = old_level - old.get_level();
+        assert!(new_drawn > 10.0);
+        assert!(old_drawn > new_drawn);
+    }
+
+    #[test]
+    fn remove_charge_matches_discharge() {
+        let mut driving = Battery::new(60.0, 0.5, 150, Vec::new());
+        let mut feeding = driving.clone();
+
+        driving.remove_charge(10.0);
+        feeding.discharge(10.0);
+        assert!((driving.get_level() - feeding.get_level()).abs() < 1e-9);
+
+        let mut driving = worn(driving);
+        let mut feeding = worn(feeding);
+        driving.remove_charge(10.0);
+        feeding.discharge(10.0);
+        assert!((driving.get_level() - feeding.get_level()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn empty_battery_delivers_what_is_left() {
+        let mut battery = Battery::new(60.0, 0.1, 150, Vec::new());
+        let level = battery.get_level();
+
+        let used = battery.remove_charge(100.0);
+        assert_eq!(battery.get_level(), 0.0);
+        assert!((used - level * DISCHARGE_EFFICIENCY).abs() < 1e-9);
+        assert_eq!(battery.remove_charge(1.0), 0.0);
+    }
+
+    #[test]
+    fn fast_charging_wears_more() {
+        let battery = Battery::new(60.0, 0.5, 150, Vec::new());
+        assert!(battery.wear_cost_per_kwh(150.0) > battery.wear_cost_per_kwh(30.0));
+        assert_eq!(battery.wear_cost_per_kwh(10.0), battery.wear_cost_per_kwh(30.0)); // both below the gentle C-rate
+
+        let mut fast = Battery::new(60.0, 0.2, 150, Vec::new());
+        let mut slow = fast.clone();
+        let fade_per_kwh = |battery: &mut Battery, charge: usize| {
+            let added = battery.add_charge(charge) as f64;
+            (1.0 - battery.get_state_of_health()) / added
+        };
+        assert!(fade_per_kwh(&mut fast, 30) > fade_per_kwh(&mut slow, 5));
+    }
+
+    #[test]
+    fn full_battery_ages_while_parked() {
+        let mut full = Battery::new(60.0, 0.9, 150, Vec::new());
+        let mut half = Battery::new(60.0, 0.5, 150, Vec::new());
+
+        full.age(100.0);
+        half.age(100.0);
+        assert!(full.get_state_of_health() < 1.0);
+        assert_eq!(half.get_state_of_health(), 1.0);
+        assert!(full.get_level() <= full.get_max_capacity());
+    }
+
+    #[test]
+    fn worn_battery_holds_less() {
+        let battery = worn(Battery::new(60.0, 0.5, 150, Vec::new()));
+        assert!((battery.get_max_capacity() - 42.0).abs() < 1e-9);
+        assert!(battery.charge_efficiency() < CHARGE_EFFICIENCY);
+        assert!(battery.discharge_efficiency() < DISCHARGE_EFFICIENCY);
+    }
+
+    #[test]
+    fn cold_battery_charges_slower() {
+        let mut battery = Battery::new(60.0, 0.5, 150, Vec::new());
+        assert_eq!(battery.cold_derating(), 1.0);
+
+        battery.temperature = MIN_POWER_TEMPERATURE;
+        assert!((battery.cold_derating() - MIN_COLD_POWER).abs() < 1e-9);
+        assert_eq!(battery.get_charge_power(), 150.0);
+
+        // Driving warms the pack up again
+        for _ in 0..100 {
+            battery.update_temperature(MIN_POWER_TEMPERATURE, true);
+        }
+        assert_eq!(battery.cold_derating(), 1.0);
+    }
+
+    #[test]
+    fn charge_curve_is_interpolated() {
+        let battery = Battery::new(60.0, 0.5, 150, vec![(0.0, 50.0), (0.2, 150.0), (0.8, 50.0)]);
+        assert_eq!(battery.curve_power(0.1), Some(100.0));
+        assert_eq!(battery.curve_power(0.5), Some(100.0));
+        assert_eq!(battery.curve_power(0.9), Some(50.0));
+        assert!((battery.get_charge_power() - 100.0).abs() < 1e-9);
+    }
+}
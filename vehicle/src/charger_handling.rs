@@ -1,18 +1,26 @@
+use crate::planner::get_deadline_offer;
+use crate::rescue::cancel_rescue;
+use crate::scoring::get_scored_offer;
+use crate::trip::{get_en_route_offer, TripPlanner};
 use crate::vehicle::{Vehicle, VehicleAlgorithm, VehicleStatus};
-use crate::SharedVehicle;
+use crate::negotiation::send_counter;
+use crate::{SharedVehicle, VehicleHandler};
 use bytes::Bytes;
 use rand::rngs::StdRng;
-use tracing::{debug, info};
+use tracing::{debug, info, trace, warn};
 use powercable::{
-    charger::*, CHARGER_ACCEPT, CHARGER_CHARGING_GET, CHARGER_CHARGING_RELEASE, CHARGER_REQUEST
+    charger::*, CHARGER_ACCEPT, CHARGER_CANCEL, CHARGER_ETA, CHARGER_CHARGING_GET, CHARGER_CHARGING_RELEASE, CHARGER_REQUEST
 };
 use rand::{Rng, SeedableRng};
 use rumqttc::QoS;
 
+/// How many ticks a vehicle is willing to wait on the waitlist of a charger
+const MAX_WAIT_TICKS: u64 = 4;
+
 /// # Description
 /// Sends a charge request to all chargers.<br>
 /// This function creates a ChargeRequest message containing the vehicle's name, the amount of charge needed,
-/// and the vehicle's current position.<br>
+/// the vehicle's current position and how much it is willing to pay from its wallet.<br>
 /// Important is that the amount doesn´t contains the amount of energy needed to drive to the charger
 /// 
 /// # Arguments
@@ -25,6 +33,12 @@ pub async fn create_charger_request(handler: SharedVehicle) {
         charge_amount: handler.vehicle.battery_non_mut().get_free_capacity() as usize,// TODO
         vehicle_position: handler.vehicle.get_location(),
         vehicle_consumption: handler.vehicle.get_consumption(),
+        connectors: handler.vehicle.get_connectors().to_vec(),
+        max_charge_power: handler.vehicle.battery_non_mut().get_charge_power(),
+        max_ac_power: handler.vehicle.get_max_ac_power(),
+        cold_derating: handler.vehicle.battery_non_mut().cold_derating(),
+        max_price: handler.wallet.max_price(handler.vehicle.battery_non_mut().get_soc()),
+        budget: handler.wallet.budget(handler.vehicle.battery_non_mut().get_soc()),
     };
 
     // publish charging request to all chargers
@@ -37,7 +51,8 @@ pub async fn create_charger_request(handler: SharedVehicle) {
 }
 
 /// # Description
-/// Receives a charge offer from a charger and stores it.
+/// Receives a charge offer from a charger and stores it.<br>
+/// The prices of all offers, also those for other vehicles, feed the price forecast of the charge planner.
 /// 
 /// # Arguments
 /// - `handler`: The shared vehicle handler containing the vehicle and its state.
@@ -47,6 +62,8 @@ pub async fn receive_offer(handler: SharedVehicle, payload: Bytes) {
 
     // Deserialize the ChargeOffer message
     let charge_offer = ChargeOffer::from_bytes(payload).unwrap();
+    let timestamp = handler.current_timestamp;
+    handler.planner.observe_price(timestamp, charge_offer.charge_price);
     
     // Check if the offer is for the current vehicle
     if charge_offer.vehicle_name.eq(&handler.vehicle.get_name()) {
@@ -57,38 +74,96 @@ pub async fn receive_offer(handler: SharedVehicle, payload: Bytes) {
 
 /// # Description
 /// Accepts the best charge offer available.<br>
+/// Offers from chargers the vehicle can't reach with its battery are discarded first. If none is left, the vehicle is stranded.<br>
 /// This function calculates the best offer based on the distance to the charger and the charge price,<br>
-/// then drives the vehicle to the charger and publishes an acceptance message.
+/// then drives the vehicle to the charger and publishes an acceptance message.<br>
+/// If the vehicle negotiates, it counters the best offer instead and accepts once it agreed with the charger.
+/// A negotiation the charger didn't answer until the next commerce phase is given up and the offer is taken as it is.
 /// 
 /// # Arguments
 /// - `handler`: The shared vehicle handler containing the vehicle and its state.
 pub async fn accept_offer(handler: SharedVehicle) {
     let mut handler = handler.lock().await;
 
+    let unanswered = handler.negotiator.open.take();
+    if let Some(negotiation) = &unanswered {
+        debug!("{} didn't answer in round {}, giving up the negotiation", negotiation.charger_name, negotiation.round);
+    }
+
     if handler.charge_offers.is_empty() {
         info!("No charge offers available to accept.");
         return;
     }
 
-    // Determine the best offer based on the vehicle's algorithm
-    let accepted_offer = match handler.vehicle.get_algorithm() {
-        VehicleAlgorithm::Best => get_best_offer(&handler.charge_offers, handler.vehicle.clone()),
-        VehicleAlgorithm::Random => get_random_offer(&handler.charge_offers, handler.seed),
-        VehicleAlgorithm::Cheapest => get_cheapest_offer(&handler.charge_offers),
-        VehicleAlgorithm::Closest => get_closest_offer(&handler.charge_offers, handler.vehicle.clone()),
-    }.unwrap();
+    // Only consider chargers within the range at the planned speed
+    let offers: Vec<ChargeOffer> = handler.charge_offers.iter()
+        .filter(|offer| handler.vehicle.can_reach(offer.charger_position))
+        .cloned()
+        .collect();
+
+    // Determine the best offer based on the deadline, the trip or the vehicle's algorithm
+    handler.trip.stop_target_soc = None;
+    let accepted_offer = if handler.planner.charging_for_deadline {
+        get_deadline_offer(&offers, &handler.vehicle)
+    } else if TripPlanner::needs_stop(&handler.vehicle) {
+        let stop = get_en_route_offer(&offers, &handler.vehicle, handler.trip.objective);
+        handler.trip.stop_target_soc = stop.as_ref().map(|(_, target_soc)| *target_soc);
+        stop.map(|(offer, _)| offer)
+    } else {
+        match handler.vehicle.get_algorithm() {
+            VehicleAlgorithm::Best => get_best_offer(&offers, handler.vehicle.clone()),
+            VehicleAlgorithm::Random => get_random_offer(&offers, handler.seed),
+            VehicleAlgorithm::Cheapest => get_cheapest_offer(&offers),
+            VehicleAlgorithm::Closest => get_closest_offer(&offers, handler.vehicle.clone()),
+            VehicleAlgorithm::Fastest => get_fastest_offer(&offers, handler.vehicle.clone()),
+            VehicleAlgorithm::Scored => get_scored_offer(&offers, &handler.vehicle, handler.scoring),
+        }
+    };
+    let Some(accepted_offer) = accepted_offer else {
+        if handler.vehicle.get_status() != VehicleStatus::Stranded {
+            warn!(
+                "{} can't reach any of the {} offering chargers with {:.1} km range, it is stranded",
+                handler.vehicle.get_name(), handler.charge_offers.len(), handler.vehicle.get_range()
+            );
+            handler.vehicle.set_status(VehicleStatus::Stranded);
+        }
+        handler.charge_offers.clear();
+        return;
+    };
+
+    if unanswered.is_none() {
+        if let Some(counter) = handler.negotiator.open(&accepted_offer) {
+            send_counter(&mut handler, counter).await;
+            return;
+        }
+    }
+    commit_to_offer(&mut handler, accepted_offer).await;
+}
+
+/// # Description
+/// Commits the vehicle to an offer: it drives to the charger and publishes an acceptance message,
+/// which binds the charger to the price of the offer and tells it when the vehicle arrives.
+///
+/// # Arguments
+/// - `handler`: The vehicle handler.
+/// - `accepted_offer`: The offer the vehicle takes.
+pub async fn commit_to_offer(handler: &mut VehicleHandler, accepted_offer: ChargeOffer) {
+    handler.stranded_ticks = 0;
+    cancel_rescue(handler).await;
 
     // drive to the charger
     handler.vehicle.set_status(VehicleStatus::SearchingForCharger);
     handler.vehicle.set_next_stop(accepted_offer.charger_position);
 
     info!(
-        "Accepting best offer from {}: {} kWh at {}€",
-        accepted_offer.charger_name, accepted_offer.charge_amount, accepted_offer.charge_price
+        "Accepting best offer from {}: {} kWh at {}€ with {} kW ({:?})",
+        accepted_offer.charger_name, accepted_offer.charge_amount, accepted_offer.charge_price,
+        accepted_offer.charge_power, accepted_offer.connector
     );
 
     handler.charge_offers.clear();
     handler.target_charger = Some(accepted_offer.clone());
+    handler.reserved_until = handler.current_tick + accepted_offer.terms.reservation_ticks;
 
     let acceptance = ChargeAccept {
         charger_name: accepted_offer.charger_name.clone(),
@@ -96,6 +171,7 @@ pub async fn accept_offer(handler: SharedVehicle) {
         charge_price: accepted_offer.charge_price,
         distance: handler.vehicle.distance_to(accepted_offer.charger_position),
         cost: accepted_offer.charge_price * accepted_offer.charge_amount as f64,
+        eta_ticks: handler.vehicle.get_eta_ticks(),
     };
 
     handler
@@ -108,6 +184,29 @@ pub async fn accept_offer(handler: SharedVehicle) {
         ).await.unwrap();
 }
 
+/// # Description
+/// Tells the charger the vehicle drives to when it arrives, so it holds the port from then on.
+///
+/// # Arguments
+/// - `handler`: The vehicle handler.
+/// - `eta_ticks`: In how many ticks the vehicle expects to arrive.
+pub async fn send_eta(handler: &mut VehicleHandler, eta_ticks: u64) {
+    let Some(target_charger) = &handler.target_charger else {
+        return;
+    };
+    let eta = ChargeEta {
+        charger_name: target_charger.charger_name.clone(),
+        vehicle_name: handler.vehicle.get_name(),
+        eta_ticks,
+    };
+    trace!("Sending estimated arrival {:?}", eta);
+    handler
+        .client
+        .publish(CHARGER_ETA, QoS::ExactlyOnce, false, eta.to_bytes())
+        .await
+        .unwrap();
+}
+
 /// # Description
 /// Selects the best charge offer based the cost.
 /// It is calculated by multiplying the charge price with the charge amount.<br>
@@ -126,13 +225,11 @@ fn get_best_offer(offers: &[ChargeOffer], vehicle: Vehicle) -> Option<ChargeOffe
         return None;
     }
     
-    // Create a sorted vector by cost (charge_price * charge_amount)
+    // Create a sorted vector by cost ((charge_price + battery wear) * charge_amount)
+    let battery = vehicle.battery_non_mut();
+    let cost = |offer: &ChargeOffer| (offer.charge_price + battery.wear_cost_per_kwh(offer.charge_power)) * offer.charge_amount as f64;
     let mut sorted_offers: Vec<ChargeOffer> = offers.to_vec();
-    sorted_offers.sort_by(|a, b| {
-        let costs = a.charge_price * a.charge_amount as f64;
-        let costs_b = b.charge_price * b.charge_amount as f64;
-        costs.partial_cmp(&costs_b).unwrap()
-    });
+    sorted_offers.sort_by(|a, b| cost(a).partial_cmp(&cost(b)).unwrap());
     debug!("Sorted offers by cost: {:?}", sorted_offers);
 
     // Return the first offer that is enough to fully charge the vehicle
@@ -205,6 +302,30 @@ fn get_closest_offer(offers: &[ChargeOffer], vehicle: Vehicle) -> Option<ChargeO
     res
 }
 
+/// # Description
+/// Selects the charge offer with the highest charging power.<br>
+/// If several offers charge equally fast, the closest one is chosen.
+/// 
+/// # Arguments
+/// - `offers`: A slice of `ChargeOffer` instances representing the available charge offers.
+/// - `vehicle`: The vehicle for which the fastest charge offer is being selected.
+/// 
+/// # Returns
+/// An `Option<ChargeOffer>` containing the fastest charge offer, or `None` if no offers are available.
+fn get_fastest_offer(offers: &[ChargeOffer], vehicle: Vehicle) -> Option<ChargeOffer> {
+    debug!("Selecting the fastest charge offer.");
+    if offers.is_empty() {
+        return None;
+    }
+    debug!("Offers: {:?}", offers);
+    let res = offers.iter().max_by(|a, b| {
+        a.charge_power.partial_cmp(&b.charge_power).unwrap()
+            .then(vehicle.distance_to(b.charger_position).partial_cmp(&vehicle.distance_to(a.charger_position)).unwrap())
+    }).cloned();
+    debug!("Fastest offer: {:?}", res);
+    res
+}
+
 /// # Description
 /// Creates a Get message to request charging from the target charger.
 /// 
@@ -256,9 +377,22 @@ pub async fn get_ack_handling(handler: SharedVehicle, payload: Bytes) {
             handler.vehicle.battery().get_soc()
         );
 
-        // At 85% state of charge, we consider the vehicle fully charged
-        if handler.vehicle.battery().get_soc() >= 0.95 {
+        // At 85% state of charge, we consider the vehicle fully charged, on the way to a distant destination it
+        // only charges as much as the trip needs
+        let soc = handler.vehicle.battery().get_soc();
+        let en_route = handler.trip.stop_target_soc.is_some_and(|target_soc| soc >= target_soc);
+        if soc >= 0.95 || en_route {
             info!("{} has been fully charged.", handler.vehicle.get_name());
+            handler.planner.charging_for_deadline = false;
+            handler.trip.stop_target_soc = None;
+
+            // Stay plugged in to feed energy back into the grid, unless it is on a trip
+            if handler.v2g_policy.enabled && !en_route {
+                info!("{} stays plugged in for V2G", handler.vehicle.get_name());
+                handler.vehicle.set_status(VehicleStatus::Parked);
+                handler.plugged_in_until = handler.current_tick + handler.v2g_policy.plugged_ticks;
+                return;
+            }
 
             handler
                 .client
@@ -276,4 +410,105 @@ pub async fn get_ack_handling(handler: SharedVehicle, payload: Bytes) {
             handler.vehicle.set_next_stop(destination);
         }
     }
-}
\ No newline at end of file
+}
+
+/// # Description
+/// Cancels the reservation at the target charger, because the vehicle won't make it in time.<br>
+/// The cancellation fee of the reservation terms is paid and the vehicle continues to its destination.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+pub async fn cancel_reservation(handler: SharedVehicle) {
+    let mut handler = handler.lock().await;
+
+    let Some(target) = handler.target_charger.take() else {
+        return;
+    };
+
+    let cancel = ChargeCancel::new(
+        target.charger_name.clone(),
+        handler.vehicle.get_name(),
+        CancelReason::Vehicle,
+        target.terms.cancellation_fee,
+    );
+    info!("Cancelling reservation at {}, paying {}€", target.charger_name, cancel.fee);
+
+    handler.vehicle.add_fee(cancel.fee);
+    handler.wallet.pay_fee(cancel.fee);
+    if !matches!(handler.vehicle.get_status(), VehicleStatus::Broken | VehicleStatus::Stranded) {
+        handler.vehicle.set_status(VehicleStatus::Random);
+    }
+    let destination = handler.vehicle.get_destination();
+    handler.vehicle.set_next_stop(destination);
+
+    handler
+        .client
+        .publish(CHARGER_CANCEL, QoS::ExactlyOnce, false, cancel.to_bytes())
+        .await
+        .unwrap();
+}
+
+/// # Description
+/// Handles a no-show message of a charger.<br>
+/// The charger released the reservation, so the no-show fee is paid and the vehicle continues to its destination.
+/// It will search for a new charger on the next tick if its battery is still low.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+/// - `payload`: The payload of the message received on the CHARGER_CANCEL topic.
+pub async fn cancel_handler(handler: SharedVehicle, payload: Bytes) {
+    let mut handler = handler.lock().await;
+
+    let cancel = ChargeCancel::from_bytes(payload).unwrap();
+    if cancel.reason != CancelReason::NoShow || cancel.vehicle_name != handler.vehicle.get_name() {
+        return;
+    }
+    if handler.target_charger.as_ref().is_none_or(|t| t.charger_name != cancel.charger_name) {
+        debug!("Ignoring no-show of {} for a charger we are not driving to", cancel.charger_name);
+        return;
+    }
+
+    info!("{} released our reservation, paying a no-show fee of {}€", cancel.charger_name, cancel.fee);
+    handler.vehicle.add_fee(cancel.fee);
+    handler.wallet.pay_fee(cancel.fee);
+    handler.target_charger = None;
+    handler.vehicle.set_status(VehicleStatus::Random);
+    let destination = handler.vehicle.get_destination();
+    handler.vehicle.set_next_stop(destination);
+}
+
+/// # Description
+/// Handles a rejected charge request.<br>
+/// The charger put the vehicle on its waitlist. If the estimated waiting time is short, the vehicle stays on it
+/// and will get an offer once a port is free. Otherwise it leaves the waitlist and takes the offers of other chargers.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+/// - `payload`: The payload of the message received on the CHARGER_REJECT topic.
+pub async fn reject_handler(handler: SharedVehicle, payload: Bytes) {
+    let handler = handler.lock().await;
+
+    let reject = ChargeReject::from_bytes(payload).unwrap();
+    if reject.vehicle_name != handler.vehicle.get_name() || handler.target_charger.is_some() {
+        return;
+    }
+
+    if reject.estimated_wait_ticks <= MAX_WAIT_TICKS {
+        debug!(
+            "Waiting for {} at position {} for about {} ticks ({:?})",
+            reject.charger_name, reject.waitlist_position, reject.estimated_wait_ticks, reject.reason
+        );
+        return;
+    }
+
+    info!(
+        "{} has a waiting time of {} ticks, driving elsewhere",
+        reject.charger_name, reject.estimated_wait_ticks
+    );
+    let cancel = ChargeCancel::new(reject.charger_name, reject.vehicle_name, CancelReason::Vehicle, 0.0);
+    handler
+        .client
+        .publish(CHARGER_CANCEL, QoS::ExactlyOnce, false, cancel.to_bytes())
+        .await
+        .unwrap();
+}
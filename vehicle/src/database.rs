@@ -1,44 +1,156 @@
+use std::{error::Error, sync::OnceLock};
+
+use powercable::charger::ConnectorType;
 use rand::{rngs::StdRng, seq::IndexedRandom, SeedableRng};
+use serde::Deserialize;
+use tracing::{error, info};
+
+/// Path to a JSON file with EV models, the built-in models are used if not set
+const EV_MODELS: &str = "EV_MODELS";
+/// The built-in EV models
+const DEFAULT_EV_MODELS: &str = include_str!("../ev_models.json");
 
-const EV_DATABASE: &[(&str, f64, f64, usize)] = &[
-    ("Skoda Elroq 85", 17.1, 77.0, 120),
-    ("Mercedes-Benz CLA 250+", 15.0, 85.0, 1700),
-    ("Smart #5 Premium", 20.2, 94.0, 230),
-    ("Kia EV3", 17.1, 78.0, 105),
-    ("Volkswagen ID.7 Pro", 16.2, 77.0, 125),
-    ("Mini Electric Cooper SE", 16.1, 28.0, 44),
-    ("Tesla Model 3 RWD", 13.7, 57.0, 108),
-    ("Tesla Model Y RWD", 16.4, 60.0, 110),
-    ("BMW i4 eDrive35", 15.6, 70.0, 120),
-    ("Hyundai IONIQ 6 Long Range 2WD", 14.9, 77.0, 220),
-    ("Renault Megane E-Tech EV60", 15.8, 60.0, 130),
-    ("Audi A6 Sportback e-tron", 16.1, 100.0, 250),
-    ("Polestar 2 Long Range Single Motor", 16.6, 78.0, 150),
-    ("Porsche Taycan", 16.6, 93.0, 270),
-    ("BYD ATTO 3", 18.3, 60.0, 71),
-    ("Fiat 500e Hatchback", 15.9, 42.0, 85),
-    ("Volkswagen ID.3 Pro", 16.2, 77.0, 125),
-    ("Lucid Air Touring", 15.9, 112.0, 300),
-    ("Ford Capri Standard Range RWD", 16.3, 52.0, 150),
-    ("BMW iX1 eDrive20", 16.6, 66.0, 130),
-    ("Audi Q4 e-tron 50 quattro", 17.5, 82.0, 135),
-    ("Nissan Ariya 87kWh", 18.0, 87.0, 130),
-    ("Volvo EX30 Twin Motor Performance", 19.0, 69.0, 150),
-    ("Genesis GV60 Sport Plus", 18.5, 77.0, 240),
-    ("Honda e:Ny1", 17.2, 62.0, 100),
-    ("Mazda MX-30", 19.0, 35.0, 50),
-];
+static MODELS: OnceLock<Vec<EvModel>> = OnceLock::new();
 
 /// # Description
-/// Returns a random electric vehicle from the database.
-/// 
-/// # Returns
-/// A tuple containing:
-/// - The name of the vehicle.
-/// - The consumption of the vehicle in kWh/100km.
-/// - The battery capacity of the vehicle in kWh.
-/// - The maximum charge rate of the vehicle in kW.
-pub fn random_ev(seed: u64) -> (&'static str, f64, f64, usize) {
+/// An electric vehicle model, vehicles are sampled from a list of them.
+///
+/// # Fields
+/// - `name`: The name of the model.
+/// - `consumption`: The consumption in kWh/100km.
+/// - `usable_capacity`: The usable capacity of the battery in kWh.
+/// - `max_ac_power`: The power limit of the on-board charger in kW, used at AC ports.
+/// - `max_dc_power`: The peak power in kW at DC fast chargers.
+/// - `connectors`: The connector types the vehicle can be charged with.
+/// - `share`: The market share of the model, only the ratio between the models matters.
+/// - `charge_curve`: The DC charging power in kW over the state of charge, as `[soc, kW]` points.
+///   If empty, a generic curve is used.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvModel {
+    pub name: String,
+    pub consumption: f64,
+    pub usable_capacity: f64,
+    pub max_ac_power: f64,
+    pub max_dc_power: f64,
+    pub connectors: Vec<ConnectorType>,
+    #[serde(default = "default_share")]
+    pub share: f64,
+    #[serde(default)]
+    pub charge_curve: Vec<(f64, f64)>,
+}
+
+fn default_share() -> f64 {
+    1.0
+}
+
+/// # Description
+/// Reads EV models from a JSON list.<br>
+/// The points of each charge curve are sorted by state of charge.
+///
+/// # Arguments
+/// - `json`: The JSON text.
+fn parse_models(json: &str) -> Result<Vec<EvModel>, Box<dyn Error>> {
+    let mut models: Vec<EvModel> = serde_json::from_str(json)?;
+    if models.is_empty() {
+        return Err("no EV models".into());
+    }
+    for model in models.iter_mut() {
+        model.charge_curve.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+    Ok(models)
+}
+
+/// # Description
+/// Reads the EV models from a file, or the built-in ones if there is none or it can't be read.
+///
+/// # Arguments
+/// - `path`: The path to a JSON file with EV models.
+fn load_models(path: Option<String>) -> Vec<EvModel> {
+    if let Some(path) = path {
+        match std::fs::read_to_string(&path).map_err(Box::<dyn Error>::from).and_then(|json| parse_models(&json)) {
+            Ok(models) => {
+                info!("Loaded {} EV models from {}", models.len(), path);
+                return models;
+            }
+            Err(e) => error!("Could not read EV models {}, using the built-in ones: {}", path, e),
+        }
+    }
+    parse_models(DEFAULT_EV_MODELS).expect("built-in EV models are valid")
+}
+
+/// # Description
+/// Returns the EV models from the file in the `EV_MODELS` environment variable, or the built-in ones.<br>
+/// They are loaded once, on the first call.
+pub fn ev_models() -> &'static [EvModel] {
+    MODELS.get_or_init(|| load_models(std::env::var(EV_MODELS).ok()))
+}
+
+/// Picks one of the models, weighted by their market share, or any of them if no model has a share.
+fn choose_model(models: &[EvModel], seed: u64) -> &EvModel {
     let mut rng = StdRng::seed_from_u64(seed);
-    *EV_DATABASE.choose(&mut rng).unwrap()
+    models
+        .choose_weighted(&mut rng, |model| model.share.max(0.0))
+        .unwrap_or_else(|_| models.choose(&mut rng).unwrap())
+}
+
+/// # Description
+/// Returns a random electric vehicle model, weighted by the market share of the models.
+///
+/// # Arguments
+/// - `seed`: A seed for the random number generator.
+///
+/// # Returns
+/// The model of the vehicle.
+pub fn random_ev(seed: u64) -> &'static EvModel {
+    choose_model(ev_models(), seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(name: &str, share: f64) -> EvModel {
+        EvModel { name: name.to_string(), share, ..parse_models(DEFAULT_EV_MODELS).unwrap().remove(0) }
+    }
+
+    #[test]
+    fn models_are_parsed_with_sorted_curves() {
+        let models = parse_models(
+            r#"[{"name": "Test", "consumption": 16.0, "usable_capacity": 60.0, "max_ac_power": 11.0, "max_dc_power": 100.0,
+                "connectors": ["Type2", "Ccs"], "charge_curve": [[0.8, 50.0], [0.0, 80.0], [0.2, 100.0]]}]"#,
+        )
+        .unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].share, 1.0);
+        assert_eq!(models[0].connectors, vec![ConnectorType::Type2, ConnectorType::Ccs]);
+        assert_eq!(models[0].charge_curve, vec![(0.0, 80.0), (0.2, 100.0), (0.8, 50.0)]);
+    }
+
+    #[test]
+    fn invalid_models_are_rejected() {
+        assert!(parse_models("[]").is_err());
+        assert!(parse_models("not json").is_err());
+        assert!(parse_models(r#"[{"name": "Missing fields"}]"#).is_err());
+    }
+
+    #[test]
+    fn unreadable_file_falls_back_to_built_in_models() {
+        let built_in = parse_models(DEFAULT_EV_MODELS).unwrap();
+        let models = load_models(Some("/does/not/exist.json".to_string()));
+        assert_eq!(models.len(), built_in.len());
+        assert_eq!(load_models(None).len(), built_in.len());
+    }
+
+    #[test]
+    fn models_are_chosen_by_market_share() {
+        let models = vec![model("Common", 9.0), model("Rare", 1.0), model("Gone", 0.0)];
+        let chosen: Vec<&str> = (0..1000).map(|seed| choose_model(&models, seed).name.as_str()).collect();
+        let common = chosen.iter().filter(|name| **name == "Common").count();
+        assert!((850..950).contains(&common), "{} of 1000 are common", common);
+        assert!(!chosen.contains(&"Gone"));
+
+        // Without any share every model can be chosen
+        let models = vec![model("A", 0.0), model("B", 0.0)];
+        assert!((0..100).any(|seed| choose_model(&models, seed).name == "B"));
+    }
 }
@@ -0,0 +1,116 @@
+use bytes::Bytes;
+use powercable::{
+    fleet::{FleetAssignment, FleetVehicleStatus},
+    FLEET_VEHICLE_STATUS,
+};
+use rumqttc::QoS;
+use tokio::task;
+use tracing::{debug, info, trace};
+
+use crate::{
+    charger_handling::{accept_offer, commit_to_offer},
+    vehicle::VehicleStatus,
+    SharedVehicle, VehicleHandler,
+};
+
+/// How many ticks a vehicle waits for the heartbeat of its fleet manager, before it chooses its chargers on its own again
+const FLEET_TIMEOUT_TICKS: u64 = 2;
+
+impl VehicleHandler {
+    /// # Returns
+    /// Whether a fleet manager currently chooses the charger offers the vehicle accepts.
+    pub fn is_managed(&self) -> bool {
+        self.fleet_seen_tick
+            .is_some_and(|tick| self.current_tick <= tick + FLEET_TIMEOUT_TICKS)
+    }
+}
+
+/// # Description
+/// Handles the heartbeat of a fleet manager.<br>
+/// If it is our fleet, the vehicle lets the manager choose its offers until the heartbeats stop.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+/// - `payload`: The payload containing the name of the fleet.
+pub async fn heartbeat_handler(handler: SharedVehicle, payload: Bytes) {
+    let fleet = String::from_utf8_lossy(&payload);
+    let mut handler = handler.lock().await;
+
+    if handler.fleet.as_deref() != Some(fleet.as_ref()) {
+        return;
+    }
+    if !handler.is_managed() {
+        info!("{} is managed by {} now", handler.vehicle.get_name(), fleet);
+    }
+    handler.fleet_seen_tick = Some(handler.current_tick);
+}
+
+/// # Description
+/// Reports the state of the vehicle to its fleet manager.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+pub async fn publish_status(handler: SharedVehicle) {
+    let handler = handler.lock().await;
+    let Some(fleet) = handler.fleet.clone() else {
+        return;
+    };
+    let status = FleetVehicleStatus {
+        vehicle_name: handler.vehicle.get_name(),
+        fleet,
+        position: handler.vehicle.get_location(),
+        soc: handler.vehicle.battery_non_mut().get_soc(),
+        range: handler.vehicle.get_range(),
+        speed: handler.vehicle.planned_speed() as f64,
+        searching: handler.target_charger.is_none() && handler.vehicle.get_status() == VehicleStatus::SearchingForCharger,
+    };
+    trace!("Sending fleet status {:?}", status);
+    handler
+        .client
+        .publish(FLEET_VEHICLE_STATUS, QoS::ExactlyOnce, false, status.to_bytes())
+        .await
+        .unwrap();
+}
+
+/// # Description
+/// Handles the assignment of the fleet manager.<br>
+/// The vehicle accepts the offer of the assigned charger as it is, without negotiating.
+/// If it is told to wait, it drops its offers and asks again on the next tick.
+/// If the assigned offer is gone or out of reach, or none of its offers is in reach, the vehicle decides on its own.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+/// - `payload`: The payload containing the assignment.
+pub async fn assign_handler(handler: SharedVehicle, payload: Bytes) {
+    let assignment = FleetAssignment::from_bytes(payload).unwrap();
+    let mut locked_handler = handler.lock().await;
+    if assignment.vehicle_name != locked_handler.vehicle.get_name() || locked_handler.target_charger.is_some() {
+        return;
+    }
+    debug!("Fleet assignment: {:?}", assignment);
+
+    let assigned = assignment.charger_name.as_ref().and_then(|charger_name| {
+        locked_handler.charge_offers.iter().rev()
+            .find(|o| o.charger_name == *charger_name && locked_handler.vehicle.can_reach(o.charger_position))
+            .cloned()
+    });
+    let any_reachable = locked_handler.charge_offers.iter().any(|o| locked_handler.vehicle.can_reach(o.charger_position));
+    match (assignment.charger_name, assigned) {
+        (Some(charger_name), Some(offer)) => {
+            info!("{} assigned us to {}", assignment.fleet, charger_name);
+            locked_handler.trip.stop_target_soc = None;
+            commit_to_offer(&mut locked_handler, offer).await;
+        }
+        (Some(charger_name), None) => {
+            debug!("The offer of {} is gone or out of reach, choosing on our own", charger_name);
+            task::spawn(accept_offer(handler.clone()));
+        }
+        (None, _) if any_reachable => {
+            info!("{} lets us wait for a free charger", assignment.fleet);
+            locked_handler.charge_offers.clear();
+        }
+        (None, _) => {
+            task::spawn(accept_offer(handler.clone()));
+        }
+    }
+}
@@ -1,19 +1,37 @@
 use tracing::{debug, info, warn};
-use powercable::{charger::ChargeOffer, CHARGER_CHARGING_ACK, CHARGER_OFFER, CONFIG_VEHICLE_SCALE, CONFIG_VEHICLE, MQTT_BROKER, MQTT_BROKER_PORT, TICK_TOPIC, CONFIG_VEHICLE_ALGORITHM, WORLDMAP_EVENT_TOPIC};
+use powercable::{charger::ChargeOffer, weather::WeatherReport, CHARGER_CANCEL, CHARGER_CHARGING_ACK, CHARGER_REJECT, CHARGER_OFFER, CONFIG_VEHICLE_SCALE, CONFIG_VEHICLE, MQTT_BROKER, MQTT_BROKER_PORT, MQTT_REQUEST_CAPACITY, TICK_TOPIC, CONFIG_VEHICLE_ALGORITHM, CONFIG_VEHICLE_V2G, CONFIG_VEHICLE_SCORING, CONFIG_VEHICLE_FAILURE, CONFIG_VEHICLE_SCHEDULE, CONFIG_VEHICLE_TRIP, CONFIG_VEHICLE_NEGOTIATION, CONFIG_VEHICLE_WALLET, CHARGER_RECEIPT, CHARGER_REVISE, FLEET_ASSIGN, FLEET_HEARTBEAT, CHARGER_V2G_DISCHARGE, RESCUE_TOW_TOPIC, WEATHER_TOPIC, WORLDMAP_EVENT_TOPIC};
 use rumqttc::{AsyncClient, MqttOptions, QoS};
 use std::{sync::Arc, time::Duration};
 use tokio::{sync::Mutex, task};
-use topic_handler::{tick_handler, worldmap_event_handler};
+use topic_handler::{tick_handler, weather_handler, worldmap_event_handler};
 use charger_handling::{receive_offer};
 use vehicle::Vehicle;
+use fleet::{assign_handler, heartbeat_handler};
+use negotiation::{negotiation_handler, revise_handler, Negotiator};
+use planner::ChargePlanner;
+use rescue::{failure_handler, tow_handler, FailurePolicy};
+use schedule::{schedule_handler, Schedule};
+use scoring::{scoring_handler, ScoringWeights};
+use trip::{trip_handler, TripPlanner};
+use v2g::{discharge_handler, v2g_policy_handler, V2gPolicy};
+use wallet::{receipt_handler, wallet_handler, Wallet};
 
-use crate::{charger_handling::get_ack_handling, topic_handler::{algorithm_handler, scale_handler, show_handler}};
+use crate::{charger_handling::{cancel_handler, get_ack_handling, reject_handler}, topic_handler::{algorithm_handler, scale_handler, show_handler}};
 
 mod battery;
 mod charger_handling;
 mod database;
+mod fleet;
+mod negotiation;
+mod planner;
+mod rescue;
+mod schedule;
+mod scoring;
 mod topic_handler;
+mod trip;
+mod v2g;
 mod vehicle;
+mod wallet;
 
 type SharedVehicle = Arc<Mutex<VehicleHandler>>;
 
@@ -23,13 +41,42 @@ struct VehicleHandler {
     pub target_charger: Option<ChargeOffer>,
     pub client: AsyncClient,
     pub seed: u64,
+    pub current_tick: u64,
+    pub current_timestamp: usize,
+    pub reserved_until: u64,
+    pub planner: ChargePlanner,
+    pub v2g_policy: V2gPolicy,
+    pub has_own_v2g_policy: bool,
+    pub plugged_in_until: u64,
+    pub v2g_earnings: f64,
+    pub scoring: ScoringWeights,
+    pub has_own_scoring: bool,
+    pub stranded_ticks: u64,
+    pub failure: FailurePolicy,
+    pub rescue_requested: bool,
+    pub schedule: Schedule,
+    pub has_own_schedule: bool,
+    pub trip: TripPlanner,
+    pub weather_station: Option<WeatherReport>,
+    pub negotiator: Negotiator,
+    pub wallet: Wallet,
+    pub has_own_wallet: bool,
+    pub fleet: Option<String>,
+    pub fleet_seen_tick: Option<u64>,
 }
 
-pub async fn start_vehicle(i: u64) {
+/// # Description
+/// Starts a vehicle at a random position.
+///
+/// # Arguments
+/// - `i`: The index of the vehicle, used for its seed.
+/// - `fleet`: The fleet the vehicle belongs to, if any.
+pub async fn start_vehicle(i: u64, fleet: Option<String>) {
     // init vehicle
     let seed = powercable::generate_seed(i, powercable::OwnType::Vehicle);
     let vehicle_name: String = powercable::generate_unique_name(seed);
     let vehicle = Vehicle::new(vehicle_name.clone(), powercable::generate_rnd_pos(seed), seed);
+    let schedule = Schedule::new(vehicle.get_location(), seed);
     info!("{:#?}", vehicle);
 
     let mut mqttoptions = MqttOptions::new(
@@ -38,7 +85,7 @@ pub async fn start_vehicle(i: u64) {
         MQTT_BROKER_PORT,
     );
     mqttoptions.set_keep_alive(Duration::from_secs(5));
-    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+    let (client, mut eventloop) = AsyncClient::new(mqttoptions, MQTT_REQUEST_CAPACITY);
     client
         .subscribe(TICK_TOPIC, QoS::ExactlyOnce)
         .await.unwrap();
@@ -50,6 +97,12 @@ pub async fn start_vehicle(i: u64) {
     client
         .subscribe(CHARGER_CHARGING_ACK, QoS::ExactlyOnce)
         .await.unwrap();
+    client
+        .subscribe(CHARGER_CANCEL, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(CHARGER_REJECT, QoS::ExactlyOnce)
+        .await.unwrap();
     client
         .subscribe(CONFIG_VEHICLE_SCALE, QoS::ExactlyOnce)
         .await.unwrap();
@@ -59,6 +112,64 @@ pub async fn start_vehicle(i: u64) {
     client
         .subscribe(CONFIG_VEHICLE, QoS::ExactlyOnce)
         .await.unwrap();
+    client
+        .subscribe(CHARGER_V2G_DISCHARGE, QoS::ExactlyOnce)
+        .await.unwrap();
+    let own_v2g_topic = format!("{}/{}", CONFIG_VEHICLE_V2G, vehicle_name);
+    client
+        .subscribe(CONFIG_VEHICLE_V2G, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(&own_v2g_topic, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(CONFIG_VEHICLE_FAILURE, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(RESCUE_TOW_TOPIC, QoS::ExactlyOnce)
+        .await.unwrap();
+    let own_schedule_topic = format!("{}/{}", CONFIG_VEHICLE_SCHEDULE, vehicle_name);
+    client
+        .subscribe(CONFIG_VEHICLE_SCHEDULE, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(&own_schedule_topic, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(CONFIG_VEHICLE_TRIP, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(CONFIG_VEHICLE_NEGOTIATION, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(CHARGER_REVISE, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(WEATHER_TOPIC, QoS::ExactlyOnce)
+        .await.unwrap();
+    let own_scoring_topic = format!("{}/{}", CONFIG_VEHICLE_SCORING, vehicle_name);
+    client
+        .subscribe(CONFIG_VEHICLE_SCORING, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(&own_scoring_topic, QoS::ExactlyOnce)
+        .await.unwrap();
+    let own_wallet_topic = format!("{}/{}", CONFIG_VEHICLE_WALLET, vehicle_name);
+    client
+        .subscribe(CONFIG_VEHICLE_WALLET, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(&own_wallet_topic, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(CHARGER_RECEIPT, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(FLEET_HEARTBEAT, QoS::ExactlyOnce)
+        .await.unwrap();
+    client
+        .subscribe(FLEET_ASSIGN, QoS::ExactlyOnce)
+        .await.unwrap();
     debug!("Connected to MQTT broker");
 
     let shared_vehicle = Arc::new(Mutex::new(VehicleHandler {
@@ -67,6 +178,28 @@ pub async fn start_vehicle(i: u64) {
         charge_offers: Vec::new(),
         client: client.clone(),
         seed,
+        current_tick: 0,
+        current_timestamp: 0,
+        reserved_until: 0,
+        planner: ChargePlanner::default(),
+        v2g_policy: V2gPolicy::default(),
+        has_own_v2g_policy: false,
+        plugged_in_until: 0,
+        v2g_earnings: 0.0,
+        scoring: ScoringWeights::default(),
+        has_own_scoring: false,
+        stranded_ticks: 0,
+        failure: FailurePolicy::default(),
+        rescue_requested: false,
+        schedule,
+        has_own_schedule: false,
+        trip: TripPlanner::default(),
+        weather_station: None,
+        negotiator: Negotiator::default(),
+        wallet: Wallet::new(seed),
+        has_own_wallet: false,
+        fleet,
+        fleet_seen_tick: None,
     }));
 
     while let Ok(notification) = eventloop.poll().await {
@@ -84,6 +217,12 @@ pub async fn start_vehicle(i: u64) {
                 CHARGER_CHARGING_ACK => {
                     let _ = task::spawn(get_ack_handling(shared_vehicle.clone(), p.payload));
                 }
+                CHARGER_CANCEL => {
+                    let _ = task::spawn(cancel_handler(shared_vehicle.clone(), p.payload));
+                }
+                CHARGER_REJECT => {
+                    let _ = task::spawn(reject_handler(shared_vehicle.clone(), p.payload));
+                }
                 CONFIG_VEHICLE_SCALE => {
                     let _ = task::spawn(scale_handler(shared_vehicle.clone(), p.payload));
                 }
@@ -93,6 +232,60 @@ pub async fn start_vehicle(i: u64) {
                 CONFIG_VEHICLE => {
                     let _ = task::spawn(show_handler(shared_vehicle.clone(), p.payload));
                 }
+                CHARGER_V2G_DISCHARGE => {
+                    task::spawn(discharge_handler(shared_vehicle.clone(), p.payload));
+                }
+                CONFIG_VEHICLE_V2G => {
+                    task::spawn(v2g_policy_handler(shared_vehicle.clone(), p.payload, false));
+                }
+                topic if topic == own_v2g_topic => {
+                    task::spawn(v2g_policy_handler(shared_vehicle.clone(), p.payload, true));
+                }
+                CONFIG_VEHICLE_SCORING => {
+                    task::spawn(scoring_handler(shared_vehicle.clone(), p.payload, false));
+                }
+                topic if topic == own_scoring_topic => {
+                    task::spawn(scoring_handler(shared_vehicle.clone(), p.payload, true));
+                }
+                CONFIG_VEHICLE_SCHEDULE => {
+                    task::spawn(schedule_handler(shared_vehicle.clone(), p.payload, false));
+                }
+                topic if topic == own_schedule_topic => {
+                    task::spawn(schedule_handler(shared_vehicle.clone(), p.payload, true));
+                }
+                CONFIG_VEHICLE_TRIP => {
+                    task::spawn(trip_handler(shared_vehicle.clone(), p.payload));
+                }
+                CONFIG_VEHICLE_WALLET => {
+                    task::spawn(wallet_handler(shared_vehicle.clone(), p.payload, false));
+                }
+                topic if topic == own_wallet_topic => {
+                    task::spawn(wallet_handler(shared_vehicle.clone(), p.payload, true));
+                }
+                CHARGER_RECEIPT => {
+                    task::spawn(receipt_handler(shared_vehicle.clone(), p.payload));
+                }
+                FLEET_HEARTBEAT => {
+                    task::spawn(heartbeat_handler(shared_vehicle.clone(), p.payload));
+                }
+                FLEET_ASSIGN => {
+                    task::spawn(assign_handler(shared_vehicle.clone(), p.payload));
+                }
+                CONFIG_VEHICLE_NEGOTIATION => {
+                    task::spawn(negotiation_handler(shared_vehicle.clone(), p.payload));
+                }
+                CHARGER_REVISE => {
+                    task::spawn(revise_handler(shared_vehicle.clone(), p.payload));
+                }
+                WEATHER_TOPIC => {
+                    task::spawn(weather_handler(shared_vehicle.clone(), p.payload));
+                }
+                CONFIG_VEHICLE_FAILURE => {
+                    task::spawn(failure_handler(shared_vehicle.clone(), p.payload));
+                }
+                RESCUE_TOW_TOPIC => {
+                    task::spawn(tow_handler(shared_vehicle.clone(), p.payload));
+                }
                 _ => {
                     warn!("Unknown topic: {}", p.topic);
                 }
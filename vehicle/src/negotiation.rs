@@ -0,0 +1,290 @@
+use bytes::Bytes;
+use powercable::{charger::{ChargeCounter, ChargeOffer}, CHARGER_COUNTER};
+use rumqttc::QoS;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use crate::{charger_handling::commit_to_offer, SharedVehicle, VehicleHandler};
+
+/// # Description
+/// An open negotiation of the vehicle with a charger about the price of its offer.
+///
+/// # Fields
+/// - `charger_name`: The name of the charger the vehicle negotiates with.
+/// - `bid`: The price per kWh the vehicle offered last.
+/// - `round`: The current round of the negotiation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Negotiation {
+    pub charger_name: String,
+    pub bid: f64,
+    pub round: u32,
+}
+
+/// # Description
+/// The answer of the vehicle to a revised offer.
+///
+/// # Variants
+/// - `Accept`: The vehicle takes the revised offer.
+/// - `Counter`: The vehicle gives in a bit and counters again.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    Accept,
+    Counter(ChargeCounter),
+}
+
+/// # Description
+/// The `Negotiator` haggles over the price of the offer the vehicle selected, instead of accepting it right away.<br>
+/// It is configured on the `CONFIG_VEHICLE_NEGOTIATION` topic, without it the vehicle accepts offers as they are.
+///
+/// # Fields
+/// - `enabled`: Whether the vehicle counters offers at all.
+/// - `target_discount`: The share (0.0 to 1.0) the first counter offer is below the price of the offer.
+/// - `concession`: The share (0.0 to 1.0) of the gap between its bid and the revised price the vehicle gives in per round.
+/// - `max_rounds`: The round after which the vehicle takes the revised offer whatever its price.
+/// - `open`: The negotiation that waits for the answer of the charger, if any.
+/// - `savings`: What the vehicle saved by negotiating since the start, compared to the original offers, in €.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Negotiator {
+    pub enabled: bool,
+    pub target_discount: f64,
+    pub concession: f64,
+    pub max_rounds: u32,
+    #[serde(skip)]
+    pub open: Option<Negotiation>,
+    #[serde(skip)]
+    pub savings: f64,
+}
+
+impl Default for Negotiator {
+    fn default() -> Self {
+        Negotiator {
+            enabled: false,
+            target_discount: 0.2,
+            concession: 0.5,
+            max_rounds: 3,
+            open: None,
+            savings: 0.0,
+        }
+    }
+}
+
+impl Negotiator {
+    /// # Description
+    /// Opens a negotiation about an offer, if negotiating is enabled.
+    ///
+    /// # Arguments
+    /// - `offer`: The offer the vehicle selected.
+    ///
+    /// # Returns
+    /// The first counter offer to send to the charger, or `None` if the vehicle accepts the offer as it is.
+    pub fn open(&mut self, offer: &ChargeOffer) -> Option<ChargeCounter> {
+        if !self.enabled {
+            return None;
+        }
+        let bid = offer.charge_price * (1.0 - self.target_discount);
+        self.open = Some(Negotiation {
+            charger_name: offer.charger_name.clone(),
+            bid,
+            round: 1,
+        });
+        Some(ChargeCounter {
+            charger_name: offer.charger_name.clone(),
+            vehicle_name: offer.vehicle_name.clone(),
+            charge_price: bid,
+            charge_amount: offer.charge_amount,
+            round: 1,
+            is_final: false,
+        })
+    }
+
+    /// # Description
+    /// Answers a revised offer of the charger the vehicle negotiates with.<br>
+    /// The vehicle accepts if the charger met its bid, made its final offer or the round limit is reached.
+    /// Otherwise it raises its bid part of the way to the revised price.
+    ///
+    /// # Arguments
+    /// - `revision`: The revised offer of the charger.
+    ///
+    /// # Returns
+    /// The reply, or `None` if the revision doesn't belong to the open negotiation.
+    pub fn answer(&mut self, revision: &ChargeCounter) -> Option<Reply> {
+        let negotiation = self.open.as_mut()
+            .filter(|n| n.charger_name == revision.charger_name && n.round == revision.round)?;
+
+        if revision.is_final || revision.charge_price <= negotiation.bid || revision.round >= self.max_rounds {
+            self.open = None;
+            return Some(Reply::Accept);
+        }
+        negotiation.bid += self.concession * (revision.charge_price - negotiation.bid);
+        negotiation.round += 1;
+        Some(Reply::Counter(ChargeCounter {
+            charger_name: revision.charger_name.clone(),
+            vehicle_name: revision.vehicle_name.clone(),
+            charge_price: negotiation.bid,
+            charge_amount: revision.charge_amount,
+            round: negotiation.round,
+            is_final: false,
+        }))
+    }
+}
+
+/// # Description
+/// Sends a counter offer to the charger.
+///
+/// # Arguments
+/// - `handler`: The vehicle handler.
+/// - `counter`: The counter offer.
+pub async fn send_counter(handler: &mut VehicleHandler, counter: ChargeCounter) {
+    debug!("Countering the offer of {} with {:.3}€ in round {}", counter.charger_name, counter.charge_price, counter.round);
+    handler
+        .client
+        .publish(CHARGER_COUNTER, QoS::ExactlyOnce, false, counter.to_bytes())
+        .await
+        .unwrap();
+}
+
+/// # Description
+/// Handles a revised offer of the charger the vehicle negotiates with.<br>
+/// If the vehicle accepts, the agreed price replaces the price of the offer and the acceptance is binding.
+/// The savings compared to the original offer are added up.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+/// - `payload`: The payload containing the revised offer.
+pub async fn revise_handler(handler: SharedVehicle, payload: Bytes) {
+    let revision = ChargeCounter::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+    if revision.vehicle_name != *handler.vehicle.get_name() {
+        return;
+    }
+    debug!("Revised offer: {:?}", revision);
+
+    match handler.negotiator.answer(&revision) {
+        Some(Reply::Accept) => {
+            let Some(mut offer) = handler.charge_offers.iter().find(|o| o.charger_name == revision.charger_name).cloned() else {
+                warn!("Agreed with {}, but its offer is gone", revision.charger_name);
+                return;
+            };
+            let saved = (offer.charge_price - revision.charge_price) * revision.charge_amount as f64;
+            info!(
+                "Agreed with {} on {:.3}€ instead of {:.3}€ after {} rounds, saving {:.2}€",
+                revision.charger_name, revision.charge_price, offer.charge_price, revision.round, saved
+            );
+            handler.negotiator.savings += saved;
+            offer.charge_price = revision.charge_price;
+            offer.charge_amount = revision.charge_amount;
+            commit_to_offer(&mut handler, offer).await;
+        }
+        Some(Reply::Counter(counter)) => send_counter(&mut handler, counter).await,
+        None => debug!("No open negotiation with {} in round {}", revision.charger_name, revision.round),
+    }
+}
+
+/// # Description
+/// The `negotiation_handler` function processes incoming negotiation configurations.<br>
+/// It is called when a message is received on the `CONFIG_VEHICLE_NEGOTIATION` topic.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler, which contains the vehicle instance.
+/// - `payload`: The incoming payload containing the negotiation configuration in JSON format.
+pub async fn negotiation_handler(handler: SharedVehicle, payload: Bytes) {
+    let config: Negotiator = match serde_json::from_slice(&payload) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid negotiation configuration: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    debug!("{} negotiation set to: {:?}", handler.vehicle.get_name(), config);
+    handler.negotiator = Negotiator {
+        open: handler.negotiator.open.take(),
+        savings: handler.negotiator.savings,
+        ..config
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use powercable::{
+        charger::{ConnectorType, ReservationTerms},
+        Position,
+    };
+
+    fn negotiator() -> Negotiator {
+        Negotiator { enabled: true, ..Negotiator::default() }
+    }
+
+    fn offer() -> ChargeOffer {
+        let terms = ReservationTerms { reservation_ticks: 4, no_show_fee: 0.0, cancellation_fee: 0.0 };
+        ChargeOffer::new("Charger".to_string(), "Vehicle".to_string(), 0.5, 20, Position::new(52.52, 13.40), terms, ConnectorType::Ccs, 50.0, 0)
+    }
+
+    fn revision(price: f64, round: u32, is_final: bool) -> ChargeCounter {
+        ChargeCounter {
+            charger_name: "Charger".to_string(),
+            vehicle_name: "Vehicle".to_string(),
+            charge_price: price,
+            charge_amount: 20,
+            round,
+            is_final,
+        }
+    }
+
+    #[test]
+    fn disabled_negotiator_accepts_offers() {
+        let mut negotiator = Negotiator::default();
+        assert!(negotiator.open(&offer()).is_none());
+        assert!(negotiator.open.is_none());
+    }
+
+    #[test]
+    fn first_bid_is_below_the_offer() {
+        let mut negotiator = negotiator();
+        let counter = negotiator.open(&offer()).unwrap();
+        assert!((counter.charge_price - 0.4).abs() < 1e-9);
+        assert_eq!(counter.round, 1);
+        assert_eq!(negotiator.open.as_ref().unwrap().charger_name, "Charger");
+    }
+
+    #[test]
+    fn raises_bid_until_charger_gives_in() {
+        let mut negotiator = negotiator();
+        negotiator.open(&offer());
+
+        let Some(Reply::Counter(counter)) = negotiator.answer(&revision(0.46, 1, false)) else {
+            panic!("expected a counter offer");
+        };
+        assert!((counter.charge_price - 0.43).abs() < 1e-9);
+        assert_eq!(counter.round, 2);
+
+        assert_eq!(negotiator.answer(&revision(0.43, 2, false)), Some(Reply::Accept));
+        assert!(negotiator.open.is_none());
+    }
+
+    #[test]
+    fn accepts_final_offer_and_round_limit() {
+        let mut negotiator = negotiator();
+        negotiator.open(&offer());
+        assert_eq!(negotiator.answer(&revision(0.48, 1, true)), Some(Reply::Accept));
+
+        let mut negotiator = Negotiator { max_rounds: 1, ..negotiator };
+        negotiator.open(&offer());
+        assert_eq!(negotiator.answer(&revision(0.48, 1, false)), Some(Reply::Accept));
+    }
+
+    #[test]
+    fn ignores_other_revisions() {
+        let mut negotiator = negotiator();
+        assert!(negotiator.answer(&revision(0.45, 1, false)).is_none());
+
+        negotiator.open(&offer());
+        assert!(negotiator.answer(&revision(0.45, 2, false)).is_none());
+        let mut other = revision(0.45, 1, false);
+        other.charger_name = "Other".to_string();
+        assert!(negotiator.answer(&other).is_none());
+        assert!(negotiator.open.is_some());
+    }
+}
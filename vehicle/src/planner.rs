@@ -0,0 +1,242 @@
+use powercable::{charger::ChargeOffer, tickgen::{hour_of_day, PHASE_AS_MIN, TICK_AS_HOUR, TICK_AS_MIN}};
+use tracing::trace;
+
+use crate::{battery::Battery, vehicle::{Vehicle, VehicleDeadline}};
+
+/// The deadline counts down every phase, charging happens once per tick
+const PHASES_PER_TICK: i64 = (TICK_AS_MIN / PHASE_AS_MIN) as i64;
+/// Phases kept in reserve for the way to the charger and waiting for a free port
+const PLANNING_BUFFER_PHASES: i64 = 24;
+/// How much a newly seen price changes the forecast of its hour
+const FORECAST_SMOOTHING: f64 = 0.2;
+/// Upper bound of the charging time simulation in ticks, in case the battery barely takes any energy
+const MAX_CHARGE_TICKS: i64 = 24 * 4;
+
+/// # Description
+/// The `ChargePlanner` decides when a vehicle with a deadline should charge.<br>
+/// It learns the usual price per kWh for every hour of the day from the charge offers it sees,
+/// and starts charging in the cheapest window that still reaches the target state of charge in time.
+///
+/// # Fields
+/// - `hourly_prices`: The forecast price per kWh for every hour of the day, `None` if no offer was seen in that hour yet.
+/// - `charging_for_deadline`: Whether the vehicle is currently searching or charging because of its deadline.
+#[derive(Debug, Clone, Default)]
+pub struct ChargePlanner {
+    hourly_prices: [Option<f64>; 24],
+    pub charging_for_deadline: bool,
+}
+
+impl ChargePlanner {
+    /// # Description
+    /// Adds a seen price to the forecast of its hour, as an exponential moving average.
+    ///
+    /// # Arguments
+    /// - `timestamp`: The timestamp of the tick the price was seen in.
+    /// - `price`: The price per kWh.
+    pub fn observe_price(&mut self, timestamp: usize, price: f64) {
+        let forecast = &mut self.hourly_prices[hour_of_day(timestamp)];
+        *forecast = Some(match *forecast {
+            Some(old) => old + FORECAST_SMOOTHING * (price - old),
+            None => price,
+        });
+    }
+
+    /// # Returns
+    /// The expected price per kWh at the given timestamp, `None` if nothing is known about that hour.
+    pub fn forecast(&self, timestamp: usize) -> Option<f64> {
+        self.hourly_prices[hour_of_day(timestamp)]
+    }
+
+    /// # Description
+    /// Decides whether the vehicle should start looking for a charger now to meet its deadline.<br>
+    /// Every possible start until the latest one that still reaches the target is rated by the average forecast
+    /// price during the charging. The vehicle charges now, if now is the cheapest start or the latest possible one.
+    /// Without any forecast, it waits for the latest start.
+    ///
+    /// # Arguments
+    /// - `battery`: The battery of the vehicle.
+    /// - `deadline`: The deadline of the vehicle.
+    /// - `timestamp`: The timestamp of the current tick in milliseconds.
+    ///
+    /// # Returns
+    /// `true` if the vehicle should charge now.
+    pub fn should_charge_now(&self, battery: &Battery, deadline: VehicleDeadline, timestamp: usize) -> bool {
+        if battery.get_soc() >= deadline.target_soc {
+            return false;
+        }
+
+        let charge_ticks = ticks_to_charge(battery, deadline.target_soc, None);
+        let latest_start = (deadline.ticks_remaining - PLANNING_BUFFER_PHASES) / PHASES_PER_TICK - charge_ticks;
+        if latest_start <= 0 {
+            return true;
+        }
+
+        let tick_ms = (TICK_AS_MIN * 60 * 1000) as i64;
+        let window_price = |start: i64| -> Option<f64> {
+            let prices: Vec<f64> = (start..start + charge_ticks.max(1))
+                .filter_map(|t| self.forecast((timestamp as i64 + t * tick_ms) as usize))
+                .collect();
+            (!prices.is_empty()).then(|| prices.iter().sum::<f64>() / prices.len() as f64)
+        };
+
+        let best_start = (0..=latest_start)
+            .filter_map(|start| window_price(start).map(|price| (start, price)))
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        trace!("Charging takes {} ticks, latest start in {} ticks, best start {:?}", charge_ticks, latest_start, best_start);
+
+        best_start.is_some_and(|(start, _)| start == 0)
+    }
+}
+
+/// # Description
+/// Simulates charging the battery along its charging curve.
+///
+/// # Arguments
+/// - `battery`: The battery of the vehicle.
+/// - `target_soc`: The state of charge to reach.
+/// - `charge_power`: The power of the charger in kW, the battery's maximum if `None`.
+///
+/// # Returns
+/// The number of ticks needed to reach the target state of charge.
+pub fn ticks_to_charge(battery: &Battery, target_soc: f64, charge_power: Option<f64>) -> i64 {
+    let power = charge_power
+        .unwrap_or(f64::MAX)
+        .min(battery.get_max_charge_rate() as f64);
+    let per_tick = (power * TICK_AS_HOUR) as usize;
+
+    let mut battery = battery.clone();
+    let mut ticks = 0;
+    while battery.get_soc() < target_soc && ticks < MAX_CHARGE_TICKS {
+        if battery.add_charge(per_tick) == 0 {
+            return MAX_CHARGE_TICKS;
+        }
+        ticks += 1;
+    }
+    ticks
+}
+
+/// # Description
+/// Selects the charge offer that meets the deadline at the lowest cost.<br>
+/// An offer meets the deadline if driving there and charging to the target takes less time than is left.
+/// If no offer meets it, the one that reaches the target the soonest is returned.
+///
+/// # Arguments
+/// - `offers`: A slice of `ChargeOffer` instances representing the available charge offers.
+/// - `vehicle`: The vehicle for which the charge offer is being selected.
+///
+/// # Returns
+/// An `Option<ChargeOffer>` containing the selected charge offer, or `None` if no offers are available.
+pub fn get_deadline_offer(offers: &[ChargeOffer], vehicle: &Vehicle) -> Option<ChargeOffer> {
+    let deadline = vehicle.get_deadline();
+    let battery = vehicle.battery_non_mut();
+    let needed = battery.get_free_capacity();
+
+    let phases_until_charged = |offer: &ChargeOffer| -> i64 {
+        let drive_hours = vehicle.distance_to(offer.charger_position) / vehicle.planned_speed() as f64;
+        let drive_phases = (drive_hours / TICK_AS_HOUR).ceil() as i64 * PHASES_PER_TICK;
+        drive_phases + ticks_to_charge(battery, deadline.target_soc, Some(offer.charge_power)) * PHASES_PER_TICK
+    };
+    let cost = |offer: &ChargeOffer| -> f64 {
+        let energy_for_way = vehicle.distance_to(offer.charger_position) * vehicle.get_consumption() / 100.0;
+        (offer.charge_price + battery.wear_cost_per_kwh(offer.charge_power)) * (needed + energy_for_way)
+    };
+
+    offers.iter()
+        .filter(|offer| phases_until_charged(offer) <= deadline.ticks_remaining)
+        .min_by(|a, b| cost(a).total_cmp(&cost(b)))
+        .or_else(|| offers.iter().min_by_key(|offer| phases_until_charged(offer)))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use powercable::{
+        charger::{ConnectorType, ReservationTerms},
+        Position,
+    };
+
+    const HOUR_MS: usize = 3_600_000;
+    const TERMS: ReservationTerms = ReservationTerms { reservation_ticks: 4, no_show_fee: 0.0, cancellation_fee: 0.0 };
+
+    fn deadline(ticks: i64) -> VehicleDeadline {
+        VehicleDeadline { ticks_remaining: PLANNING_BUFFER_PHASES + ticks * PHASES_PER_TICK, target_soc: 0.8 }
+    }
+
+    fn offer(charger: &str, latitude: f64, price: f64, power: f64) -> ChargeOffer {
+        ChargeOffer::new(charger.to_string(), "vehicle".to_string(), price, 30, Position::new(latitude, 13.40), TERMS, ConnectorType::Ccs, power, 0)
+    }
+
+    /// A vehicle at 30% state of charge with the given deadline in phases
+    fn vehicle(ticks_remaining: i64) -> Vehicle {
+        let mut vehicle = Vehicle::new("vehicle".to_string(), Position::new(52.50, 13.40), 42);
+        let battery = vehicle.battery();
+        let excess = battery.get_level() - battery.get_max_capacity() * 0.3;
+        let efficiency = battery.discharge_efficiency();
+        battery.remove_charge(excess * efficiency);
+        vehicle.set_deadline(VehicleDeadline { ticks_remaining, target_soc: 0.8 });
+        vehicle
+    }
+
+    #[test]
+    fn forecast_follows_seen_prices() {
+        let mut planner = ChargePlanner::default();
+        assert_eq!(planner.forecast(0), None);
+
+        planner.observe_price(0, 0.5);
+        planner.observe_price(HOUR_MS / 2, 0.3);
+        assert!((planner.forecast(0).unwrap() - 0.46).abs() < 1e-9);
+        assert_eq!(planner.forecast(HOUR_MS), None);
+        assert_eq!(planner.forecast(24 * HOUR_MS), planner.forecast(0)); // the next day
+    }
+
+    #[test]
+    fn slower_charger_takes_longer() {
+        let battery = Battery::new(60.0, 0.2, 150, Vec::new());
+        let fast = ticks_to_charge(&battery, 0.8, None);
+        let slow = ticks_to_charge(&battery, 0.8, Some(22.0));
+        assert!(fast >= 1);
+        assert!(slow > fast);
+        assert_eq!(ticks_to_charge(&battery, 0.1, None), 0);
+    }
+
+    #[test]
+    fn charges_in_the_cheapest_window() {
+        let battery = Battery::new(60.0, 0.2, 150, Vec::new());
+
+        let mut cheap_now = ChargePlanner::default();
+        cheap_now.observe_price(0, 0.2);
+        cheap_now.observe_price(HOUR_MS, 0.5);
+        assert!(cheap_now.should_charge_now(&battery, deadline(10), 0));
+
+        let mut cheap_later = ChargePlanner::default();
+        cheap_later.observe_price(0, 0.5);
+        cheap_later.observe_price(HOUR_MS, 0.2);
+        assert!(!cheap_later.should_charge_now(&battery, deadline(10), 0));
+    }
+
+    #[test]
+    fn waits_for_latest_start_without_forecast() {
+        let battery = Battery::new(60.0, 0.2, 150, Vec::new());
+        let planner = ChargePlanner::default();
+
+        assert!(!planner.should_charge_now(&battery, deadline(10), 0));
+        assert!(planner.should_charge_now(&battery, deadline(1), 0));
+        assert!(!planner.should_charge_now(&Battery::new(60.0, 0.9, 150, Vec::new()), deadline(1), 0));
+    }
+
+    #[test]
+    fn deadline_offer_prefers_cheap_if_in_time() {
+        // The far charger is about 100 km away
+        let offers = [offer("near", 52.50, 0.6, 150.0), offer("far", 53.40, 0.1, 150.0)];
+
+        let relaxed = get_deadline_offer(&offers, &vehicle(24 * 12)).unwrap();
+        assert_eq!(relaxed.charger_name, "far");
+
+        let hurried = get_deadline_offer(&offers, &vehicle(4 * PHASES_PER_TICK)).unwrap();
+        assert_eq!(hurried.charger_name, "near");
+
+        let late = get_deadline_offer(&offers, &vehicle(0)).unwrap();
+        assert_eq!(late.charger_name, "near");
+    }
+}
@@ -0,0 +1,128 @@
+use bytes::Bytes;
+use powercable::{rescue::{RescueReason, RescueRequest, RescueTow}, RESCUE_CANCEL_TOPIC, RESCUE_REQUEST_TOPIC};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rumqttc::QoS;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use crate::{vehicle::VehicleStatus, SharedVehicle, VehicleHandler};
+
+/// # Description
+/// The failure model of the vehicles, configured on the `CONFIG_VEHICLE_FAILURE` topic.
+///
+/// # Fields
+/// - `breakdown_rate`: The chance of a mechanical failure in every tick the vehicle is driving, 0.0 disables failures.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct FailurePolicy {
+    pub breakdown_rate: f64,
+}
+
+impl FailurePolicy {
+    /// # Description
+    /// Rolls for a mechanical failure.
+    ///
+    /// # Arguments
+    /// - `seed`: A fresh seed of the vehicle.
+    ///
+    /// # Returns
+    /// `true` if the vehicle breaks down in this tick.
+    pub fn breaks_down(&self, seed: u64) -> bool {
+        let mut rng = StdRng::seed_from_u64(seed);
+        rng.random_bool(self.breakdown_rate.clamp(0.0, 1.0))
+    }
+}
+
+/// # Description
+/// Asks the rescue service to tow the vehicle to a charger.<br>
+/// The request is only sent once, until the vehicle is towed or gets going again on its own.
+///
+/// # Arguments
+/// - `handler`: The vehicle handler, its vehicle must be stranded or broken.
+pub async fn request_rescue(handler: &mut VehicleHandler) {
+    if handler.rescue_requested {
+        return;
+    }
+    let reason = match handler.vehicle.get_status() {
+        VehicleStatus::Broken => RescueReason::Broken,
+        _ => RescueReason::Stranded,
+    };
+    let request = RescueRequest {
+        vehicle_name: handler.vehicle.get_name(),
+        position: handler.vehicle.get_location(),
+        reason,
+    };
+    info!("{} requests a rescue: {:?}", request.vehicle_name, reason);
+
+    handler.rescue_requested = true;
+    handler
+        .client
+        .publish(RESCUE_REQUEST_TOPIC, QoS::ExactlyOnce, false, request.to_bytes())
+        .await
+        .unwrap();
+}
+
+/// # Description
+/// Withdraws the rescue request of a vehicle that found a charger in range on its own.
+///
+/// # Arguments
+/// - `handler`: The vehicle handler.
+pub async fn cancel_rescue(handler: &mut VehicleHandler) {
+    if !handler.rescue_requested {
+        return;
+    }
+    info!("{} doesn't need a rescue anymore", handler.vehicle.get_name());
+
+    handler.rescue_requested = false;
+    handler
+        .client
+        .publish(RESCUE_CANCEL_TOPIC, QoS::ExactlyOnce, false, handler.vehicle.get_name())
+        .await
+        .unwrap();
+}
+
+/// # Description
+/// Handles a vehicle dropped off at a charger by the rescue service.<br>
+/// A broken vehicle is repaired on the way. The vehicle parks at the charger and searches for a charger
+/// on the next tick if its battery is low.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler.
+/// - `payload`: The payload of the message received on the `RESCUE_TOW_TOPIC` topic.
+pub async fn tow_handler(handler: SharedVehicle, payload: Bytes) {
+    let tow = RescueTow::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+    if tow.vehicle_name != handler.vehicle.get_name() {
+        return;
+    }
+    if !handler.rescue_requested {
+        debug!("{} didn't wait for the rescue, ignoring tow to {}", tow.vehicle_name, tow.charger_name);
+        return;
+    }
+
+    info!("{} was towed to {} after {} ticks", tow.vehicle_name, tow.charger_name, tow.rescue_ticks);
+    handler.vehicle.tow_to(tow.position);
+    handler.vehicle.set_status(VehicleStatus::Parked);
+    handler.rescue_requested = false;
+    handler.stranded_ticks = 0;
+}
+
+/// # Description
+/// The `failure_handler` function processes the failure model of the vehicles.<br>
+/// It is called when a message is received on the `CONFIG_VEHICLE_FAILURE` topic.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler.
+/// - `payload`: The incoming payload containing the failure model in JSON format.
+pub async fn failure_handler(handler: SharedVehicle, payload: Bytes) {
+    let policy: FailurePolicy = match serde_json::from_slice(&payload) {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Invalid failure policy: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    debug!("{} failure policy set to: {:?}", handler.vehicle.get_name(), policy);
+    handler.failure = policy;
+}
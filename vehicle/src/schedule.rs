@@ -0,0 +1,275 @@
+use bytes::Bytes;
+use powercable::{generate_rnd_pos, tickgen::minute_of_day, Position};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::SharedVehicle;
+
+/// Kilometers per degree of latitude
+const KM_PER_DEGREE: f64 = 111.0;
+/// Farthest distance between home and work of a commuter in km
+const MAX_COMMUTE_KM: f64 = 40.0;
+/// Farthest distance of a weekend trip from home in km
+const MAX_LEISURE_KM: f64 = 25.0;
+/// Farthest distance of a taxi or delivery stop from home in km
+const MAX_SERVICE_KM: f64 = 15.0;
+
+/// # Description
+/// The `TripProfile` enum defines how a vehicle chooses its next trip after reaching its destination.
+///
+/// # Variants
+/// - `Random`: The vehicle parks for about three hours and then drives to a random position.
+/// - `Commuter`: On weekdays the vehicle drives from home to work between 7 and 9 and back between 16 and 18.
+///   On weekends it makes leisure trips around home during the day.
+/// - `Taxi`: Between 6 and 22 the vehicle makes many short trips around home, at night it waits at home.
+/// - `Delivery`: On weekdays between 8 and 18 the vehicle drives from stop to stop around its depot at home.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TripProfile {
+    Random,
+    Commuter,
+    Taxi,
+    Delivery,
+}
+
+/// # Description
+/// Returns whether the timestamp of the tick generator is on a Saturday or Sunday.
+///
+/// # Arguments
+/// - `timestamp`: The timestamp in milliseconds since 1970-01-01, which was a Thursday.
+fn is_weekend(timestamp: usize) -> bool {
+    let weekday = (timestamp / 86_400_000 + 3) % 7; // 0 is Monday
+    weekday >= 5
+}
+
+/// # Description
+/// Generates a random position around a center.
+///
+/// # Arguments
+/// - `center`: The center of the area.
+/// - `max_km`: The farthest distance from the center in km.
+/// - `seed`: A seed for the random number generator.
+fn generate_pos_near(center: Position, max_km: f64, seed: u64) -> Position {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let distance = rng.random_range(0.0..max_km);
+    let angle = rng.random_range(0.0..std::f64::consts::TAU);
+    Position::new(
+        center.latitude + distance * angle.cos() / KM_PER_DEGREE,
+        center.longitude + distance * angle.sin() / (KM_PER_DEGREE * center.latitude.to_radians().cos()),
+    )
+}
+
+/// # Description
+/// The `Schedule` decides where a vehicle drives next, following the daily routine of its trip profile.
+///
+/// # Fields
+/// - `profile`: The trip profile of the vehicle.
+/// - `home`: Where the vehicle lives, also the depot of delivery vehicles.
+/// - `work`: Where a commuter works.
+/// - `departure_minute`: The minute of the day a commuter leaves home, between 7:00 and 9:00.
+/// - `return_minute`: The minute of the day a commuter leaves work, between 16:00 and 18:00.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Schedule {
+    pub profile: TripProfile,
+    pub home: Position,
+    pub work: Position,
+    departure_minute: usize,
+    return_minute: usize,
+}
+
+impl Schedule {
+    /// # Description
+    /// Creates the schedule of a vehicle, with a profile, workplace and commuting times depending on the seed.<br>
+    /// Most vehicles are commuters, the others are split between taxi, delivery and random trips.
+    ///
+    /// # Arguments
+    /// - `home`: Where the vehicle lives.
+    /// - `seed`: The seed of the vehicle.
+    pub fn new(home: Position, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let profile = match rng.random_range(0..10) {
+            0 => TripProfile::Taxi,
+            1 => TripProfile::Delivery,
+            2 => TripProfile::Random,
+            _ => TripProfile::Commuter,
+        };
+        Schedule {
+            profile,
+            home,
+            work: generate_pos_near(home, MAX_COMMUTE_KM, rng.random()),
+            departure_minute: 7 * 60 + rng.random_range(0..120),
+            return_minute: 16 * 60 + rng.random_range(0..120),
+        }
+    }
+
+    /// # Description
+    /// Decides the next trip of a vehicle that reached its destination.
+    ///
+    /// # Arguments
+    /// - `location`: The current location of the vehicle.
+    /// - `timestamp`: The timestamp of the current tick in milliseconds.
+    /// - `seed`: A fresh seed of the vehicle.
+    ///
+    /// # Returns
+    /// The destination of the next trip, or `None` if the vehicle stays parked.
+    pub fn next_trip(&self, location: Position, timestamp: usize, seed: u64) -> Option<Position> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let minute = minute_of_day(timestamp);
+        let hour = minute / 60;
+        let at_home = location.is_near(self.home);
+
+        match self.profile {
+            TripProfile::Random => {
+                // Average parking time is 3 hours (made up)
+                (rng.random_range(0..11) == 0).then(|| generate_rnd_pos(seed))
+            }
+            TripProfile::Commuter if !is_weekend(timestamp) => {
+                let at_work = location.is_near(self.work);
+                if at_home && (self.departure_minute..12 * 60).contains(&minute) {
+                    Some(self.work)
+                } else if (at_work && minute >= self.return_minute) || (!at_home && !at_work) {
+                    Some(self.home)
+                } else {
+                    None
+                }
+            }
+            TripProfile::Commuter => {
+                if at_home {
+                    // About one trip every four hours during the day
+                    ((10..18).contains(&hour) && rng.random_range(0..16) == 0)
+                        .then(|| generate_pos_near(self.home, MAX_LEISURE_KM, seed))
+                } else {
+                    // Stay for about two hours, but be home for the night
+                    (hour >= 20 || rng.random_range(0..8) == 0).then_some(self.home)
+                }
+            }
+            TripProfile::Taxi => {
+                if (6..22).contains(&hour) {
+                    (rng.random_range(0..2) == 0).then(|| generate_pos_near(self.home, MAX_SERVICE_KM, seed))
+                } else {
+                    (!at_home).then_some(self.home)
+                }
+            }
+            TripProfile::Delivery => {
+                if !is_weekend(timestamp) && (8..18).contains(&hour) {
+                    (rng.random_range(0..2) == 0).then(|| generate_pos_near(self.home, MAX_SERVICE_KM, seed))
+                } else {
+                    (!at_home).then_some(self.home)
+                }
+            }
+        }
+    }
+}
+
+/// # Description
+/// A change of the schedule, only the given fields are changed.
+///
+/// # Fields
+/// - `profile`: The new trip profile.
+/// - `home`: The new home location.
+/// - `work`: The new work location.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    pub profile: Option<TripProfile>,
+    pub home: Option<Position>,
+    pub work: Option<Position>,
+}
+
+/// # Description
+/// The `schedule_handler` function processes incoming schedule configurations.<br>
+/// It is called when a message is received on the `CONFIG_VEHICLE_SCHEDULE` topic, or on the topic of this vehicle
+/// `CONFIG_VEHICLE_SCHEDULE/<vehicle name>`. A schedule for this vehicle takes precedence over the one for all vehicles.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler, which contains the vehicle instance.
+/// - `payload`: The incoming payload containing the schedule configuration in JSON format.
+/// - `own`: Whether the configuration is meant for this vehicle only.
+pub async fn schedule_handler(handler: SharedVehicle, payload: Bytes, own: bool) {
+    let config: ScheduleConfig = match serde_json::from_slice(&payload) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid schedule configuration: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    if handler.has_own_schedule && !own {
+        debug!("{} keeps its own schedule, ignoring {:?}", handler.vehicle.get_name(), config);
+        return;
+    }
+
+    if let Some(profile) = config.profile {
+        handler.schedule.profile = profile;
+    }
+    if let Some(home) = config.home {
+        handler.schedule.home = home;
+    }
+    if let Some(work) = config.work {
+        handler.schedule.work = work;
+    }
+    handler.has_own_schedule = own;
+    debug!("{} schedule set to: {:?}", handler.vehicle.get_name(), handler.schedule);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: usize = 3_600_000;
+    /// 1970-01-05, a Monday
+    const MONDAY: usize = 4 * 24 * HOUR_MS;
+    /// 1970-01-10, a Saturday
+    const SATURDAY: usize = 9 * 24 * HOUR_MS;
+
+    fn schedule(profile: TripProfile) -> Schedule {
+        Schedule { profile, ..Schedule::new(Position::new(52.52, 13.40), 7) }
+    }
+
+    #[test]
+    fn knows_weekdays_and_minutes() {
+        assert!(!is_weekend(MONDAY));
+        assert!(!is_weekend(MONDAY + 4 * 24 * HOUR_MS)); // Friday
+        assert!(is_weekend(SATURDAY));
+        assert!(is_weekend(SATURDAY + 24 * HOUR_MS));
+        assert_eq!(minute_of_day(MONDAY + 9 * HOUR_MS + 30 * 60_000), 9 * 60 + 30);
+    }
+
+    #[test]
+    fn positions_stay_near_center() {
+        let center = Position::new(52.52, 13.40);
+        for seed in 0..100 {
+            assert!(center.distance_to(generate_pos_near(center, MAX_SERVICE_KM, seed)) <= MAX_SERVICE_KM + 0.5);
+        }
+    }
+
+    #[test]
+    fn commuter_drives_to_work_and_back() {
+        let schedule = schedule(TripProfile::Commuter);
+        let (home, work) = (schedule.home, schedule.work);
+
+        assert_eq!(schedule.next_trip(home, MONDAY + 9 * HOUR_MS + HOUR_MS / 2, 1), Some(work));
+        assert_eq!(schedule.next_trip(home, MONDAY + 5 * HOUR_MS, 1), None);
+        assert_eq!(schedule.next_trip(work, MONDAY + 12 * HOUR_MS, 1), None);
+        assert_eq!(schedule.next_trip(work, MONDAY + 18 * HOUR_MS + HOUR_MS / 2, 1), Some(home));
+    }
+
+    #[test]
+    fn everyone_goes_home_for_the_night() {
+        let away = Position::new(52.60, 13.50);
+        for profile in [TripProfile::Commuter, TripProfile::Taxi, TripProfile::Delivery] {
+            let schedule = schedule(profile);
+            assert_eq!(schedule.next_trip(away, SATURDAY + 23 * HOUR_MS, 1), Some(schedule.home));
+        }
+        assert_eq!(schedule(TripProfile::Taxi).next_trip(Position::new(52.52, 13.40), MONDAY + 2 * HOUR_MS, 1), None);
+    }
+
+    #[test]
+    fn delivery_rests_on_weekends() {
+        let schedule = schedule(TripProfile::Delivery);
+        for seed in 0..20 {
+            assert_eq!(schedule.next_trip(schedule.home, SATURDAY + 10 * HOUR_MS, seed), None);
+        }
+    }
+}
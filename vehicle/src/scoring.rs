@@ -0,0 +1,207 @@
+use bytes::Bytes;
+use powercable::{charger::ChargeOffer, routing::road_distance};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::{vehicle::Vehicle, SharedVehicle};
+
+/// # Description
+/// The weights of the criteria the `Scored` algorithm rates charge offers with.<br>
+/// Only the ratio of the weights matters, a weight of 0.0 ignores the criterion.
+/// They can be configured on the `CONFIG_VEHICLE_SCORING` topic for all vehicles or on `CONFIG_VEHICLE_SCORING/<vehicle name>` for one.
+///
+/// # Fields
+/// - `price`: Weight of the price per kWh including the wear of the battery, cheaper is better.
+/// - `detour`: Weight of the detour time to the charger on the way to the destination, shorter is better.
+/// - `amount`: Weight of the share of the needed energy the charger offers, more is better.
+/// - `speed`: Weight of the charging power, faster is better.
+/// - `waiting`: Weight of the estimated waiting time at the charger, shorter is better.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ScoringWeights {
+    pub price: f64,
+    pub detour: f64,
+    pub amount: f64,
+    pub speed: f64,
+    pub waiting: f64,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        ScoringWeights {
+            price: 1.0,
+            detour: 1.0,
+            amount: 1.0,
+            speed: 0.5,
+            waiting: 0.5,
+        }
+    }
+}
+
+/// # Description
+/// The raw values of the criteria of one charge offer.
+#[derive(Debug, Clone, Copy)]
+struct Criteria {
+    price: f64,
+    detour: f64,
+    amount: f64,
+    speed: f64,
+    waiting: f64,
+}
+
+impl Criteria {
+    fn of(offer: &ChargeOffer, vehicle: &Vehicle) -> Self {
+        let destination = vehicle.get_destination();
+        let detour_km = vehicle.distance_to(offer.charger_position) + road_distance(offer.charger_position, destination)
+            - vehicle.distance_to(destination);
+        let needed = vehicle.battery_non_mut().get_free_capacity().max(1.0);
+
+        Criteria {
+            price: offer.charge_price + vehicle.battery_non_mut().wear_cost_per_kwh(offer.charge_power),
+            detour: detour_km.max(0.0) / vehicle.planned_speed() as f64,
+            amount: (offer.charge_amount as f64 / needed).min(1.0),
+            speed: offer.charge_power,
+            waiting: offer.estimated_wait_ticks as f64,
+        }
+    }
+}
+
+/// # Description
+/// Scales a value to 0.0 (worst) to 1.0 (best) between the worst and the best value of all offers.
+/// If all offers are equal in a criterion, it doesn't make a difference and all get 1.0.
+fn normalise(value: f64, min: f64, max: f64, higher_is_better: bool) -> f64 {
+    if max - min <= f64::EPSILON {
+        return 1.0;
+    }
+    let scaled = (value - min) / (max - min);
+    if higher_is_better { scaled } else { 1.0 - scaled }
+}
+
+/// # Description
+/// Selects the charge offer with the highest weighted score.<br>
+/// Each criterion is normalised over all offers, so criteria with different units can be weighted against each other.
+///
+/// # Arguments
+/// - `offers`: A slice of `ChargeOffer` instances representing the available charge offers.
+/// - `vehicle`: The vehicle for which the charge offer is being selected.
+/// - `weights`: The weights of the criteria.
+///
+/// # Returns
+/// An `Option<ChargeOffer>` containing the best scored offer, or `None` if no offers are available.
+pub fn get_scored_offer(offers: &[ChargeOffer], vehicle: &Vehicle, weights: ScoringWeights) -> Option<ChargeOffer> {
+    debug!("Selecting the best scored charge offer with {:?}", weights);
+    let rated: Vec<(&ChargeOffer, Criteria)> = offers.iter()
+        .map(|offer| (offer, Criteria::of(offer, vehicle)))
+        .collect();
+
+    let range = |criterion: fn(&Criteria) -> f64| -> (f64, f64) {
+        rated.iter().map(|(_, c)| criterion(c)).fold((f64::MAX, f64::MIN), |(min, max), v| (min.min(v), max.max(v)))
+    };
+    let (price, detour, amount, speed, waiting) = (
+        range(|c| c.price),
+        range(|c| c.detour),
+        range(|c| c.amount),
+        range(|c| c.speed),
+        range(|c| c.waiting),
+    );
+    let total_weight = (weights.price + weights.detour + weights.amount + weights.speed + weights.waiting).max(f64::EPSILON);
+
+    let score = |c: &Criteria| -> f64 {
+        (weights.price * normalise(c.price, price.0, price.1, false)
+            + weights.detour * normalise(c.detour, detour.0, detour.1, false)
+            + weights.amount * normalise(c.amount, amount.0, amount.1, true)
+            + weights.speed * normalise(c.speed, speed.0, speed.1, true)
+            + weights.waiting * normalise(c.waiting, waiting.0, waiting.1, false))
+            / total_weight
+    };
+
+    let res = rated.iter()
+        .map(|(offer, criteria)| (offer, score(criteria)))
+        .inspect(|(offer, score)| debug!("Score of {}: {:.3}", offer.charger_name, score))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(offer, _)| (*offer).clone());
+    debug!("Best scored offer: {:?}", res);
+    res
+}
+
+/// # Description
+/// The `scoring_handler` function processes incoming scoring weights for the `Scored` algorithm.<br>
+/// It is called when a message is received on the `CONFIG_VEHICLE_SCORING` topic, or on the topic of this vehicle
+/// `CONFIG_VEHICLE_SCORING/<vehicle name>`. Weights for this vehicle take precedence over the ones for all vehicles.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler, which contains the vehicle instance.
+/// - `payload`: The incoming payload containing the weights in JSON format.
+/// - `own`: Whether the weights are meant for this vehicle only.
+pub async fn scoring_handler(handler: SharedVehicle, payload: Bytes, own: bool) {
+    let weights: ScoringWeights = match serde_json::from_slice(&payload) {
+        Ok(weights) => weights,
+        Err(e) => {
+            warn!("Invalid scoring weights: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    if handler.has_own_scoring && !own {
+        debug!("{} keeps its own scoring weights, ignoring {:?}", handler.vehicle.get_name(), weights);
+        return;
+    }
+    debug!("{} scoring weights set to: {:?}", handler.vehicle.get_name(), weights);
+    handler.scoring = weights;
+    handler.has_own_scoring = own;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use powercable::{
+        charger::{ConnectorType, ReservationTerms},
+        Position,
+    };
+
+    const TERMS: ReservationTerms = ReservationTerms { reservation_ticks: 4, no_show_fee: 0.0, cancellation_fee: 0.0 };
+
+    fn vehicle() -> Vehicle {
+        let mut vehicle = Vehicle::new("vehicle".to_string(), Position::new(52.50, 13.40), 42);
+        vehicle.set_destination(Position::new(52.60, 13.40));
+        vehicle
+    }
+
+    fn offer(charger: &str, longitude: f64, price: f64, amount: usize) -> ChargeOffer {
+        ChargeOffer::new(charger.to_string(), "vehicle".to_string(), price, amount, Position::new(52.55, longitude), TERMS, ConnectorType::Ccs, 50.0, 0)
+    }
+
+    fn weights(price: f64, detour: f64, amount: f64) -> ScoringWeights {
+        ScoringWeights { price, detour, amount, speed: 0.0, waiting: 0.0 }
+    }
+
+    #[test]
+    fn normalise_scales_between_worst_and_best() {
+        assert_eq!(normalise(5.0, 0.0, 10.0, true), 0.5);
+        assert_eq!(normalise(2.0, 0.0, 10.0, false), 0.8);
+        assert_eq!(normalise(3.0, 3.0, 3.0, false), 1.0);
+    }
+
+    #[test]
+    fn weights_decide_between_price_and_detour() {
+        let offers = [offer("on_the_way", 13.40, 0.6, 10), offer("detour", 13.60, 0.3, 10)];
+        let vehicle = vehicle();
+
+        let cheapest = get_scored_offer(&offers, &vehicle, weights(1.0, 0.0, 0.0)).unwrap();
+        assert_eq!(cheapest.charger_name, "detour");
+        let closest = get_scored_offer(&offers, &vehicle, weights(0.0, 1.0, 0.0)).unwrap();
+        assert_eq!(closest.charger_name, "on_the_way");
+    }
+
+    #[test]
+    fn larger_offer_wins_on_amount() {
+        let offers = [offer("small", 13.40, 0.4, 5), offer("large", 13.40, 0.4, 30)];
+        let best = get_scored_offer(&offers, &vehicle(), weights(0.0, 0.0, 1.0)).unwrap();
+        assert_eq!(best.charger_name, "large");
+    }
+
+    #[test]
+    fn no_offers_no_choice() {
+        assert!(get_scored_offer(&[], &vehicle(), ScoringWeights::default()).is_none());
+    }
+}
@@ -1,12 +1,11 @@
 use bytes::Bytes;
-use rand::{rngs::StdRng, Rng, SeedableRng};
 use tracing::{debug, info, trace, warn};
-use powercable::{generate_rnd_pos, tickgen::{Phase, TickPayload}, POWER_LOCATION_TOPIC, VEHICLE_TOPIC};
+use powercable::{tickgen::{Phase, TickPayload}, weather::WeatherReport, ChartEntry, POWER_LOCATION_TOPIC, VEHICLE_DEADLINE_TOPIC, VEHICLE_STRANDED_TOPIC, VEHICLE_TOPIC};
 use rumqttc::QoS;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::task;
-use crate::{charger_handling::{accept_offer, create_charger_request, create_get}, vehicle::{VehicleAlgorithm, VehicleDeadline, VehicleStatus}, SharedVehicle};
+use crate::{fleet::publish_status, charger_handling::{accept_offer, cancel_reservation, create_charger_request, create_get, send_eta}, rescue::request_rescue, trip::TripPlanner, v2g::offer_v2g, vehicle::{VehicleAlgorithm, VehicleDeadline, VehicleStatus}, SharedVehicle};
 
 const FIND_CHARGER_AT_LEAST: f64 = 0.3; // 30% charge left
 
@@ -41,9 +40,15 @@ pub struct LocationPayload {
 /// - `payload`: The incoming payload containing the tick information in JSON format.
 pub async fn tick_handler(handler: SharedVehicle, payload: Bytes) {
     let payload: TickPayload = serde_json::from_slice(&payload).unwrap();
+    {
+        let mut handler = handler.lock().await;
+        handler.current_tick = payload.tick;
+        handler.current_timestamp = payload.timestamp;
+    }
     match payload.phase {
         Phase::Process => {
             process_tick(handler.clone()).await;
+            publish_status(handler.clone()).await;
         }
         Phase::Commerce => {
             commerce_tick(handler.clone()).await;
@@ -68,35 +73,77 @@ pub async fn process_tick(handler: SharedVehicle) { // TODO: rework this functio
     let mut locked_handler = handler.lock().await;
     //let seed = locked_handler.seed.clone();
 
-    if locked_handler.target_charger.is_none() {
+    let status = locked_handler.vehicle.get_status();
+    let failure = locked_handler.failure;
+    if status == VehicleStatus::Stranded || status == VehicleStatus::Broken { // Wait for the rescue service
+        if locked_handler.target_charger.is_some() {
+            task::spawn(cancel_reservation(handler.clone()));
+        }
+        request_rescue(&mut locked_handler).await;
+        if status == VehicleStatus::Stranded { // or a charger in range
+            locked_handler.stranded_ticks += 1;
+            let stranded = ChartEntry::new(
+                locked_handler.vehicle.get_name(),
+                locked_handler.stranded_ticks as isize,
+                locked_handler.current_timestamp,
+            );
+            locked_handler
+                .client
+                .publish(VEHICLE_STRANDED_TOPIC, QoS::ExactlyOnce, false, stranded.to_string())
+                .await
+                .unwrap();
+            // With an empty battery no charger is in range, only the rescue service helps
+            if locked_handler.vehicle.get_range() > 0.0 {
+                task::spawn(create_charger_request(handler.clone()));
+            }
+        }
+    } else if locked_handler.vehicle.get_speed() > 0 && failure.breakdown_rate > 0.0
+        && failure.breaks_down(locked_handler.vehicle.get_seed()) {
+        warn!("{} broke down", locked_handler.vehicle.get_name());
+        locked_handler.vehicle.set_status(VehicleStatus::Broken);
+    } else if locked_handler.target_charger.is_none() {
         if locked_handler.vehicle.battery().get_soc() <= FIND_CHARGER_AT_LEAST { // If the vehicle low on battery, search for a charger
             info!("{} has no charge left, searching for charging station", locked_handler.vehicle.get_name());
             locked_handler.vehicle.set_status(VehicleStatus::SearchingForCharger);
             task::spawn(create_charger_request(handler.clone()));
+        } else if TripPlanner::should_search(&locked_handler.vehicle) { // Destination out of range, plan a stop on the way
+            info!("{} can't reach its destination, searching for a charging stop on the way", locked_handler.vehicle.get_name());
+            locked_handler.vehicle.set_status(VehicleStatus::SearchingForCharger);
+            task::spawn(create_charger_request(handler.clone()));
         }
 
-        if locked_handler.vehicle.get_deadline().ticks_remaining <= 0 {
-            if locked_handler.vehicle.battery().get_soc() < locked_handler.vehicle.get_deadline().target_soc {
+        let deadline = locked_handler.vehicle.get_deadline();
+        let soc = locked_handler.vehicle.battery().get_soc();
+        let timestamp = locked_handler.current_timestamp;
+        if deadline.ticks_remaining <= 0 {
+            let missed_by = (deadline.target_soc - soc).max(0.0);
+            if missed_by > 0.0 {
                 warn!("{} failed the deadline!", locked_handler.vehicle.get_name());
             }
+            let result = ChartEntry::new(locked_handler.vehicle.get_name(), (missed_by * 100.0).ceil() as isize, timestamp);
+            locked_handler
+                .client
+                .publish(VEHICLE_DEADLINE_TOPIC, QoS::ExactlyOnce, false, result.to_string())
+                .await
+                .unwrap();
             locked_handler.vehicle.set_deadline(VehicleDeadline { ticks_remaining: 12 * 24, target_soc: 0.8 });
-        } else if locked_handler.vehicle.get_deadline().ticks_remaining <= 60 { // deadline soon, need charge
-            if locked_handler.vehicle.battery().get_soc() >= locked_handler.vehicle.get_deadline().target_soc {
-                locked_handler.vehicle.set_status(VehicleStatus::Parked);
-            } else {
-                info!("{} is approaching the deadline, searching for charging station", locked_handler.vehicle.get_name());
-                locked_handler.vehicle.set_status(VehicleStatus::SearchingForCharger);
-                task::spawn(create_charger_request(handler.clone()));
-            }
+            locked_handler.planner.charging_for_deadline = false;
+        } else if locked_handler.planner.should_charge_now(locked_handler.vehicle.battery_non_mut(), deadline, timestamp) {
+            info!("{} plans to charge now for its deadline, searching for charging station", locked_handler.vehicle.get_name());
+            locked_handler.planner.charging_for_deadline = true;
+            locked_handler.vehicle.set_status(VehicleStatus::SearchingForCharger);
+            task::spawn(create_charger_request(handler.clone()));
+        } else if deadline.ticks_remaining <= 60 && soc >= deadline.target_soc { // deadline soon, wait with enough charge
+            locked_handler.vehicle.set_status(VehicleStatus::Parked);
         } else if locked_handler.vehicle.get_location() == locked_handler.vehicle.get_destination() {
             let seed = locked_handler.vehicle.get_seed();
-            let mut rng = StdRng::seed_from_u64(seed);
-            match rng.random_range(0..11) { // Average parking time is 3 hours (made up)
-                0 => {
-                    locked_handler.vehicle.set_status(VehicleStatus::Random); // Generate new destination
-                    locked_handler.vehicle.set_destination(generate_rnd_pos(seed));
+            let location = locked_handler.vehicle.get_location();
+            match locked_handler.schedule.next_trip(location, timestamp, seed) {
+                Some(destination) => {
+                    locked_handler.vehicle.set_status(VehicleStatus::Random); // Drive to the next activity
+                    locked_handler.vehicle.set_destination(destination);
                 },
-                _ => locked_handler.vehicle.set_status(VehicleStatus::Parked), // Usually the car is parked after reaching its destination
+                None => locked_handler.vehicle.set_status(VehicleStatus::Parked), // Usually the car is parked after reaching its destination
             }
         } else { // continue after it parked for deadline
             locked_handler.vehicle.set_status(VehicleStatus::Random);
@@ -106,7 +153,15 @@ pub async fn process_tick(handler: SharedVehicle) { // TODO: rework this functio
         if locked_handler.vehicle.get_location().is_near(locked_handler.vehicle.get_next_stop()) {
             task::spawn(at_charger(handler.clone()));
         } else {
-            trace!("{} is driving to the charger", locked_handler.vehicle.get_name());
+            // Cancel the reservation if we can't reach the charger before it expires
+            let eta_ticks = locked_handler.vehicle.get_eta_ticks();
+            if locked_handler.current_tick + eta_ticks > locked_handler.reserved_until {
+                info!("{} won't reach the charger in time", locked_handler.vehicle.get_name());
+                task::spawn(cancel_reservation(handler.clone()));
+            } else {
+                trace!("{} is driving to the charger", locked_handler.vehicle.get_name());
+                send_eta(&mut locked_handler, eta_ticks).await;
+            }
         }
     }
 }
@@ -127,6 +182,8 @@ async fn at_charger(handler: SharedVehicle) {
         l_handler.vehicle.set_status(VehicleStatus::Charging);
     } else if l_handler.vehicle.get_status().eq(&VehicleStatus::Charging) { // Vehicle is in the process of charging
         task::spawn(create_get(handler.clone()));
+    } else if l_handler.vehicle.get_status().eq(&VehicleStatus::Parked) { // Vehicle is plugged in after charging
+        task::spawn(offer_v2g(handler.clone()));
     } else {
         warn!("drive_to_charger: you should not be here");
     }
@@ -143,6 +200,10 @@ pub async fn commerce_tick(handler: SharedVehicle) {
     if l_handler.target_charger.is_some() || l_handler.charge_offers.is_empty() {
         return;
     }
+    if l_handler.is_managed() {
+        trace!("{} waits for its fleet manager to choose an offer", l_handler.vehicle.get_name());
+        return;
+    }
     trace!("{} has received charge offers, accepting the best one", l_handler.vehicle.get_name());
     task::spawn(accept_offer(handler.clone()));
 }
@@ -159,7 +220,12 @@ pub async fn publish_vehicle(handler: SharedVehicle) {
     let mut vehicle_payload = json!(handler.vehicle);
     vehicle_payload["speed_kph"] = json!(handler.vehicle.get_speed());
     vehicle_payload["soc"] = json!((handler.vehicle.battery().get_soc_percentage()) as u32);
+    vehicle_payload["soh"] = json!(handler.vehicle.battery().get_state_of_health() * 100.0);
     vehicle_payload["deadline"] = json!(handler.vehicle.get_deadline().ticks_remaining);
+    vehicle_payload["v2g_earnings"] = json!(handler.v2g_earnings);
+    vehicle_payload["negotiated_savings"] = json!(handler.negotiator.savings);
+    vehicle_payload["wallet"] = json!(handler.wallet);
+    vehicle_payload["schedule"] = json!(handler.schedule);
 
     let client = &mut handler.client;
     client.publish(
@@ -243,6 +309,34 @@ pub async fn worldmap_event_handler(handler: SharedVehicle, payload: Bytes) {
     }
 }
 
+/// # Description
+/// The `weather_handler` function processes the weather reports of the turbines.<br>
+/// The vehicle takes the air temperature of the closest reporting station, which changes as it drives.<br>
+/// It is called when a message is received on the `WEATHER_TOPIC` topic.<br>
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler, which contains the vehicle instance.
+/// - `payload`: The incoming payload containing the weather report.
+pub async fn weather_handler(handler: SharedVehicle, payload: Bytes) {
+    let report = match WeatherReport::from_bytes(payload) {
+        Ok(report) => report,
+        Err(e) => {
+            warn!("Invalid weather report: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    let location = handler.vehicle.get_location();
+    let closer = handler.weather_station.as_ref().is_none_or(|station| {
+        station.station == report.station || location.distance_to(report.position) < location.distance_to(station.position)
+    });
+    if closer {
+        trace!("{} has {} °C from {}", handler.vehicle.get_name(), report.air_temperature, report.station);
+        handler.vehicle.set_air_temperature(report.air_temperature);
+        handler.weather_station = Some(report);
+    }
+}
+
 /// # Description
 /// The `scale_handler` function processes incoming scale configuration messages for the vehicle.<br>
 /// It updates the vehicle's consumption scale based on the received payload.<br>
@@ -275,6 +369,8 @@ pub async fn algorithm_handler(handler: SharedVehicle, payload: Bytes) {
         1 => VehicleAlgorithm::Random,
         2 => VehicleAlgorithm::Closest,
         3 => VehicleAlgorithm::Cheapest,
+        4 => VehicleAlgorithm::Fastest,
+        5 => VehicleAlgorithm::Scored,
         _ => {
             warn!("Unknown algorithm number: {}, defaulting to Best", algo_num);
             VehicleAlgorithm::Best
@@ -0,0 +1,159 @@
+use bytes::Bytes;
+use powercable::{charger::ChargeOffer, routing::road_distance, tickgen::TICK_AS_HOUR};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::{planner::ticks_to_charge, vehicle::Vehicle, SharedVehicle};
+
+/// State of charge the vehicle wants to have left when it arrives at its destination or the next charger
+const ARRIVAL_RESERVE_SOC: f64 = 0.1;
+/// A vehicle on a trip beyond its range starts looking for a charger once it has less range than this in km
+const EN_ROUTE_SEARCH_RANGE: f64 = 100.0;
+/// Above this state of charge fast charging gets slow, so an en-route stop charges at most this far
+const MAX_EN_ROUTE_SOC: f64 = 0.8;
+
+/// # Description
+/// What a vehicle minimises when it chooses a charging stop on the way to a distant destination.
+///
+/// # Variants
+/// - `Time`: The total time of the rest of the trip, driving plus charging.
+/// - `Cost`: The price of the energy charged at the stop and the wear of the battery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TripObjective {
+    #[default]
+    Time,
+    Cost,
+}
+
+/// # Description
+/// The `TripPlanner` plans the charging stops of a vehicle whose destination is beyond its range.<br>
+/// It is configured on the `CONFIG_VEHICLE_TRIP` topic.
+///
+/// # Fields
+/// - `objective`: What the vehicle minimises when choosing a stop.
+/// - `stop_target_soc`: The state of charge to charge to at the current en-route stop, `None` if the vehicle isn't on one.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct TripPlanner {
+    pub objective: TripObjective,
+    #[serde(skip)]
+    pub stop_target_soc: Option<f64>,
+}
+
+/// # Returns
+/// The energy in kWh the vehicle needs to drive the distance at its planned speed.
+fn energy_for(vehicle: &Vehicle, distance: f64) -> f64 {
+    distance * vehicle.get_planned_consumption() / 100.0
+}
+
+impl TripPlanner {
+    /// # Returns
+    /// Whether the vehicle can't reach its destination with the reserve left and has to charge on the way.
+    pub fn needs_stop(vehicle: &Vehicle) -> bool {
+        let battery = vehicle.battery_non_mut();
+        let destination = vehicle.get_destination();
+        energy_for(vehicle, vehicle.distance_to(destination)) + ARRIVAL_RESERVE_SOC * battery.get_max_capacity()
+            > battery.get_level()
+    }
+
+    /// # Description
+    /// Decides whether a vehicle on a trip beyond its range should look for a charging stop now.<br>
+    /// It doesn't charge right at the start, but waits until its range gets short, so the stop is along the way.
+    ///
+    /// # Returns
+    /// `true` if the vehicle should search for a charger.
+    pub fn should_search(vehicle: &Vehicle) -> bool {
+        Self::needs_stop(vehicle) && vehicle.get_range() <= EN_ROUTE_SEARCH_RANGE
+    }
+}
+
+/// # Description
+/// A charging stop on the way to the destination, rated by the time and cost of the rest of the trip.
+#[derive(Debug, Clone, Copy)]
+struct Stop {
+    hours: f64,
+    cost: f64,
+    target_soc: f64,
+    reachable_in_time: bool,
+}
+
+impl Stop {
+    fn of(offer: &ChargeOffer, vehicle: &Vehicle) -> Self {
+        let speed = vehicle.planned_speed().max(1) as f64;
+        let to_charger = vehicle.distance_to(offer.charger_position);
+        let to_destination = road_distance(offer.charger_position, vehicle.get_destination());
+
+        // The battery when arriving at the charger
+        let mut battery = vehicle.battery_non_mut().clone();
+        battery.remove_charge(energy_for(vehicle, to_charger));
+        let capacity = battery.get_max_capacity();
+
+        // Charge just enough for the rest of the trip, the remainder needs another stop
+        let needed_soc = (energy_for(vehicle, to_destination) / capacity + ARRIVAL_RESERVE_SOC).max(battery.get_soc());
+        let target_soc = needed_soc.min(MAX_EN_ROUTE_SOC);
+        let charged = ((target_soc - battery.get_soc()) * capacity).max(0.0);
+        let charge_hours = ticks_to_charge(&battery, target_soc, Some(offer.charge_power)) as f64 * TICK_AS_HOUR;
+        let further_hours = (needed_soc - target_soc) * capacity / offer.charge_power.max(1.0);
+
+        Stop {
+            hours: (to_charger + to_destination) / speed + offer.estimated_wait_ticks as f64 * TICK_AS_HOUR
+                + charge_hours + further_hours,
+            cost: (offer.charge_price + battery.wear_cost_per_kwh(offer.charge_power)) * charged,
+            target_soc,
+            reachable_in_time: (to_charger / speed / TICK_AS_HOUR).ceil() as u64 <= offer.terms.reservation_ticks,
+        }
+    }
+}
+
+/// # Description
+/// Selects the charging stop on the way to a distant destination that minimises the objective.<br>
+/// For every offer the time of the rest of the trip is estimated: driving via the charger, waiting,
+/// charging along the battery curve just as far as needed, and charging the remainder at a later stop.
+/// Chargers the vehicle can't reach before the reservation expires are only taken if there is no other.
+///
+/// # Arguments
+/// - `offers`: A slice of `ChargeOffer` instances representing the available charge offers.
+/// - `vehicle`: The vehicle for which the charge offer is being selected.
+/// - `objective`: Whether to minimise the trip time or the charging cost.
+///
+/// # Returns
+/// The selected offer and the state of charge to charge to, or `None` if no offers are available.
+pub fn get_en_route_offer(offers: &[ChargeOffer], vehicle: &Vehicle, objective: TripObjective) -> Option<(ChargeOffer, f64)> {
+    let rated: Vec<(&ChargeOffer, Stop)> = offers.iter()
+        .map(|offer| (offer, Stop::of(offer, vehicle)))
+        .inspect(|(offer, stop)| debug!("En-route stop at {}: {:?}", offer.charger_name, stop))
+        .collect();
+    let value = |stop: &Stop| match objective {
+        TripObjective::Time => stop.hours,
+        TripObjective::Cost => stop.cost,
+    };
+
+    let res = rated.iter()
+        .filter(|(_, stop)| stop.reachable_in_time)
+        .min_by(|a, b| value(&a.1).total_cmp(&value(&b.1)))
+        .or_else(|| rated.iter().min_by(|a, b| value(&a.1).total_cmp(&value(&b.1))))
+        .map(|(offer, stop)| ((*offer).clone(), stop.target_soc));
+    debug!("Best en-route offer: {:?}", res);
+    res
+}
+
+/// # Description
+/// The `trip_handler` function processes incoming trip planning configurations.<br>
+/// It is called when a message is received on the `CONFIG_VEHICLE_TRIP` topic.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler, which contains the vehicle instance.
+/// - `payload`: The incoming payload containing the trip planning configuration in JSON format.
+pub async fn trip_handler(handler: SharedVehicle, payload: Bytes) {
+    let config: TripPlanner = match serde_json::from_slice(&payload) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid trip configuration: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    debug!("{} trip objective set to: {:?}", handler.vehicle.get_name(), config.objective);
+    handler.trip.objective = config.objective;
+}
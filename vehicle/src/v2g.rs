@@ -0,0 +1,148 @@
+use bytes::Bytes;
+use powercable::{
+    charger::{Get, V2gDischarge, V2gOffer},
+    tickgen::TICK_AS_HOUR,
+    CHARGER_CHARGING_RELEASE, CHARGER_V2G_OFFER,
+};
+use rumqttc::QoS;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use crate::{battery::Battery, vehicle::VehicleStatus, SharedVehicle};
+
+/// # Description
+/// The `V2gPolicy` defines whether a vehicle feeds energy of its battery into the grid, while it is parked at a charger.<br>
+/// It can be configured on the `CONFIG_VEHICLE_V2G` topic for all vehicles or on `CONFIG_VEHICLE_V2G/<vehicle name>` for one.
+///
+/// # Fields
+/// - `enabled`: Whether the vehicle stays plugged in after charging and offers its energy.
+/// - `degradation_cost`: The wear of the battery per kWh fed into the grid in €, the vehicle never sells below it.
+///   If `None`, the wear is estimated by the state of health model of the battery.
+/// - `plugged_ticks`: How many ticks the vehicle stays plugged in after charging.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct V2gPolicy {
+    pub enabled: bool,
+    pub degradation_cost: Option<f64>,
+    pub plugged_ticks: u64,
+}
+
+impl Default for V2gPolicy {
+    fn default() -> Self {
+        V2gPolicy {
+            enabled: false,
+            degradation_cost: None,
+            plugged_ticks: 16,
+        }
+    }
+}
+
+impl V2gPolicy {
+    /// # Returns
+    /// The wear of the battery per kWh fed into the grid in €, the configured one or the estimate of the battery.
+    pub fn degradation_cost(&self, battery: &Battery) -> f64 {
+        self.degradation_cost.unwrap_or_else(|| battery.wear_cost_per_kwh(battery.get_max_charge_rate() as f64))
+    }
+}
+
+/// # Description
+/// Offers energy of the battery to the power market through the charger the vehicle is parked at.<br>
+/// The vehicle keeps the state of charge its deadline asks for and sells no cheaper than it paid for the energy,
+/// including the losses of charging and discharging and the wear of the battery.
+/// Once its parking time is over or it has nothing left to offer, it unplugs and continues to its destination.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+pub async fn offer_v2g(handler: SharedVehicle) {
+    let mut handler = handler.lock().await;
+    let Some(target) = handler.target_charger.clone() else {
+        return;
+    };
+
+    let protected_soc = handler.vehicle.get_deadline().target_soc;
+    let battery = handler.vehicle.battery_non_mut();
+    let max_per_tick = battery.get_max_charge_rate() as f64 * TICK_AS_HOUR;
+    let amount = battery.dischargeable_above(protected_soc).min(max_per_tick) as usize;
+
+    if handler.current_tick >= handler.plugged_in_until || !handler.v2g_policy.enabled || amount == 0 {
+        info!("{} unplugs from {} after earning {}€ with V2G", handler.vehicle.get_name(), target.charger_name, handler.v2g_earnings);
+        let release = Get::new(target.charger_name, handler.vehicle.get_name(), 0);
+        handler
+            .client
+            .publish(CHARGER_CHARGING_RELEASE, QoS::ExactlyOnce, false, release.to_bytes())
+            .await
+            .unwrap();
+
+        handler.vehicle.set_status(VehicleStatus::Random);
+        handler.target_charger = None;
+        let destination = handler.vehicle.get_destination();
+        handler.vehicle.set_next_stop(destination);
+        return;
+    }
+
+    let offer = V2gOffer {
+        charger_name: target.charger_name,
+        vehicle_name: handler.vehicle.get_name(),
+        amount,
+        min_price: target.charge_price / (battery.charge_efficiency() * battery.discharge_efficiency())
+            + handler.v2g_policy.degradation_cost(battery),
+    };
+    debug!("Offering V2G energy: {:?}", offer);
+    handler
+        .client
+        .publish(CHARGER_V2G_OFFER, QoS::ExactlyOnce, false, offer.to_bytes())
+        .await
+        .unwrap();
+}
+
+/// # Description
+/// Handles energy of the vehicle, that its charger sold on the power market.<br>
+/// The energy and its discharge losses are taken from the battery, the vehicle earns the price it is paid,
+/// the same money the charger puts on the receipt for the wallet. The wear of the battery is only logged.
+///
+/// # Arguments
+/// - `handler`: The shared vehicle handler containing the vehicle and its state.
+/// - `payload`: The payload of the message received on the `CHARGER_V2G_DISCHARGE` topic.
+pub async fn discharge_handler(handler: SharedVehicle, payload: Bytes) {
+    let discharge = V2gDischarge::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+    if discharge.vehicle_name != handler.vehicle.get_name() {
+        return;
+    }
+
+    let degradation_cost = handler.v2g_policy.degradation_cost(handler.vehicle.battery_non_mut());
+    let drawn = handler.vehicle.battery().discharge(discharge.amount as f64);
+    let earned = discharge.amount as f64 * discharge.price;
+    handler.v2g_earnings += earned;
+    info!(
+        "{} fed {} kWh into the grid through {} ({} kWh from the battery), earning {}€ for {}€ of battery wear",
+        discharge.vehicle_name, discharge.amount, discharge.charger_name, drawn, earned, discharge.amount as f64 * degradation_cost
+    );
+}
+
+/// # Description
+/// The `v2g_policy_handler` function processes incoming V2G configuration messages for the vehicle.<br>
+/// It is called when a message is received on the `CONFIG_VEHICLE_V2G` topic, or on the topic of this vehicle
+/// `CONFIG_VEHICLE_V2G/<vehicle name>`. A configuration for this vehicle takes precedence over the one for all vehicles.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler, which contains the vehicle instance.
+/// - `payload`: The incoming payload containing the V2G policy in JSON format.
+/// - `own`: Whether the configuration is meant for this vehicle only.
+pub async fn v2g_policy_handler(handler: SharedVehicle, payload: Bytes, own: bool) {
+    let policy: V2gPolicy = match serde_json::from_slice(&payload) {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Invalid V2G policy: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    if handler.has_own_v2g_policy && !own {
+        debug!("{} keeps its own V2G policy, ignoring {:?}", handler.vehicle.get_name(), policy);
+        return;
+    }
+    debug!("{} V2G policy set to: {:?}", handler.vehicle.get_name(), policy);
+    handler.v2g_policy = policy;
+    handler.has_own_v2g_policy = own;
+}
@@ -1,5 +1,5 @@
 use tracing::debug;
-use powercable::{tickgen::PHASE_AS_HOUR, Position};
+use powercable::{charger::ConnectorType, routing::{road_distance, road_network, RoadNetwork, Route}, tickgen::{PHASE_AS_HOUR, TICK_AS_HOUR}, Position};
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Serialize, Deserialize};
 
@@ -9,16 +9,29 @@ use crate::{battery::Battery, database::random_ev};
 const ROLLING_RESISTANCE: f64 = 0.0005;
 /// Aerodynamic drag coefficient is used to calculate consumption based on speed
 const AERODYNAMIC_DRAG: f64 = 0.00003;
+/// Air temperature in °C until weather reports arrive, neither heating nor cooling is needed
+const MILD_TEMPERATURE: f64 = 20.0;
+/// Below this air temperature in °C the cabin is heated
+const HEATING_BELOW: f64 = 18.0;
+/// Above this air temperature in °C the cabin is cooled
+const COOLING_ABOVE: f64 = 24.0;
+/// Heating power in kW per °C below `HEATING_BELOW`
+const HEATING_POWER: f64 = 0.15;
+/// Cooling power in kW per °C above `COOLING_ABOVE`
+const COOLING_POWER: f64 = 0.1;
+/// Mixed into the seed of a vehicle for the draw of its initial state of charge
+const SOC_SEED: u64 = 0x5eed_50c0;
 
 /// # Description
 /// The `VehicleStatus` enum represents the different states a vehicle can be in.
 /// 
 /// # Variants
-/// - `Random`: The vehicle is in a random state.
-/// - `Waiting`: The vehicle is waiting.
+/// - `Random`: The vehicle drives to its next destination.
+/// - `Parked`: The vehicle is parked and waits for its next trip.
 /// - `SearchingForCharger`: The vehicle is looking for a charger.
 /// - `Charging`: The vehicle is currently charging.
-/// - `Broken`: The vehicle is broken and cannot be used.
+/// - `Broken`: The vehicle had a mechanical failure and waits for the rescue service.
+/// - `Stranded`: The battery is empty or no charger is in range, the vehicle waits for one in range or the rescue service.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VehicleStatus {
     Random,
@@ -26,6 +39,7 @@ pub enum VehicleStatus {
     SearchingForCharger,
     Charging,
     Broken,
+    Stranded,
 }
 
 /// # Description
@@ -36,12 +50,16 @@ pub enum VehicleStatus {
 /// - `Random`: The vehicle will choose a charger randomly.
 /// - `Closest`: The vehicle will choose the closest charger.
 /// - `Cheapest`: The vehicle will choose the cheapest charger, based on price per kWh.
+/// - `Fastest`: The vehicle will choose the charger with the highest charging power.
+/// - `Scored`: The vehicle will choose the charger with the highest weighted score of price, detour, amount, power and waiting time.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VehicleAlgorithm {
     Best,
     Random,
     Closest,
     Cheapest,
+    Fastest,
+    Scored,
 }
 
 /// # Description
@@ -71,9 +89,14 @@ pub struct VehicleDeadline {
 /// - `scale`: A scale factor for the vehicle's consumption, default is 1.0.
 /// - `speed`: The speed of the vehicle in km/h.
 /// - `battery`: The battery of the vehicle, which contains information about its capacity, current charge level, and maximum charge rate.
+/// - `connectors`: The connector types the vehicle can be charged with.
+/// - `max_ac_power`: The power limit of the on-board charger in kW, for charging at AC ports.
 /// - `algorithm`: The algorithm used by the vehicle to determine its behavior when searching for a charger.
 /// - `deadline`: The deadline to which the vehicle must charge its battery.
 /// - `visible`: A flag indicating whether the vehicle is visible on the world map.
+/// - `fees_paid`: The sum of all cancellation and no-show fees the vehicle had to pay, in €.
+/// - `route`: The route to the next stop on the road network, if one is configured.
+/// - `air_temperature`: The air temperature in °C at the vehicle, reported by the closest weather station.
 /// - `seed`: To be used for randomness by this vehicle for deterministic but unique outcome.
 #[derive(Clone, Debug, Serialize)]
 pub struct Vehicle {
@@ -87,9 +110,15 @@ pub struct Vehicle {
     scale: f64,
     speed: usize,
     battery: Battery,
+    connectors: Vec<ConnectorType>,
+    max_ac_power: f64,
     algorithm: VehicleAlgorithm,
     deadline: VehicleDeadline,
     pub visible: bool,
+    fees_paid: f64,
+    #[serde(skip)]
+    route: Option<Route>,
+    air_temperature: f64,
     seed: u64,
 }
 
@@ -109,23 +138,34 @@ impl Vehicle {
         location: Position,
         seed: u64,
     ) -> Self {
-        let mut rng = StdRng::seed_from_u64(seed);
-        let (model, consumption, capacity, max_charge) = random_ev(seed);
-        let battery = Battery::new(capacity, rng.random_range(0.4..1.0), max_charge);
+        // The state of charge is drawn with its own seed, so it isn't tied to the draw of the model
+        let mut rng = StdRng::seed_from_u64(seed ^ SOC_SEED);
+        let model = random_ev(seed);
+        let battery = Battery::new(
+            model.usable_capacity,
+            rng.random_range(0.4..1.0),
+            model.max_dc_power as usize,
+            model.charge_curve.clone(),
+        );
         Vehicle {
             name,
-            model: model.to_owned(),
+            model: model.name.clone(),
             status: VehicleStatus::Parked,
             location,
             next_stop: location,
             destination: location,// Initially, the destination is the same as the location
-            consumption,
+            consumption: model.consumption,
             scale: 1.0,
             speed: 0,
             battery,
+            connectors: model.connectors.clone(),
+            max_ac_power: model.max_ac_power,
             algorithm: VehicleAlgorithm::Best,
             deadline: VehicleDeadline { ticks_remaining: 12 * 24, target_soc: 0.8 },
             visible: true,
+            fees_paid: 0.0,
+            route: None,
+            air_temperature: MILD_TEMPERATURE,
             seed: seed,
         }
     }
@@ -169,6 +209,17 @@ impl Vehicle {
         self.next_stop = next_stop;
     }
 
+    /// # Description
+    /// Moves the vehicle to a position without driving, when it is towed.
+    ///
+    /// # Arguments
+    /// - `position`: The position the vehicle is dropped off at.
+    pub fn tow_to(&mut self, position: Position) {
+        self.location = position;
+        self.next_stop = position;
+        self.route = None;
+    }
+
     /// # Returns
     /// The next-stop-destination of the vehicle as a `Position`.
     pub fn get_next_stop(&self) -> Position {
@@ -201,23 +252,57 @@ impl Vehicle {
     /// # Returns
     /// The speed efficiency factor of the vehicle, which is a function of its speed.
     /// The higher the speed, the more energy is consumed due to rolling resistance and aerodynamic drag.
-    fn speed_efficiency_factor(&self) -> f64 {
-        1.0 + ROLLING_RESISTANCE * self.speed as f64 + AERODYNAMIC_DRAG * (self.speed as f64).powi(2)
+    fn speed_efficiency_factor(speed: usize) -> f64 {
+        1.0 + ROLLING_RESISTANCE * speed as f64 + AERODYNAMIC_DRAG * (speed as f64).powi(2)
     }
 
     /// # Returns
-    /// The current consumption of the vehicle in kWh/100km, adjusted for the vehicle's speed.
+    /// The power in kW the heating or air conditioning needs at the current air temperature.
+    pub fn hvac_power(&self) -> f64 {
+        (HEATING_BELOW - self.air_temperature).max(0.0) * HEATING_POWER
+            + (self.air_temperature - COOLING_ABOVE).max(0.0) * COOLING_POWER
+    }
+
+    /// # Returns
+    /// The consumption of the vehicle in kWh/100km at the given speed, including the heating or air conditioning.
+    /// The slower the vehicle, the longer the heating runs per 100 km.
+    fn consumption_at(&self, speed: usize) -> f64 {
+        let hvac = if speed > 0 { self.hvac_power() * 100.0 / speed as f64 } else { 0.0 };// kW * h/100km = kWh/100km
+        self.get_consumption() * Self::speed_efficiency_factor(speed) + hvac
+    }
+
+    /// # Returns
+    /// The current consumption of the vehicle in kWh/100km, adjusted for the vehicle's speed and the weather.
     pub fn get_current_consumption(&self) -> f64 {
-        self.get_consumption() * self.speed_efficiency_factor()
+        self.consumption_at(self.speed)
     }
 
     /// # Description
-    /// Returns the range of the vehicle in kilometers based on its battery capacity and consumption.
-    /// 
+    /// Returns the range of the vehicle in kilometers when driving at its planned speed,
+    /// including the additional consumption at that speed and the discharge losses of the battery.
+    ///
     /// # Returns
     /// The range of the vehicle in kilometers.
     pub fn get_range(&self) -> f64 {
-        self.battery.get_level() / (self.get_consumption() / 100.0)// kWh / kWh/km = km
+        self.battery.get_level() * self.battery.discharge_efficiency() / (self.get_planned_consumption() / 100.0)// kWh / kWh/km = km
+    }
+
+    /// # Returns
+    /// The consumption of the vehicle in kWh/100km when driving at its planned speed in the current weather.
+    pub fn get_planned_consumption(&self) -> f64 {
+        self.consumption_at(self.planned_speed())
+    }
+
+    /// # Sets
+    /// The air temperature in °C at the vehicle.
+    pub fn set_air_temperature(&mut self, air_temperature: f64) {
+        self.air_temperature = air_temperature;
+    }
+
+    /// # Returns
+    /// Whether the vehicle can reach the position with the energy left in its battery.
+    pub fn can_reach(&self, position: Position) -> bool {
+        self.distance_to(position) <= self.get_range()
     }
 
     /// # Sets
@@ -254,9 +339,21 @@ impl Vehicle {
     }
 
     /// # Returns
-    /// The distance from the vehicle's current location to another position.
+    /// The connector types the vehicle can be charged with.
+    pub fn get_connectors(&self) -> &[ConnectorType] {
+        &self.connectors
+    }
+
+    /// # Returns
+    /// The power limit of the on-board charger in kW, for charging at AC ports.
+    pub fn get_max_ac_power(&self) -> f64 {
+        self.max_ac_power
+    }
+
+    /// # Returns
+    /// The distance from the vehicle's current location to another position, on the road network if one is configured.
     pub fn distance_to(&self, other:Position) -> f64 {
-        self.location.distance_to(other)
+        road_distance(self.location, other)
     }
 
     /// # Sets
@@ -283,6 +380,25 @@ impl Vehicle {
         self.deadline
     }
 
+    /// # Description
+    /// Records a cancellation or no-show fee the vehicle has to pay.
+    ///
+    /// # Arguments
+    /// - `fee`: The fee in €.
+    pub fn add_fee(&mut self, fee: f64) {
+        self.fees_paid += fee;
+    }
+
+    /// # Returns
+    /// The speed in km/h the vehicle drives with at its current state of charge.
+    pub fn planned_speed(&self) -> usize {
+        match self.battery.get_soc() {
+            0.0..0.2 => 30,
+            0.2..0.5 => 60,
+            _ => 90,
+        }
+    }
+
     /// # Returns
     /// Current seed for deterministic randomness. Updates automatically.
     pub fn get_seed(&mut self) -> u64 {
@@ -294,19 +410,22 @@ impl Vehicle {
     /// # Description
     pub fn drive(&mut self) {
         self.deadline.ticks_remaining -= 1;
+        self.battery.update_temperature(self.air_temperature, self.speed > 0);
+        self.battery.age(PHASE_AS_HOUR);
 
         // check status and set speed
         if self.status == VehicleStatus::Parked
         || self.status == VehicleStatus::Charging
-        || self.status == VehicleStatus::Broken {
+        || self.status == VehicleStatus::Broken
+        || self.status == VehicleStatus::Stranded
+        || self.location == self.next_stop {
             self.speed = 0;
             return;
+        } else if let Some(network) = road_network() {
+            self.drive_on_roads(network);
+            return;
         } else {
-            match self.battery.get_soc() {
-                0.0..0.2 => self.speed = 30,
-                0.2..0.5 => self.speed = 60,
-                _ => self.speed = 90,
-            }
+            self.speed = self.planned_speed();
         }
 
         // compute energy demand
@@ -315,11 +434,17 @@ impl Vehicle {
         let used_energy = self.battery.remove_charge(wanted_energy);
         debug!("Wanded distance: {}, wanted energy: {}, used energy: {}", 
             wanted_distance, wanted_energy, used_energy);
+        if used_energy <= 0.0 {
+            debug!("{} ran out of energy", self.name);
+            self.speed = 0;
+            self.status = VehicleStatus::Stranded;
+            return;
+        }
 
         let charge_factor = wanted_energy / used_energy;
 
         // drive
-        let total_distance = self.distance_to(self.get_next_stop()) * charge_factor;
+        let total_distance = self.location.distance_to(self.get_next_stop()) * charge_factor;
         debug!("Total distance: {}", total_distance);
         if total_distance > 0.0 {
             let step_ratio = wanted_distance/ total_distance;
@@ -332,4 +457,83 @@ impl Vehicle {
             }
         }
     }
-}
\ No newline at end of file
+
+    /// # Description
+    /// Estimates when the vehicle arrives at its next stop, at the speed it drives with at its state of charge.<br>
+    /// On the road network it follows the route `drive` planned, at the speed of each road.
+    ///
+    /// # Returns
+    /// The ticks until the vehicle arrives, 0 if it is there.
+    pub fn get_eta_ticks(&self) -> u64 {
+        let hours = match &self.route {
+            Some(route) if route.destination() == self.next_stop => route.hours_left(self.location, |speed| self.road_speed(speed)),
+            _ => self.distance_to(self.next_stop) / self.planned_speed() as f64,
+        };
+        (hours / TICK_AS_HOUR).ceil() as u64
+    }
+
+    /// # Returns
+    /// The speed in km/h on a road with the given speed, the vehicle drives slower on a low state of charge.
+    fn road_speed(&self, road_speed: f64) -> f64 {
+        match self.planned_speed() {
+            90 => road_speed,
+            planned => road_speed.min(planned as f64),
+        }
+    }
+
+    /// # Description
+    /// Drives along the route to the next stop for one phase, at the speed of each road.<br>
+    /// The route is planned when the next stop changes, in a straight line if the road network doesn't connect them.
+    ///
+    /// # Arguments
+    /// - `network`: The road network.
+    fn drive_on_roads(&mut self, network: &RoadNetwork) {
+        if self.route.as_ref().is_none_or(|route| route.destination() != self.next_stop) {
+            self.route = Some(network.route(self.location, self.next_stop)
+                .unwrap_or_else(|| Route::straight(self.location, self.next_stop, self.planned_speed() as f64)));
+        }
+
+        let mut hours_left = PHASE_AS_HOUR;
+        while hours_left > 0.0 {
+            let Some((target, road_speed)) = self.route.as_ref().and_then(|route| route.next_leg()) else {
+                break;
+            };
+            let speed = self.road_speed(road_speed);
+            self.speed = speed as usize;
+            let leg_distance = self.location.distance_to(target);
+            let wanted_distance = leg_distance.min(speed * hours_left);// km/h * h = km
+            let wanted_energy = (self.get_current_consumption() / 100.0) * wanted_distance;
+
+            // the battery may run empty on the way
+            let used_energy = self.battery.remove_charge(wanted_energy);
+            if used_energy <= 0.0 && wanted_energy > 0.0 {
+                debug!("{} ran out of energy", self.name);
+                self.speed = 0;
+                self.status = VehicleStatus::Stranded;
+                return;
+            }
+            let share = if self.battery.get_level() > 0.0 {
+                1.0
+            } else {
+                (used_energy / wanted_energy).min(1.0)
+            };
+            let distance = wanted_distance * share;
+
+            if distance >= leg_distance {
+                self.location = target;
+                if let Some(route) = self.route.as_mut() {
+                    route.advance();
+                }
+            } else {
+                let step_ratio = distance / leg_distance;
+                self.location.latitude += step_ratio * (target.latitude - self.location.latitude);
+                self.location.longitude += step_ratio * (target.longitude - self.location.longitude);
+            }
+            hours_left -= distance / speed;
+            if share < 1.0 {
+                break;
+            }
+        }
+        debug!("{} drove on roads to {:?}", self.name, self.location);
+    }
+}
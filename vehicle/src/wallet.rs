@@ -0,0 +1,195 @@
+use bytes::Bytes;
+use powercable::charger::ChargeReceipt;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::SharedVehicle;
+
+/// Below this state of charge the driver pays any price to get charged
+const URGENT_SOC: f64 = 0.1;
+/// Lowest and highest balance a wallet starts with, in €
+const MIN_BALANCE: f64 = 50.0;
+const MAX_BALANCE: f64 = 250.0;
+/// Lowest and highest price per kWh drivers are willing to pay, in €
+const MIN_PRICE_LIMIT: f64 = 0.6;
+const MAX_PRICE_LIMIT: f64 = 1.2;
+
+/// # Description
+/// The `Wallet` holds the money of the driver and how much they are willing to pay for charging.<br>
+/// Both are sent with every charge request, so chargers that are too expensive don't answer.
+/// Every charging session is paid from the balance once the charger sends its receipt, fees are paid right away.
+///
+/// # Fields
+/// - `balance`: The money in the wallet in €.
+/// - `price_limit`: The highest price per kWh the driver is willing to pay.
+/// - `spent`: The money paid for charging and fees since the start, in €.
+/// - `sessions`: The number of charging sessions paid since the start.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Wallet {
+    pub balance: f64,
+    pub price_limit: f64,
+    pub spent: f64,
+    pub sessions: u32,
+}
+
+impl Wallet {
+    /// # Description
+    /// Creates the wallet of a vehicle, with a balance and price limit depending on the seed.
+    ///
+    /// # Arguments
+    /// - `seed`: The seed of the vehicle.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Wallet {
+            balance: rng.random_range(MIN_BALANCE..MAX_BALANCE),
+            price_limit: rng.random_range(MIN_PRICE_LIMIT..MAX_PRICE_LIMIT),
+            spent: 0.0,
+            sessions: 0,
+        }
+    }
+
+    /// # Description
+    /// Pays a charging session from the balance, which can go below zero if the driver paid any price.
+    ///
+    /// # Arguments
+    /// - `cost`: The cost of the session in €.
+    pub fn pay(&mut self, cost: f64) {
+        self.balance -= cost;
+        self.spent += cost;
+        self.sessions += 1;
+    }
+
+    /// # Description
+    /// Pays a cancellation or no-show fee from the balance, which can go below zero.
+    ///
+    /// # Arguments
+    /// - `fee`: The fee in €.
+    pub fn pay_fee(&mut self, fee: f64) {
+        self.balance -= fee;
+        self.spent += fee;
+    }
+
+    /// # Description
+    /// Puts the money earned by feeding energy into the grid into the wallet.
+    ///
+    /// # Arguments
+    /// - `earned`: The money in €.
+    pub fn earn(&mut self, earned: f64) {
+        self.balance += earned;
+    }
+
+    /// # Description
+    /// Returns the highest price per kWh the driver pays now.<br>
+    /// With a nearly empty battery there is no choice, so any price is paid.
+    ///
+    /// # Arguments
+    /// - `soc`: The state of charge of the battery (0.0 to 1.0).
+    pub fn max_price(&self, soc: f64) -> f64 {
+        if soc < URGENT_SOC {
+            f64::MAX
+        } else {
+            self.price_limit
+        }
+    }
+
+    /// # Description
+    /// Returns the most the driver can spend on a charge in €, which is the balance of the wallet.<br>
+    /// With a nearly empty battery the balance doesn't limit the charge, like the price in `max_price`.
+    ///
+    /// # Arguments
+    /// - `soc`: The state of charge of the battery (0.0 to 1.0).
+    pub fn budget(&self, soc: f64) -> f64 {
+        if soc < URGENT_SOC {
+            f64::MAX
+        } else {
+            self.balance.max(0.0)
+        }
+    }
+}
+
+/// # Description
+/// A change of the wallet, only the given fields are changed.
+///
+/// # Fields
+/// - `balance`: The new balance in €.
+/// - `price_limit`: The new highest price per kWh the driver is willing to pay.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WalletConfig {
+    pub balance: Option<f64>,
+    pub price_limit: Option<f64>,
+}
+
+/// # Description
+/// The `wallet_handler` function processes incoming wallet configurations.<br>
+/// It is called when a message is received on the `CONFIG_VEHICLE_WALLET` topic, or on the topic of this vehicle
+/// `CONFIG_VEHICLE_WALLET/<vehicle name>`. A wallet for this vehicle takes precedence over the one for all vehicles.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler, which contains the vehicle instance.
+/// - `payload`: The incoming payload containing the wallet configuration in JSON format.
+/// - `own`: Whether the configuration is meant for this vehicle only.
+pub async fn wallet_handler(handler: SharedVehicle, payload: Bytes, own: bool) {
+    let config: WalletConfig = match serde_json::from_slice(&payload) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid wallet configuration: {}", e);
+            return;
+        }
+    };
+    let mut handler = handler.lock().await;
+    if handler.has_own_wallet && !own {
+        debug!("{} keeps its own wallet, ignoring {:?}", handler.vehicle.get_name(), config);
+        return;
+    }
+
+    if let Some(balance) = config.balance {
+        handler.wallet.balance = balance;
+    }
+    if let Some(price_limit) = config.price_limit {
+        handler.wallet.price_limit = price_limit;
+    }
+    handler.has_own_wallet = own;
+    debug!("{} wallet set to: {:?}", handler.vehicle.get_name(), handler.wallet);
+}
+
+/// # Description
+/// The `receipt_handler` function pays the receipt of a charging session of this vehicle from its wallet.<br>
+/// What the vehicle earned with V2G in the session goes into the wallet. A session without charging is not counted.<br>
+/// It is called when a message is received on the `CHARGER_RECEIPT` topic.
+///
+/// # Arguments
+/// - `handler`: A shared reference to the vehicle handler, which contains the vehicle instance.
+/// - `payload`: The incoming payload containing the receipt in JSON format.
+pub async fn receipt_handler(handler: SharedVehicle, payload: Bytes) {
+    let receipt = ChargeReceipt::from_bytes(payload).unwrap();
+    let mut handler = handler.lock().await;
+    if receipt.vehicle_name != handler.vehicle.get_name() {
+        return;
+    }
+
+    if receipt.energy > 0.0 {
+        handler.wallet.pay(receipt.cost);
+    }
+    handler.wallet.earn(receipt.earned);
+    info!(
+        "Paid {:.2}€ to {} for {} kWh and earned {:.2}€ for {} kWh, {:.2}€ left",
+        receipt.cost, receipt.charger_name, receipt.energy, receipt.earned, receipt.fed_in, handler.wallet.balance
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearly_empty_vehicle_pays_any_price_and_amount() {
+        let mut wallet = Wallet::new(1);
+        wallet.pay(wallet.balance + 10.0);
+        assert_eq!(wallet.budget(0.5), 0.0);
+        assert_eq!(wallet.max_price(0.5), wallet.price_limit);
+        assert_eq!(wallet.budget(0.05), f64::MAX);
+        assert_eq!(wallet.max_price(0.05), f64::MAX);
+    }
+}